@@ -0,0 +1,294 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use crate::{header, pad_to_word, Compression, DecompressError};
+
+// Offsets to child nodes are stored in 6 bits
+const MAX_NODE_OFFSET: usize = 63;
+
+enum Node {
+    Leaf(u8),
+    Internal(Box<Node>, Box<Node>),
+}
+
+/// Compresses using whichever of 4 or 8 bit symbols is smaller. The tree for 8 bit
+/// symbols can't always be encoded, in which case 4 bit symbols are used.
+pub(crate) fn compress(data: &[u8]) -> Vec<u8> {
+    let four_bit = compress_with_symbol_size(data, 4).expect("4 bit trees always fit");
+
+    match compress_with_symbol_size(data, 8) {
+        Some(eight_bit) if eight_bit.len() < four_bit.len() => eight_bit,
+        _ => four_bit,
+    }
+}
+
+fn symbols(data: &[u8], symbol_size: u8) -> Vec<u8> {
+    // The BIOS writes whole words, so the data gets padded to a word boundary
+    let padded = data
+        .iter()
+        .copied()
+        .chain(std::iter::repeat(0))
+        .take(data.len().next_multiple_of(4));
+
+    if symbol_size == 8 {
+        padded.collect()
+    } else {
+        padded.flat_map(|byte| [byte & 0xf, byte >> 4]).collect()
+    }
+}
+
+fn compress_with_symbol_size(data: &[u8], symbol_size: u8) -> Option<Vec<u8>> {
+    let symbols = symbols(data, symbol_size);
+
+    let tree = build_tree(&symbols, symbol_size);
+    let tree_table = layout_tree(&tree)?;
+
+    let mut codes = vec![None; 1 << symbol_size];
+    assign_codes(&tree, Code::default(), &mut codes);
+
+    let mut output = header(Compression::Huffman, symbol_size, data.len());
+    output.extend_from_slice(&tree_table);
+
+    let mut bits = BitWriter::default();
+    for &symbol in &symbols {
+        let code = codes[symbol as usize].expect("Every symbol should have a code");
+        bits.write(code);
+    }
+
+    output.extend(bits.finish());
+    pad_to_word(&mut output);
+
+    Some(output)
+}
+
+fn build_tree(symbols: &[u8], symbol_size: u8) -> Node {
+    let mut frequencies = vec![0usize; 1 << symbol_size];
+    for &symbol in symbols {
+        frequencies[symbol as usize] += 1;
+    }
+
+    let mut nodes: Vec<Option<Node>> = vec![];
+    let mut heap = BinaryHeap::new();
+
+    for (symbol, &frequency) in frequencies.iter().enumerate() {
+        if frequency > 0 {
+            heap.push(Reverse((frequency, nodes.len())));
+            nodes.push(Some(Node::Leaf(symbol as u8)));
+        }
+    }
+
+    // The root must be an internal node, so make sure there are at least 2 leaves
+    for (symbol, &frequency) in frequencies.iter().enumerate().take(2) {
+        if heap.len() < 2 && frequency == 0 {
+            heap.push(Reverse((0, nodes.len())));
+            nodes.push(Some(Node::Leaf(symbol as u8)));
+        }
+    }
+
+    while heap.len() > 1 {
+        let Reverse((left_frequency, left)) = heap.pop().unwrap();
+        let Reverse((right_frequency, right)) = heap.pop().unwrap();
+
+        let node = Node::Internal(
+            Box::new(nodes[left].take().unwrap()),
+            Box::new(nodes[right].take().unwrap()),
+        );
+
+        heap.push(Reverse((left_frequency + right_frequency, nodes.len())));
+        nodes.push(Some(node));
+    }
+
+    let Reverse((_, root)) = heap.pop().unwrap();
+    nodes[root].take().unwrap()
+}
+
+/// Lays out the tree in breadth first order as the BIOS expects. Returns the tree
+/// table starting with the size byte, padded such that the bitstream will be word
+/// aligned, or `None` if a node is too far from its children.
+///
+/// Positions are relative to the size byte. The root is at position 1, and the
+/// children of the node at position `p` with offset `o` are at `(p & !1) + 2 * o + 2`
+/// and the position after.
+fn layout_tree(root: &Node) -> Option<Vec<u8>> {
+    let mut table = vec![0u8; 2];
+    let mut queue = std::collections::VecDeque::new();
+    queue.push_back((root, 1usize));
+
+    while let Some((node, position)) = queue.pop_front() {
+        let Node::Internal(left, right) = node else {
+            unreachable!("Only internal nodes are queued");
+        };
+
+        let children_position = table.len();
+        let offset = (children_position - (position & !1) - 2) / 2;
+        if offset > MAX_NODE_OFFSET {
+            return None;
+        }
+
+        let mut node_value = offset as u8;
+
+        for (i, child) in [left, right].into_iter().enumerate() {
+            match child.as_ref() {
+                Node::Leaf(symbol) => {
+                    node_value |= 0x80 >> i;
+                    table.push(*symbol);
+                }
+                Node::Internal(..) => {
+                    queue.push_back((child.as_ref(), children_position + i));
+                    table.push(0);
+                }
+            }
+        }
+
+        table[position] = node_value;
+    }
+
+    // The size byte plus the table needs to be a multiple of 4 bytes
+    table.resize(table.len().next_multiple_of(4), 0);
+    table[0] = (table.len() / 2 - 1) as u8;
+
+    Some(table)
+}
+
+#[derive(Clone, Copy, Default)]
+struct Code {
+    bits: u32,
+    length: u32,
+}
+
+fn assign_codes(node: &Node, code: Code, codes: &mut [Option<Code>]) {
+    match node {
+        Node::Leaf(symbol) => codes[*symbol as usize] = Some(code),
+        Node::Internal(left, right) => {
+            for (bit, child) in [left, right].into_iter().enumerate() {
+                assign_codes(
+                    child,
+                    Code {
+                        bits: (code.bits << 1) | bit as u32,
+                        length: code.length + 1,
+                    },
+                    codes,
+                );
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct BitWriter {
+    words: Vec<u32>,
+    current: u32,
+    bits_in_current: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: Code) {
+        for i in (0..code.length).rev() {
+            self.current = (self.current << 1) | ((code.bits >> i) & 1);
+            self.bits_in_current += 1;
+
+            if self.bits_in_current == 32 {
+                self.words.push(self.current);
+                self.current = 0;
+                self.bits_in_current = 0;
+            }
+        }
+    }
+
+    fn finish(mut self) -> impl Iterator<Item = u8> {
+        if self.bits_in_current > 0 {
+            self.words.push(self.current << (32 - self.bits_in_current));
+        }
+
+        self.words.into_iter().flat_map(u32::to_le_bytes)
+    }
+}
+
+/// Takes the entire compressed data, since node positions are relative to the
+/// start of the tree table.
+pub(crate) fn decompress(data: &[u8], size: usize) -> Result<Vec<u8>, DecompressError> {
+    let symbol_size = u32::from(data[0] & 0xf);
+    if symbol_size != 4 && symbol_size != 8 {
+        return Err(DecompressError::Invalid);
+    }
+
+    let tree = data.get(4..).ok_or(DecompressError::Truncated)?;
+    let tree_size = *tree.first().ok_or(DecompressError::Truncated)? as usize;
+    let bitstream = tree
+        .get((tree_size + 1) * 2..)
+        .ok_or(DecompressError::Truncated)?;
+
+    let node_at = |position: usize| tree.get(position).copied().ok_or(DecompressError::Invalid);
+
+    let mut output = Vec::with_capacity(size.next_multiple_of(4));
+    let mut word = 0u32;
+    let mut bits_in_word = 0;
+    let mut position = 1;
+
+    'words: for compressed_word in bitstream.chunks_exact(4) {
+        let compressed_word = u32::from_le_bytes(compressed_word.try_into().unwrap());
+
+        for bit in (0..32).rev() {
+            let direction = ((compressed_word >> bit) & 1) as usize;
+            let node = node_at(position)?;
+
+            let child = (position & !1) + (node as usize & 0x3f) * 2 + 2 + direction;
+
+            if node & (0x80 >> direction) == 0 {
+                position = child;
+                continue;
+            }
+
+            word |= u32::from(node_at(child)?) << bits_in_word;
+            bits_in_word += symbol_size;
+            position = 1;
+
+            if bits_in_word == 32 {
+                output.extend_from_slice(&word.to_le_bytes());
+                word = 0;
+                bits_in_word = 0;
+
+                if output.len() >= size {
+                    break 'words;
+                }
+            }
+        }
+    }
+
+    if output.len() < size {
+        return Err(DecompressError::Truncated);
+    }
+
+    output.truncate(size);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_round_trip_both_symbol_sizes() -> Result<(), DecompressError> {
+        for input in crate::test::test_inputs() {
+            for symbol_size in [4, 8] {
+                let Some(compressed) = compress_with_symbol_size(&input, symbol_size) else {
+                    assert_eq!(symbol_size, 8, "4 bit trees should always fit");
+                    continue;
+                };
+
+                assert_eq!(compressed[0], 0x20 | symbol_size);
+                assert_eq!(compressed.len() % 4, 0);
+                assert_eq!(crate::decompress(&compressed)?, input);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_word_align_bitstream() {
+        let compressed = compress_with_symbol_size(b"hello world", 8).unwrap();
+        let tree_size = compressed[4] as usize;
+
+        assert_eq!((4 + (tree_size + 1) * 2) % 4, 0);
+    }
+}
@@ -0,0 +1,230 @@
+//! Encoders for the LZ77, run length and Huffman formats which the Game Boy
+//! Advance BIOS can decompress, along with a reference decoder.
+//!
+//! Every format starts with the same 32 bit header, with the type of
+//! compression in bits 4-7 and the size of the decompressed data in bits 8-31.
+//! The compressed output is always padded to a multiple of 4 bytes, and should
+//! be stored 4 byte aligned.
+
+#![deny(clippy::all)]
+
+use std::fmt;
+
+mod huffman;
+mod lz77;
+mod rle;
+
+/// The maximum size of the decompressed data, limited by the 24 bit size in
+/// the header.
+pub const MAX_DECOMPRESSED_SIZE: usize = (1 << 24) - 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// LZ77, decompressed with the `LZ77UnCompReadNormalWrite8bit` or
+    /// `LZ77UnCompReadNormalWrite16bit` BIOS calls. The output of [`compress`]
+    /// is always safe to decompress directly into video RAM.
+    Lz77,
+    /// Run length encoding, decompressed with the `RLUnCompReadNormalWrite8bit` or
+    /// `RLUnCompReadNormalWrite16bit` BIOS calls.
+    RunLength,
+    /// Huffman coding of either 4 or 8 bit symbols, decompressed with the
+    /// `HuffUnCompReadNormal` BIOS call. This writes 32 bits at a time.
+    Huffman,
+}
+
+impl Compression {
+    pub const ALL: [Compression; 3] = [
+        Compression::Lz77,
+        Compression::RunLength,
+        Compression::Huffman,
+    ];
+
+    const fn header_type(self) -> u8 {
+        match self {
+            Compression::Lz77 => 1,
+            Compression::Huffman => 2,
+            Compression::RunLength => 3,
+        }
+    }
+
+    const fn from_header_type(header_type: u8) -> Option<Self> {
+        match header_type {
+            1 => Some(Compression::Lz77),
+            2 => Some(Compression::Huffman),
+            3 => Some(Compression::RunLength),
+            _ => None,
+        }
+    }
+}
+
+/// Compresses `data` in the given format, including the header.
+///
+/// # Panics
+///
+/// Panics if `data` is longer than [`MAX_DECOMPRESSED_SIZE`].
+pub fn compress(data: &[u8], compression: Compression) -> Vec<u8> {
+    match compression {
+        Compression::Lz77 => lz77::compress(data),
+        Compression::RunLength => rle::compress(data),
+        Compression::Huffman => huffman::compress(data),
+    }
+}
+
+/// Compresses `data` with each of the `allowed` formats and returns whichever
+/// is the smallest.
+///
+/// # Panics
+///
+/// Panics if `allowed` is empty or `data` is longer than [`MAX_DECOMPRESSED_SIZE`].
+pub fn compress_smallest(data: &[u8], allowed: &[Compression]) -> Vec<u8> {
+    allowed
+        .iter()
+        .map(|&compression| compress(data, compression))
+        .min_by_key(Vec::len)
+        .expect("Must allow at least one compression format")
+}
+
+/// Returns the format of some compressed data based on its header.
+pub fn compression_of(data: &[u8]) -> Option<Compression> {
+    Compression::from_header_type(*data.first()? >> 4)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecompressError {
+    /// The data is too short to contain a header
+    MissingHeader,
+    /// The header doesn't have a known compression type
+    UnknownCompression(u8),
+    /// The compressed data ended before all the decompressed data was produced
+    Truncated,
+    /// The compressed data refers to something which doesn't exist
+    Invalid,
+}
+
+impl fmt::Display for DecompressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecompressError::MissingHeader => write!(f, "Compressed data is missing its header"),
+            DecompressError::UnknownCompression(header_type) => {
+                write!(f, "Unknown compression type {header_type}")
+            }
+            DecompressError::Truncated => write!(f, "Compressed data is truncated"),
+            DecompressError::Invalid => write!(f, "Compressed data is invalid"),
+        }
+    }
+}
+
+impl std::error::Error for DecompressError {}
+
+/// Decompresses data in any of the supported formats, in the same way that the
+/// BIOS would.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, DecompressError> {
+    if data.len() < 4 {
+        return Err(DecompressError::MissingHeader);
+    }
+
+    let header_type = data[0] >> 4;
+    let size = u32::from_le_bytes([data[1], data[2], data[3], 0]) as usize;
+
+    match Compression::from_header_type(header_type) {
+        Some(Compression::Lz77) => lz77::decompress(&data[4..], size),
+        Some(Compression::RunLength) => rle::decompress(&data[4..], size),
+        Some(Compression::Huffman) => huffman::decompress(data, size),
+        None => Err(DecompressError::UnknownCompression(header_type)),
+    }
+}
+
+fn header(compression: Compression, low_bits: u8, decompressed_size: usize) -> Vec<u8> {
+    assert!(
+        decompressed_size <= MAX_DECOMPRESSED_SIZE,
+        "Data is too large to compress, maximum size is {MAX_DECOMPRESSED_SIZE} bytes"
+    );
+
+    let header = (decompressed_size as u32) << 8
+        | u32::from(compression.header_type()) << 4
+        | u32::from(low_bits);
+
+    header.to_le_bytes().to_vec()
+}
+
+fn pad_to_word(output: &mut Vec<u8>) {
+    output.resize(output.len().next_multiple_of(4), 0);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    pub(crate) fn test_inputs() -> Vec<Vec<u8>> {
+        let mut random = Vec::with_capacity(5000);
+        let mut state = 0x1234_5678u32;
+        for _ in 0..5000 {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            random.push((state >> 16) as u8);
+        }
+
+        let skewed = random
+            .iter()
+            .map(|&x| x.saturating_sub(200).wrapping_mul(x & 3))
+            .collect();
+
+        vec![
+            vec![],
+            vec![7],
+            vec![1, 2],
+            vec![0; 1000],
+            b"the quick brown fox jumps over the lazy dog, the lazy dog jumps over the quick brown fox"
+                .to_vec(),
+            (0..=255).collect(),
+            (0..2000).map(|i| (i / 7) as u8).collect(),
+            random,
+            skewed,
+        ]
+    }
+
+    #[test]
+    fn should_round_trip_every_format() -> Result<(), DecompressError> {
+        for input in test_inputs() {
+            for compression in Compression::ALL {
+                let compressed = compress(&input, compression);
+
+                assert_eq!(compressed.len() % 4, 0, "{compression:?} should be padded");
+                assert_eq!(compression_of(&compressed), Some(compression));
+                assert_eq!(
+                    decompress(&compressed)?,
+                    input,
+                    "{compression:?} failed to round trip"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_store_size_in_header() {
+        let compressed = compress(&[0; 0x12345], Compression::RunLength);
+
+        assert_eq!(&compressed[..4], &[0x30, 0x45, 0x23, 0x01]);
+    }
+
+    #[test]
+    fn should_pick_the_smallest_format() {
+        let data = vec![0; 4096];
+
+        let smallest = compress_smallest(&data, &Compression::ALL);
+
+        for compression in Compression::ALL {
+            assert!(smallest.len() <= compress(&data, compression).len());
+        }
+    }
+
+    #[test]
+    fn should_reject_unknown_compression() {
+        assert_eq!(
+            decompress(&[0x40, 1, 0, 0]),
+            Err(DecompressError::UnknownCompression(4))
+        );
+        assert_eq!(decompress(&[0x10]), Err(DecompressError::MissingHeader));
+    }
+}
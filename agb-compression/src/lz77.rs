@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use crate::{header, pad_to_word, Compression, DecompressError};
+
+const MIN_MATCH_LENGTH: usize = 3;
+const MAX_MATCH_LENGTH: usize = 18;
+const WINDOW_SIZE: usize = 4096;
+
+// The 16 bit write variant of the BIOS call writes two bytes at a time, so it
+// can't copy from the byte immediately before the one it's writing.
+const MIN_DISTANCE: usize = 2;
+
+// Bounds the time spent searching long runs of the same bytes
+const MAX_CANDIDATES: usize = 256;
+
+pub(crate) fn compress(data: &[u8]) -> Vec<u8> {
+    let mut output = header(Compression::Lz77, 0, data.len());
+
+    let mut previous_positions: HashMap<[u8; 3], Vec<usize>> = HashMap::new();
+    let mut position = 0;
+
+    let mut flag_index = 0;
+    let mut blocks_in_group = 8;
+
+    while position < data.len() {
+        if blocks_in_group == 8 {
+            flag_index = output.len();
+            output.push(0);
+            blocks_in_group = 0;
+        }
+
+        let length = match longest_match(data, position, &previous_positions) {
+            Some((length, distance)) => {
+                output[flag_index] |= 0x80 >> blocks_in_group;
+
+                let displacement = distance - 1;
+                output.push((((length - MIN_MATCH_LENGTH) << 4) | (displacement >> 8)) as u8);
+                output.push(displacement as u8);
+
+                length
+            }
+            None => {
+                output.push(data[position]);
+                1
+            }
+        };
+
+        for inserted in position..position + length {
+            if let Some(key) = prefix(data, inserted) {
+                previous_positions.entry(key).or_default().push(inserted);
+            }
+        }
+
+        position += length;
+        blocks_in_group += 1;
+    }
+
+    pad_to_word(&mut output);
+    output
+}
+
+fn prefix(data: &[u8], position: usize) -> Option<[u8; 3]> {
+    data.get(position..position + 3)?.try_into().ok()
+}
+
+fn longest_match(
+    data: &[u8],
+    position: usize,
+    previous_positions: &HashMap<[u8; 3], Vec<usize>>,
+) -> Option<(usize, usize)> {
+    let candidates = previous_positions.get(&prefix(data, position)?)?;
+    let max_length = MAX_MATCH_LENGTH.min(data.len() - position);
+
+    let mut best: Option<(usize, usize)> = None;
+
+    for &candidate in candidates.iter().rev().take(MAX_CANDIDATES) {
+        let distance = position - candidate;
+        if distance > WINDOW_SIZE {
+            break;
+        }
+
+        if distance < MIN_DISTANCE {
+            continue;
+        }
+
+        let length = (0..max_length)
+            .take_while(|&i| data[candidate + i] == data[position + i])
+            .count();
+
+        if best.is_none_or(|(best_length, _)| length > best_length) {
+            best = Some((length, distance));
+
+            if length == max_length {
+                break;
+            }
+        }
+    }
+
+    best.filter(|&(length, _)| length >= MIN_MATCH_LENGTH)
+}
+
+pub(crate) fn decompress(data: &[u8], size: usize) -> Result<Vec<u8>, DecompressError> {
+    let mut output = Vec::with_capacity(size);
+    let mut data = data.iter().copied();
+
+    while output.len() < size {
+        let flags = data.next().ok_or(DecompressError::Truncated)?;
+
+        for block in 0..8 {
+            if output.len() >= size {
+                break;
+            }
+
+            if flags & (0x80 >> block) == 0 {
+                output.push(data.next().ok_or(DecompressError::Truncated)?);
+                continue;
+            }
+
+            let first = data.next().ok_or(DecompressError::Truncated)? as usize;
+            let second = data.next().ok_or(DecompressError::Truncated)? as usize;
+
+            let length = (first >> 4) + MIN_MATCH_LENGTH;
+            let distance = (((first & 0xf) << 8) | second) + 1;
+
+            let start = output
+                .len()
+                .checked_sub(distance)
+                .ok_or(DecompressError::Invalid)?;
+
+            for i in 0..length {
+                output.push(output[start + i]);
+            }
+        }
+    }
+
+    output.truncate(size);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn distances(compressed: &[u8]) -> Vec<usize> {
+        let mut data = compressed[4..].iter().copied();
+        let mut distances = vec![];
+        let size = u32::from_le_bytes([compressed[1], compressed[2], compressed[3], 0]) as usize;
+        let mut produced = 0;
+
+        while produced < size {
+            let flags = data.next().unwrap();
+            for block in 0..8 {
+                if produced >= size {
+                    break;
+                }
+
+                if flags & (0x80 >> block) == 0 {
+                    data.next();
+                    produced += 1;
+                } else {
+                    let first = data.next().unwrap() as usize;
+                    let second = data.next().unwrap() as usize;
+                    distances.push((((first & 0xf) << 8) | second) + 1);
+                    produced += (first >> 4) + MIN_MATCH_LENGTH;
+                }
+            }
+        }
+
+        distances
+    }
+
+    #[test]
+    fn should_be_safe_to_decompress_to_vram() {
+        for input in crate::test::test_inputs() {
+            let compressed = compress(&input);
+
+            assert!(distances(&compressed)
+                .iter()
+                .all(|&distance| distance >= MIN_DISTANCE));
+        }
+    }
+
+    #[test]
+    fn should_compress_repeated_data() {
+        let compressed = compress(&[0; 1000]);
+
+        // 2 literals to start things off, then ~55 copies of 18 bytes
+        assert!(compressed.len() < 140, "{} bytes", compressed.len());
+    }
+}
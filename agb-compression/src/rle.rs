@@ -0,0 +1,79 @@
+use crate::{header, pad_to_word, Compression, DecompressError};
+
+const MAX_LITERAL_LENGTH: usize = 128;
+const MIN_RUN_LENGTH: usize = 3;
+const MAX_RUN_LENGTH: usize = 130;
+
+pub(crate) fn compress(data: &[u8]) -> Vec<u8> {
+    let mut output = header(Compression::RunLength, 0, data.len());
+
+    let mut literal_start = 0;
+    let mut position = 0;
+
+    while position < data.len() {
+        let run_length = data[position..]
+            .iter()
+            .take(MAX_RUN_LENGTH)
+            .take_while(|&&byte| byte == data[position])
+            .count();
+
+        if run_length >= MIN_RUN_LENGTH {
+            write_literals(&mut output, &data[literal_start..position]);
+
+            output.push(0x80 | (run_length - MIN_RUN_LENGTH) as u8);
+            output.push(data[position]);
+
+            position += run_length;
+            literal_start = position;
+        } else {
+            position += 1;
+        }
+    }
+
+    write_literals(&mut output, &data[literal_start..]);
+
+    pad_to_word(&mut output);
+    output
+}
+
+fn write_literals(output: &mut Vec<u8>, literals: &[u8]) {
+    for chunk in literals.chunks(MAX_LITERAL_LENGTH) {
+        output.push((chunk.len() - 1) as u8);
+        output.extend_from_slice(chunk);
+    }
+}
+
+pub(crate) fn decompress(data: &[u8], size: usize) -> Result<Vec<u8>, DecompressError> {
+    let mut output = Vec::with_capacity(size);
+    let mut data = data.iter().copied();
+
+    while output.len() < size {
+        let flag = data.next().ok_or(DecompressError::Truncated)?;
+
+        if flag & 0x80 != 0 {
+            let length = (flag & 0x7f) as usize + MIN_RUN_LENGTH;
+            let byte = data.next().ok_or(DecompressError::Truncated)?;
+
+            output.extend(std::iter::repeat_n(byte, length));
+        } else {
+            for _ in 0..=flag {
+                output.push(data.next().ok_or(DecompressError::Truncated)?);
+            }
+        }
+    }
+
+    output.truncate(size);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_encode_runs_and_literals() {
+        let compressed = compress(&[1, 2, 3, 3, 3, 3, 4]);
+
+        assert_eq!(&compressed[4..], &[0x01, 1, 2, 0x81, 3, 0x00, 4, 0]);
+    }
+}
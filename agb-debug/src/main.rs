@@ -2,38 +2,83 @@ use std::{
     borrow::Cow,
     error::Error,
     fs::{self, File},
-    io::Read,
-    path::PathBuf,
+    io::{self, Read},
+    path::{Path, PathBuf},
     time::SystemTime,
 };
 
-use agb_debug::{address_info, AddressInfo, Location};
-use clap::Parser;
+use agb_debug::{address_info, AddressInfo, FunctionProfile, Location};
+use clap::{Parser, Subcommand};
 use colored::Colorize;
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// The filename of the elf file
-    elf_path: PathBuf,
+    #[arg(required = true)]
+    elf_path: Option<PathBuf>,
 
     /// The output of agb's dump
-    dump: String,
+    #[arg(required = true)]
+    dump: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print a profile from the output of agb's profiler
+    Profile {
+        /// The filename of the elf file
+        elf_path: PathBuf,
+
+        /// The mgba log containing the profiler output, or - to read from stdin
+        log: PathBuf,
+
+        /// The number of functions to show in each table
+        #[arg(short, long, default_value_t = 25)]
+        limit: usize,
+    },
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let cli = Args::parse();
 
-    let modification_time = fs::metadata(&cli.elf_path)?
-        .modified()
-        .unwrap_or(SystemTime::UNIX_EPOCH);
+    match cli.command {
+        Some(Command::Profile {
+            elf_path,
+            log,
+            limit,
+        }) => print_profile(&elf_path, &log, limit),
+        None => print_stack_trace(
+            &cli.elf_path.expect("elf path is required"),
+            &cli.dump.expect("dump is required"),
+        ),
+    }
+}
 
-    let file = fs::read(&cli.elf_path)?;
+fn load_context(elf_path: &Path) -> Result<agb_debug::Addr2LineContext, Box<dyn Error>> {
+    let file = fs::read(elf_path)?;
     let dwarf = agb_debug::load_dwarf(&file)?;
 
-    let ctx = addr2line::Context::from_dwarf(dwarf)?;
+    Ok(addr2line::Context::from_dwarf(dwarf)?)
+}
 
-    for (i, address) in agb_debug::gwilym_decode(&cli.dump)?.enumerate() {
+fn print_stack_trace(elf_path: &Path, dump: &str) -> Result<(), Box<dyn Error>> {
+    let modification_time = fs::metadata(elf_path)?
+        .modified()
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+
+    let ctx = load_context(elf_path)?;
+
+    for (i, address) in agb_debug::gwilym_decode(dump)?.enumerate() {
         let infos = address_info(&ctx, address.into())?;
         for info in infos {
             print_address_info(&info, i, modification_time)?;
@@ -43,6 +88,76 @@ fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+fn print_profile(elf_path: &Path, log_path: &Path, limit: usize) -> Result<(), Box<dyn Error>> {
+    let log = if log_path == Path::new("-") {
+        let mut log = String::new();
+        io::stdin().read_to_string(&mut log)?;
+        log
+    } else {
+        fs::read_to_string(log_path)?
+    };
+
+    let profile = agb_debug::parse_profile(&log)?;
+    let ctx = load_context(elf_path)?;
+    let mut functions = agb_debug::symbolicate_profile(&ctx, &profile)?;
+
+    let sample_count: usize = profile.samples.iter().map(|sample| sample.count).sum();
+    if sample_count == 0 {
+        eprintln!("Profile contains no samples");
+        return Ok(());
+    }
+
+    print!("{sample_count} samples");
+    if let Some(period) = profile.period {
+        print!(" taken every {period} cycles");
+    }
+    println!();
+
+    if profile.dropped_samples > 0 {
+        eprintln!(
+            "Warning: {} samples were dropped because the profiler's buffer was full",
+            profile.dropped_samples
+        );
+    }
+
+    println!("\n{}", "Flat profile".bold());
+    print_function_table(functions.iter().take(limit), sample_count);
+
+    functions.sort_by(|a, b| {
+        b.total_samples
+            .cmp(&a.total_samples)
+            .then(b.self_samples.cmp(&a.self_samples))
+    });
+
+    println!("\n{}", "Inclusive profile".bold());
+    print_function_table(functions.iter().take(limit), sample_count);
+
+    Ok(())
+}
+
+fn print_function_table<'a>(
+    functions: impl Iterator<Item = &'a FunctionProfile>,
+    sample_count: usize,
+) {
+    println!(
+        "{:>7} {:>7} {:>7} {:>7}  function",
+        "self%", "self", "total%", "total"
+    );
+
+    for function in functions {
+        let percentage = |samples| samples as f64 * 100.0 / sample_count as f64;
+
+        println!(
+            "{:>6.2}% {:>7} {:>6.2}% {:>7}  {}",
+            percentage(function.self_samples),
+            function.self_samples,
+            percentage(function.total_samples),
+            function.total_samples,
+            function.function
+        );
+    }
+}
+
 fn print_address_info(
     info: &AddressInfo,
     index: usize,
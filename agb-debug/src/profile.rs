@@ -0,0 +1,237 @@
+use std::collections::{hash_map::Entry, HashMap};
+
+use thiserror::Error;
+
+use crate::{address_info, gwilym_decode, Addr2LineContext, AddressInfoError, GwilymDecodeError};
+
+const LOG_PREFIX: &str = "agb-profile:";
+
+#[derive(Debug, Error)]
+pub enum ProfileParseError {
+    #[error("No profile found in the log")]
+    NoProfile,
+    #[error("Profile was not terminated, the log may be truncated")]
+    Unterminated,
+    #[error("Invalid sample line `{0}`")]
+    InvalidSample(String),
+    #[error(transparent)]
+    Decode(#[from] GwilymDecodeError),
+}
+
+/// A single call stack and the number of times it was sampled. The first
+/// address is where the sample was taken, followed by the callers.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ProfileSample {
+    pub count: usize,
+    pub stack: Vec<u32>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Profile {
+    pub period: Option<u32>,
+    pub total_samples: usize,
+    pub dropped_samples: usize,
+    pub samples: Vec<ProfileSample>,
+}
+
+/// Parses the last complete profile written by `agb::profiler::Profiler` out
+/// of an mgba log. Lines which aren't part of the profile are ignored.
+pub fn parse_profile(log: &str) -> Result<Profile, ProfileParseError> {
+    let mut current: Option<Profile> = None;
+    let mut last_complete = None;
+
+    for line in log.lines() {
+        let Some(index) = line.find(LOG_PREFIX) else {
+            continue;
+        };
+        let content = line[index + LOG_PREFIX.len()..].trim();
+
+        if let Some(header) = content.strip_prefix("start") {
+            current = Some(parse_header(header));
+        } else if content == "end" {
+            if let Some(profile) = current.take() {
+                last_complete = Some(profile);
+            }
+        } else if let Some(profile) = current.as_mut() {
+            let (count, stack) = content
+                .split_once(' ')
+                .ok_or_else(|| ProfileParseError::InvalidSample(content.to_string()))?;
+            let count = count
+                .parse()
+                .map_err(|_| ProfileParseError::InvalidSample(content.to_string()))?;
+
+            profile.samples.push(ProfileSample {
+                count,
+                stack: gwilym_decode(stack)?.collect(),
+            });
+        }
+    }
+
+    match (last_complete, current) {
+        (Some(profile), _) => Ok(profile),
+        (None, Some(_)) => Err(ProfileParseError::Unterminated),
+        (None, None) => Err(ProfileParseError::NoProfile),
+    }
+}
+
+fn parse_header(header: &str) -> Profile {
+    let mut profile = Profile::default();
+
+    for (key, value) in header
+        .split_whitespace()
+        .filter_map(|kv| kv.split_once('='))
+    {
+        match key {
+            "period" => profile.period = value.parse().ok(),
+            "samples" => profile.total_samples = value.parse().unwrap_or(0),
+            "dropped" => profile.dropped_samples = value.parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+
+    profile
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FunctionProfile {
+    pub function: String,
+    /// Number of samples where this function was executing
+    pub self_samples: usize,
+    /// Number of samples where this function was anywhere on the call stack
+    pub total_samples: usize,
+}
+
+/// Symbolicates every sample in the profile, and returns the per function
+/// totals sorted by the number of samples spent in the function itself.
+pub fn symbolicate_profile(
+    ctx: &Addr2LineContext,
+    profile: &Profile,
+) -> Result<Vec<FunctionProfile>, AddressInfoError> {
+    let mut function_names: HashMap<u32, Vec<String>> = HashMap::new();
+    let mut functions: HashMap<String, FunctionProfile> = HashMap::new();
+
+    for sample in &profile.samples {
+        let mut seen_in_sample = Vec::new();
+
+        for (depth, &address) in sample.stack.iter().enumerate() {
+            let names = match function_names.entry(address) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(
+                    address_info(ctx, address.into())?
+                        .into_iter()
+                        .map(|info| info.function)
+                        .collect(),
+                ),
+            };
+
+            let mut names = names.iter();
+
+            if depth == 0 {
+                // the innermost (possibly inlined) function is the one that was executing
+                let name = names
+                    .next()
+                    .cloned()
+                    .unwrap_or_else(|| "unknown function".to_string());
+                function_entry(&mut functions, &name).self_samples += sample.count;
+                seen_in_sample.push(name);
+            }
+
+            seen_in_sample.extend(names.cloned());
+        }
+
+        seen_in_sample.sort_unstable();
+        seen_in_sample.dedup();
+
+        for name in &seen_in_sample {
+            function_entry(&mut functions, name).total_samples += sample.count;
+        }
+    }
+
+    let mut result: Vec<_> = functions.into_values().collect();
+    result.sort_by(|a, b| {
+        b.self_samples
+            .cmp(&a.self_samples)
+            .then(b.total_samples.cmp(&a.total_samples))
+            .then_with(|| a.function.cmp(&b.function))
+    });
+
+    Ok(result)
+}
+
+fn function_entry<'a>(
+    functions: &'a mut HashMap<String, FunctionProfile>,
+    name: &str,
+) -> &'a mut FunctionProfile {
+    functions
+        .entry(name.to_string())
+        .or_insert_with(|| FunctionProfile {
+            function: name.to_string(),
+            ..Default::default()
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_parse_profile_from_mgba_log() -> Result<(), ProfileParseError> {
+        let log = "\
+[INFO] GBA Debug: Hello, world
+[INFO] GBA Debug: agb-profile:start period=2048 samples=5 dropped=1
+[INFO] GBA Debug: agb-profile:3 2QI65Qv1
+[INFO] GBA Debug: agb-profile:2 69306Kv1
+[INFO] GBA Debug: agb-profile:end
+";
+
+        assert_eq!(
+            parse_profile(log)?,
+            Profile {
+                period: Some(2048),
+                total_samples: 5,
+                dropped_samples: 1,
+                samples: vec![
+                    ProfileSample {
+                        count: 3,
+                        stack: vec![0x0800_16d3, 0x0800_315b]
+                    },
+                    ProfileSample {
+                        count: 2,
+                        stack: vec![0x0800_3243, 0x0800_0195]
+                    }
+                ]
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_use_the_last_complete_profile() -> Result<(), ProfileParseError> {
+        let log = "\
+agb-profile:start period=1 samples=1 dropped=0
+agb-profile:1 2QIv1
+agb-profile:end
+agb-profile:start period=2 samples=1 dropped=0
+agb-profile:1 65Qv1
+agb-profile:end
+agb-profile:start period=3 samples=1 dropped=0
+";
+
+        assert_eq!(parse_profile(log)?.period, Some(2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_error_on_missing_or_truncated_profile() {
+        assert!(matches!(
+            parse_profile("nothing to see here"),
+            Err(ProfileParseError::NoProfile)
+        ));
+        assert!(matches!(
+            parse_profile("agb-profile:start period=1 samples=1 dropped=0"),
+            Err(ProfileParseError::Unterminated)
+        ));
+    }
+}
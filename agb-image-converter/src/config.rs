@@ -12,4 +12,7 @@ pub(crate) trait Image {
     fn filename(&self) -> String;
     fn colours(&self) -> Colours;
     fn deduplicate(&self) -> bool;
+    fn compress(&self) -> bool;
+    fn animated(&self) -> bool;
+    fn affine(&self) -> bool;
 }
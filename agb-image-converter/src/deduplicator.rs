@@ -139,3 +139,119 @@ pub(crate) fn deduplicate_image(input: &Image, can_flip: bool) -> (Image, Vec<De
         .collect::<Vec<_>>();
     (Image::from_colour_data(image_data), deduplication_data)
 }
+
+pub struct AnimatedTile {
+    pub tile: usize,
+    pub frames: Vec<usize>,
+}
+
+/// Deduplicates an image made of `frames` frames of animation stacked vertically.
+///
+/// Each tile position of the first frame gets its own tile unless it is identical to
+/// another position in every frame. Positions which change between frames are returned
+/// as animations, whose later frames are extra tiles which are never placed on the map.
+/// `palette_of` gives the palette assignment of a tile of the input, since tiles can
+/// only share data if they also share a palette.
+pub(crate) fn deduplicate_animation(
+    input: &Image,
+    frames: usize,
+    deduplicate: bool,
+    palette_of: impl Fn(usize) -> usize,
+) -> (Image, Vec<DeduplicatedData>, Vec<AnimatedTile>) {
+    let all_tiles = Tile::split_image(input);
+    let tiles_per_frame = all_tiles.len() / frames;
+
+    let mut resulting_tiles = vec![];
+    let mut new_indices = vec![0; all_tiles.len()];
+
+    let mut existing_tiles = BTreeMap::new();
+    let mut existing_animations = BTreeMap::new();
+    let mut new_animations = vec![];
+
+    for position in 0..tiles_per_frame {
+        let sequence = (0..frames)
+            .map(|frame| all_tiles[frame * tiles_per_frame + position].clone())
+            .collect::<Vec<_>>();
+        let palette = palette_of(position);
+
+        let index = if sequence.iter().all(|tile| tile == &sequence[0]) {
+            let key = (palette, sequence[0].clone());
+
+            match existing_tiles.get(&key) {
+                Some(&index) if deduplicate => index,
+                _ => {
+                    resulting_tiles.push(sequence[0].clone());
+                    existing_tiles.insert(key, resulting_tiles.len() - 1);
+                    resulting_tiles.len() - 1
+                }
+            }
+        } else {
+            // Animated tiles can only be shared with positions which animate in exactly
+            // the same way, otherwise changing one would change the other
+            let first_tile = sequence[0].clone();
+            let key = (palette, sequence);
+
+            match existing_animations.get(&key) {
+                Some(&index) if deduplicate => index,
+                _ => {
+                    resulting_tiles.push(first_tile);
+                    let index = resulting_tiles.len() - 1;
+
+                    existing_animations.insert(key, index);
+                    new_animations.push((position, index));
+                    index
+                }
+            }
+        };
+
+        for frame in 0..frames {
+            new_indices[frame * tiles_per_frame + position] = index;
+        }
+    }
+
+    let mut animations = vec![];
+
+    for (position, index) in new_animations {
+        let mut animation_frames = vec![index];
+
+        for frame in 1..frames {
+            let tile_index = frame * tiles_per_frame + position;
+            let key = (palette_of(tile_index), all_tiles[tile_index].clone());
+
+            let source = match existing_tiles.get(&key) {
+                Some(&source) if deduplicate => source,
+                _ => {
+                    resulting_tiles.push(key.1.clone());
+                    existing_tiles.insert(key, resulting_tiles.len() - 1);
+                    resulting_tiles.len() - 1
+                }
+            };
+
+            new_indices[tile_index] = source;
+            animation_frames.push(source);
+        }
+
+        animations.push(AnimatedTile {
+            tile: index,
+            frames: animation_frames,
+        });
+    }
+
+    let deduplication_data = new_indices
+        .into_iter()
+        .map(|new_index| DeduplicatedData {
+            new_index,
+            transformation: Transformation::none(),
+        })
+        .collect();
+
+    let image_data = resulting_tiles
+        .iter()
+        .flat_map(|tile| tile.data)
+        .collect::<Vec<_>>();
+    (
+        Image::from_colour_data(image_data),
+        deduplication_data,
+        animations,
+    )
+}
@@ -24,6 +24,39 @@ impl Image {
         Self::load_from_dyn_image(img)
     }
 
+    /// Loads every frame of an aseprite file, stacked vertically into a single image,
+    /// along with the duration of each frame in milliseconds.
+    pub fn load_animation_from_file(image_path: &path::Path) -> (Self, Vec<u32>) {
+        assert!(
+            image_path.extension() == Some(OsStr::new("aseprite")),
+            "Animated backgrounds must be aseprite files, but got {}",
+            image_path.display()
+        );
+
+        let ase =
+            asefile::AsepriteFile::read_file(image_path).expect("failed to read aseprite file");
+
+        let mut colour_data = vec![];
+        let mut durations = vec![];
+
+        for frame in 0..ase.num_frames() {
+            let frame = ase.frame(frame);
+
+            colour_data.extend(
+                Self::load_from_dyn_image(DynamicImage::ImageRgba8(frame.image())).colour_data,
+            );
+            durations.push(frame.duration());
+        }
+
+        let image = Image {
+            width: ase.width(),
+            height: ase.height() * ase.num_frames() as usize,
+            colour_data,
+        };
+
+        (image, durations)
+    }
+
     pub fn load_from_dyn_image(img: image::DynamicImage) -> Self {
         let (width, height) = img.dimensions();
 
@@ -37,6 +37,9 @@ struct BackgroundGfxOption {
     file_name: String,
     colours: Colours,
     deduplicate: bool,
+    compress: bool,
+    animated: bool,
+    affine: bool,
 }
 
 impl config::Image for BackgroundGfxOption {
@@ -53,6 +56,18 @@ impl config::Image for BackgroundGfxOption {
     fn deduplicate(&self) -> bool {
         self.deduplicate
     }
+
+    fn compress(&self) -> bool {
+        self.compress
+    }
+
+    fn animated(&self) -> bool {
+        self.animated
+    }
+
+    fn affine(&self) -> bool {
+        self.affine
+    }
 }
 
 impl Parse for BackgroundGfxOption {
@@ -66,8 +81,8 @@ impl Parse for BackgroundGfxOption {
             let num_colours: syn::LitInt = input.parse()?;
 
             match num_colours.base10_parse()? {
-                16 => Colours::Colours16,
-                256 => Colours::Colours256,
+                16 => Some((Colours::Colours16, num_colours)),
+                256 => Some((Colours::Colours256, num_colours)),
                 _ => {
                     return Err(syn::Error::new_spanned(
                         num_colours,
@@ -76,26 +91,60 @@ impl Parse for BackgroundGfxOption {
                 }
             }
         } else {
-            Colours::Colours16
+            None
         };
 
-        let lookahead = input.lookahead1();
-
-        let deduplicate = if lookahead.peek(syn::Ident) {
-            let deduplicate: syn::Ident = input.parse()?;
-
-            if deduplicate == "deduplicate" {
-                true
+        let mut deduplicate = false;
+        let mut compress = false;
+        let mut animated = false;
+        let mut affine = false;
+
+        while input.lookahead1().peek(syn::Ident) {
+            let option: syn::Ident = input.parse()?;
+
+            if option == "deduplicate" && !deduplicate {
+                deduplicate = true;
+            } else if option == "compress" && !compress {
+                compress = true;
+            } else if option == "animated" && !animated {
+                animated = true;
+            } else if option == "affine" && !affine {
+                affine = true;
             } else {
                 return Err(syn::Error::new_spanned(
-                    deduplicate,
-                    "Must either be the literal deduplicate or missing",
+                    option,
+                    "Options must be deduplicate, compress, animated or affine, each at most once",
                 ));
             }
-        } else {
-            false
+        }
+
+        // Affine backgrounds can only use 256 colour tiles
+        let colours = match colours {
+            Some((Colours::Colours16, num_colours)) if affine => {
+                return Err(syn::Error::new_spanned(
+                    num_colours,
+                    "Affine backgrounds must use 256 colours",
+                ));
+            }
+            Some((colours, _)) => colours,
+            None if affine => Colours::Colours256,
+            None => Colours::Colours16,
         };
 
+        if affine && (compress || animated) {
+            return Err(syn::Error::new_spanned(
+                module_name,
+                "Affine backgrounds cannot be compressed or animated",
+            ));
+        }
+
+        if compress && animated {
+            return Err(syn::Error::new_spanned(
+                module_name,
+                "Animated backgrounds cannot be compressed",
+            ));
+        }
+
         let file_name: syn::LitStr = input.parse()?;
 
         Ok(Self {
@@ -103,6 +152,9 @@ impl Parse for BackgroundGfxOption {
             file_name: file_name.value(),
             colours,
             deduplicate,
+            compress,
+            animated,
+            affine,
         })
     }
 }
@@ -217,7 +269,7 @@ fn include_gfx_from_config(
 
     for (name, settings) in images.iter() {
         let image_filename = &parent.join(settings.filename());
-        let image = Image::load_from_file(image_filename);
+        let (image, frame_durations) = load_background_image(*settings, image_filename);
 
         match settings.colours() {
             Colours::Colours16 => {
@@ -226,13 +278,21 @@ fn include_gfx_from_config(
                     panic!("Image size not a multiple of tile size");
                 }
 
-                add_to_optimiser(
-                    &mut optimiser,
-                    &image,
-                    tile_size,
-                    tile_size,
-                    config.transparent_colour(),
-                );
+                match frame_durations {
+                    Some(frame_durations) => add_animation_to_optimiser(
+                        &mut optimiser,
+                        &image,
+                        frame_durations.len(),
+                        config.transparent_colour(),
+                    ),
+                    None => add_to_optimiser(
+                        &mut optimiser,
+                        &image,
+                        tile_size,
+                        tile_size,
+                        config.transparent_colour(),
+                    ),
+                }
 
                 let num_tiles = image.width * image.height / 8usize.pow(2);
                 assignment_offsets.insert(name, assignment_offset);
@@ -326,12 +386,150 @@ pub fn include_colours_inner(input: TokenStream) -> TokenStream {
     })
 }
 
+#[proc_macro]
+pub fn include_bitmap_inner(input: TokenStream) -> TokenStream {
+    let parser = |input: syn::parse::ParseStream| {
+        let paletted = if input.peek(syn::LitInt) {
+            let colours: syn::LitInt = input.parse()?;
+            if colours.base10_parse::<u32>()? != 256 {
+                return Err(syn::Error::new_spanned(
+                    colours,
+                    "Must either be the literal 256 or missing",
+                ));
+            }
+
+            true
+        } else {
+            false
+        };
+
+        Ok((paletted, input.parse::<LitStr>()?))
+    };
+    let (paletted, input_filename) = match parser.parse(input) {
+        Ok(e) => e,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let root = std::env::var("CARGO_MANIFEST_DIR").expect("Failed to get cargo manifest dir");
+    let input_path = Path::new(&root).join(input_filename.value());
+
+    let image = Image::load_from_file(Path::new(&input_path));
+
+    let width = u16::try_from(image.width).expect("Bitmap is too wide");
+    let height = u16::try_from(image.height).expect("Bitmap is too tall");
+
+    let filename = input_path.to_string_lossy();
+
+    if !paletted {
+        let mut colour_data = Vec::with_capacity(image.width * image.height);
+        for y in 0..image.height {
+            for x in 0..image.width {
+                colour_data.push(image.colour(x, y).to_rgb15())
+            }
+        }
+
+        return TokenStream::from(quote! {
+            {
+                const _: &[u8] = include_bytes!(#filename);
+                BitmapData::new(#width, #height, &[#(#colour_data),*])
+            }
+        });
+    }
+
+    if !image.width.is_multiple_of(2) {
+        return syn::Error::new_spanned(input_filename, "Paletted bitmaps must have an even width")
+            .to_compile_error()
+            .into();
+    }
+
+    let (palette, index_data) = match paletted_bitmap(&image) {
+        Ok(bitmap) => bitmap,
+        Err(message) => {
+            return syn::Error::new_spanned(input_filename, message)
+                .to_compile_error()
+                .into()
+        }
+    };
+    let index_data = ByteString(&index_data);
+
+    TokenStream::from(quote! {
+        {
+            const _: &[u8] = include_bytes!(#filename);
+
+            const DATA: &[u8] = {
+                pub struct AlignedAs<Align, Bytes: ?Sized> {
+                    pub _align: [Align; 0],
+                    pub bytes: Bytes,
+                }
+
+                const ALIGNED: &AlignedAs<u32, [u8]> = &AlignedAs {
+                    _align: [],
+                    bytes: *#index_data,
+                };
+
+                &ALIGNED.bytes
+            };
+
+            unsafe { PalettedBitmapData::new(#width, #height, DATA, &[#(#palette),*]) }
+        }
+    })
+}
+
+/// Builds a palette from the colours of `image` in the order they first
+/// appear, and the palette index of every pixel. Fails if there are more than
+/// 256 colours.
+fn paletted_bitmap(image: &Image) -> Result<(Vec<u16>, Vec<u8>), &'static str> {
+    let mut palette: Vec<u16> = vec![];
+    let mut index_data = Vec::with_capacity(image.width * image.height);
+
+    for y in 0..image.height {
+        for x in 0..image.width {
+            let colour = image.colour(x, y).to_rgb15();
+            let index = match palette.iter().position(|&c| c == colour) {
+                Some(index) => index,
+                None => {
+                    if palette.len() == 256 {
+                        return Err("Paletted bitmaps can only use 256 colours");
+                    }
+
+                    palette.push(colour);
+                    palette.len() - 1
+                }
+            };
+
+            index_data.push(index as u8);
+        }
+    }
+
+    Ok((palette, index_data))
+}
+
 #[proc_macro]
 pub fn include_aseprite_inner(input: TokenStream) -> TokenStream {
     let out_dir_path = get_out_dir(&input.to_string());
 
-    let parser = Punctuated::<LitStr, syn::Token![,]>::parse_terminated;
-    let parsed = match parser.parse(input) {
+    let parser = |input: syn::parse::ParseStream| {
+        let compress = if input.peek(syn::Ident) {
+            let option: syn::Ident = input.parse()?;
+            if option != "compress" {
+                return Err(syn::Error::new_spanned(
+                    option,
+                    "Must either be the literal compress or missing",
+                ));
+            }
+
+            let _: Token![,] = input.parse()?;
+            true
+        } else {
+            false
+        };
+
+        Ok((
+            compress,
+            Punctuated::<LitStr, syn::Token![,]>::parse_terminated(input)?,
+        ))
+    };
+    let (compress, parsed) = match parser.parse(input) {
         Ok(e) => e,
         Err(e) => return e.to_compile_error().into(),
     };
@@ -400,17 +598,36 @@ pub fn include_aseprite_inner(input: TokenStream) -> TokenStream {
         .map(|(f, assignment)| {
             let start: usize = pre;
             let end: usize = pre + (f.width / 8) * (f.height / 8) * 32;
-            let data = ByteString(&tile_data[start..end]);
+            let data = &tile_data[start..end];
             pre = end;
             let width = f.width;
             let height = f.height;
-            quote! {
-                unsafe {
-                        Sprite::new(
-                        &PALETTES[#assignment],
-                        align_bytes!(u16, #data),
-                        Size::from_width_height(#width, #height)
-                    )
+
+            if compress {
+                let compressed =
+                    agb_compression::compress_smallest(data, &agb_compression::Compression::ALL);
+                let data = ByteString(&compressed);
+
+                quote! {
+                    unsafe {
+                        Sprite::new_compressed(
+                            &PALETTES[#assignment],
+                            align_bytes!(u32, #data),
+                            Size::from_width_height(#width, #height)
+                        )
+                    }
+                }
+            } else {
+                let data = ByteString(data);
+
+                quote! {
+                    unsafe {
+                            Sprite::new(
+                            &PALETTES[#assignment],
+                            align_bytes!(u16, #data),
+                            Size::from_width_height(#width, #height)
+                        )
+                    }
                 }
             }
         });
@@ -469,8 +686,10 @@ fn convert_image(
     assignment_offset: Option<usize>,
 ) -> proc_macro2::TokenStream {
     let image_filename = &parent.join(settings.filename());
-    let image = Image::load_from_file(image_filename);
+    let (image, frame_durations) = load_background_image(settings, image_filename);
     let deduplicate = settings.deduplicate();
+    let compress = settings.compress();
+    let affine = settings.affine();
 
     rust_generator::generate_code(
         variable_name,
@@ -480,9 +699,68 @@ fn convert_image(
         crate_prefix.to_owned(),
         assignment_offset,
         deduplicate,
+        compress,
+        affine,
+        frame_durations.as_deref(),
     )
 }
 
+/// Loads the image for a background, along with the duration of each frame in
+/// 1/60ths of a second if it is animated. The frames of an animated background
+/// are stacked vertically.
+fn load_background_image(
+    settings: &dyn config::Image,
+    image_filename: &Path,
+) -> (Image, Option<Vec<u16>>) {
+    if !settings.animated() {
+        return (Image::load_from_file(image_filename), None);
+    }
+
+    let (image, durations) = Image::load_animation_from_file(image_filename);
+    let frame_durations = durations
+        .iter()
+        .map(|&ms| frames_from_milliseconds(ms))
+        .collect();
+
+    (image, Some(frame_durations))
+}
+
+/// Converts the duration of an animation frame in milliseconds to 1/60ths of a second.
+/// This is at least 1, since `AnimationFrame` rejects frames which last no time at all.
+fn frames_from_milliseconds(milliseconds: u32) -> u16 {
+    ((u64::from(milliseconds) * 60 + 500) / 1000).clamp(1, u16::MAX.into()) as u16
+}
+
+/// Adds the tiles of an animated image with `frames` frames stacked vertically to the
+/// optimiser. Every frame of a tile gets a palette with the colours of all of them, so
+/// they all end up with the same palette and the tile can be swapped between frames.
+fn add_animation_to_optimiser(
+    palette_optimiser: &mut palette16::Palette16Optimiser,
+    image: &Image,
+    frames: usize,
+    transparent_colour: Option<Colour>,
+) {
+    let mut frame_optimiser = palette16::Palette16Optimiser::new(transparent_colour);
+    add_to_optimiser(&mut frame_optimiser, image, 8, 8, transparent_colour);
+
+    let palettes = frame_optimiser.palettes();
+    let tiles_per_frame = palettes.len() / frames;
+
+    for tile in 0..palettes.len() {
+        let mut palette = palette16::Palette16::new();
+
+        for frame in 0..frames {
+            for &colour in palettes[frame * tiles_per_frame + tile % tiles_per_frame].colours() {
+                if !palette.try_add_colour(colour) {
+                    panic!("Every frame of an animated tile must use the same 16 colours");
+                }
+            }
+        }
+
+        palette_optimiser.add_palette(palette);
+    }
+}
+
 fn add_to_optimiser(
     palette_optimiser: &mut palette16::Palette16Optimiser,
     image: &Image,
@@ -726,4 +1004,169 @@ mod tests {
         assert_eq!(AnimationDirection::Reverse as usize, 1);
         assert_eq!(AnimationDirection::PingPong as usize, 2);
     }
+
+    #[test]
+    fn animated_tiles_are_split_into_frames() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../agb/examples/gfx/water_tiles.aseprite");
+        let (image, durations) = crate::Image::load_animation_from_file(&path);
+
+        assert_eq!((image.width, image.height), (8, 64));
+        assert_eq!(durations.len(), 8);
+
+        let (image, dedup_data, animations) =
+            crate::deduplicator::deduplicate_animation(&image, 8, true, |_| 0);
+
+        assert_eq!(image.height / 8, 8);
+        assert_eq!(dedup_data[0].new_index, 0);
+        assert_eq!(animations.len(), 1);
+        assert_eq!(animations[0].tile, 0);
+        assert_eq!(animations[0].frames, (0..8).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn animation_frames_last_at_least_one_frame() {
+        assert_eq!(crate::frames_from_milliseconds(0), 1);
+        assert_eq!(crate::frames_from_milliseconds(100), 6);
+        assert_eq!(crate::frames_from_milliseconds(u32::MAX), u16::MAX);
+    }
+
+    fn parse_background_option(option: &str) -> syn::Result<crate::BackgroundGfxOption> {
+        syn::parse_str(option)
+    }
+
+    fn error_message(option: &str) -> String {
+        match parse_background_option(option) {
+            Ok(_) => panic!("{option} should not parse"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn affine_backgrounds_are_256_colours() {
+        let option = parse_background_option(r#"tiles => affine "tiles.png""#).unwrap();
+
+        assert!(option.affine);
+        assert!(matches!(option.colours, crate::Colours::Colours256));
+
+        let option =
+            parse_background_option(r#"tiles => 256 affine deduplicate "tiles.png""#).unwrap();
+        assert!(option.affine && option.deduplicate);
+    }
+
+    #[test]
+    fn affine_backgrounds_reject_16_colours() {
+        assert_eq!(
+            error_message(r#"tiles => 16 affine "tiles.png""#),
+            "Affine backgrounds must use 256 colours"
+        );
+    }
+
+    #[test]
+    fn affine_backgrounds_cannot_be_compressed_or_animated() {
+        for option in [
+            r#"tiles => affine compress "tiles.png""#,
+            r#"tiles => animated affine "tiles.aseprite""#,
+        ] {
+            assert_eq!(
+                error_message(option),
+                "Affine backgrounds cannot be compressed or animated"
+            );
+        }
+    }
+
+    /// An image with one 8x8 tile for each entry of `tiles`, where each tile
+    /// is red in the pixels set in the mask (counting from the top left) and
+    /// blue everywhere else.
+    fn tile_column(tiles: &[u16]) -> crate::Image {
+        use crate::Colour;
+
+        let red = Colour::from_rgb(255, 0, 0, 255);
+        let blue = Colour::from_rgb(0, 0, 255, 255);
+
+        let colour_data = tiles
+            .iter()
+            .flat_map(|&mask| {
+                (0..64).map(move |pixel| {
+                    if pixel < 16 && mask & (1 << pixel) != 0 {
+                        red
+                    } else {
+                        blue
+                    }
+                })
+            })
+            .collect();
+
+        crate::Image::from_colour_data(colour_data)
+    }
+
+    #[test]
+    fn affine_tiles_are_deduplicated_without_flips() {
+        // the second tile is the first flipped horizontally, and the third is a copy of the first
+        let image = tile_column(&[0b0000_0001, 0b1000_0000, 0b0000_0001]);
+
+        let (image, dedup_data) =
+            crate::rust_generator::deduplicate_affine(&image, "tiles.png").unwrap();
+
+        assert_eq!(image.height / 8, 2);
+        assert_eq!(
+            dedup_data
+                .iter()
+                .map(|data| data.new_index)
+                .collect::<Vec<_>>(),
+            [0, 1, 0]
+        );
+        assert!(dedup_data
+            .iter()
+            .all(|data| !data.transformation.hflip && !data.transformation.vflip));
+    }
+
+    #[test]
+    fn affine_backgrounds_can_only_use_256_tiles() {
+        // repeated tiles don't count towards the limit
+        let tiles = (0..256).chain(0..10).collect::<Vec<u16>>();
+        let (image, _) =
+            crate::rust_generator::deduplicate_affine(&tile_column(&tiles), "tiles.png").unwrap();
+        assert_eq!(image.height / 8, 256);
+
+        let tiles = (0..257).collect::<Vec<u16>>();
+        assert_eq!(
+            crate::rust_generator::deduplicate_affine(&tile_column(&tiles), "tiles.png")
+                .err()
+                .unwrap(),
+            "tiles.png has 257 unique tiles, but affine backgrounds can only use 256"
+        );
+    }
+
+    #[test]
+    fn paletted_bitmaps_index_colours_in_order_of_appearance() {
+        use crate::Colour;
+
+        let red = Colour::from_rgb(255, 0, 0, 255);
+        let blue = Colour::from_rgb(0, 0, 255, 255);
+        let image =
+            crate::Image::from_colour_data(vec![blue, red, blue, blue, red, red, blue, blue]);
+
+        let (palette, index_data) = crate::paletted_bitmap(&image).unwrap();
+
+        assert_eq!(palette, vec![blue.to_rgb15(), red.to_rgb15()]);
+        assert_eq!(index_data, vec![0, 1, 0, 0, 1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn paletted_bitmaps_reject_more_than_256_colours() {
+        use crate::Colour;
+
+        let colours = |count: u16| {
+            (0..count)
+                .map(|i| Colour::from_rgb((i % 32 * 8) as u8, (i / 32 * 8) as u8, 0, 255))
+                .collect::<Vec<_>>()
+        };
+
+        assert!(crate::paletted_bitmap(&crate::Image::from_colour_data(colours(256))).is_ok());
+        assert_eq!(
+            crate::paletted_bitmap(&crate::Image::from_colour_data(colours(264))).err(),
+            Some("Paletted bitmaps can only use 256 colours")
+        );
+    }
 }
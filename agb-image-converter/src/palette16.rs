@@ -145,6 +145,10 @@ impl Palette16Optimiser {
         }
     }
 
+    pub fn palettes(&self) -> &[Palette16] {
+        &self.palettes
+    }
+
     pub fn optimise_palettes(&self) -> Result<Palette16OptimisationResults, DoesNotFitError> {
         let transparent_colour = self
             .transparent_colour
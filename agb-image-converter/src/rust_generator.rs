@@ -35,6 +35,29 @@ pub(crate) fn generate_palette_code(
     }
 }
 
+/// Affine maps store each tile index in a single byte
+const MAX_AFFINE_TILES: usize = 256;
+
+/// Affine tiles are always deduplicated, and must fit in the tiles an affine
+/// map can refer to.
+pub(crate) fn deduplicate_affine(
+    image: &Image,
+    image_filename: &str,
+) -> Result<(Image, Vec<DeduplicatedData>), String> {
+    // Only 16 colour tiles can be flipped, affine tiles are always 256 colours
+    let (new_image, dedup_data) = crate::deduplicator::deduplicate_image(image, false);
+
+    let tile_count = new_image.width * new_image.height / 8 / 8;
+    if tile_count > MAX_AFFINE_TILES {
+        return Err(format!(
+            "{image_filename} has {tile_count} unique tiles, but affine backgrounds can only use {MAX_AFFINE_TILES}"
+        ));
+    }
+
+    Ok((new_image, dedup_data))
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn generate_code(
     output_variable_name: &str,
     results: &Palette16OptimisationResults,
@@ -43,15 +66,31 @@ pub(crate) fn generate_code(
     crate_prefix: String,
     assignment_offset: Option<usize>,
     deduplicate: bool,
+    compress: bool,
+    affine: bool,
+    frame_durations: Option<&[u16]>,
 ) -> TokenStream {
     let crate_prefix = format_ident!("{}", crate_prefix);
     let output_variable_name = format_ident!("{}", output_variable_name);
 
-    let (image, dedup_data) = if deduplicate {
+    let frames = frame_durations.map_or(1, <[u16]>::len);
+
+    let (image, dedup_data, animated_tiles) = if frame_durations.is_some() {
+        let all_assignments = assignment_offset.map(|offset| &results.assignments[offset..]);
+
+        crate::deduplicator::deduplicate_animation(image, frames, deduplicate, |tile| {
+            all_assignments.map_or(0, |assignments| assignments[tile])
+        })
+    } else if affine {
+        match deduplicate_affine(image, image_filename) {
+            Ok((new_image, dedup_data)) => (new_image, dedup_data, vec![]),
+            Err(message) => return quote! { compile_error!(#message); },
+        }
+    } else if deduplicate {
         let (new_image, dedup_data) =
             crate::deduplicator::deduplicate_image(image, assignment_offset.is_some());
 
-        (new_image, dedup_data)
+        (new_image, dedup_data, vec![])
     } else {
         (
             image.clone(),
@@ -61,6 +100,7 @@ pub(crate) fn generate_code(
                     transformation: Transformation::none(),
                 })
                 .collect(),
+            vec![],
         )
     };
 
@@ -102,7 +142,8 @@ pub(crate) fn generate_code(
         (tile_data, vec![])
     };
 
-    let tile_settings = dedup_data.iter().map(|data| {
+    // Only the first frame of an animation is placed on the map
+    let tile_settings = dedup_data.iter().take(dedup_data.len() / frames).map(|data| {
         let palette_assignment = assignments.get(data.new_index).unwrap_or(&0);
         let vflipped = data.transformation.vflip;
         let hflipped = data.transformation.hflip;
@@ -113,6 +154,12 @@ pub(crate) fn generate_code(
         }
     });
 
+    let tile_data = if compress {
+        agb_compression::compress_smallest(&tile_data, &agb_compression::Compression::ALL)
+    } else {
+        tile_data
+    };
+
     let data = ByteString(&tile_data);
     let tile_format = if assignment_offset.is_some() {
         quote! { #crate_prefix::display::tiled::TileFormat::FourBpp }
@@ -120,9 +167,87 @@ pub(crate) fn generate_code(
         quote! { #crate_prefix::display::tiled::TileFormat::EightBpp }
     };
 
+    let animations = animated_tiles.iter().enumerate().map(|(i, animated_tile)| {
+        let frames_name = format_ident!("FRAMES_{}", i);
+        let tile = animated_tile.tile as u16;
+        let frames = animated_tile
+            .frames
+            .iter()
+            .zip(frame_durations.unwrap_or_default())
+            .map(|(&frame, &duration)| {
+                let frame = frame as u16;
+                quote! { #crate_prefix::display::tiled::AnimationFrame::new(#frame, #duration) }
+            });
+
+        (
+            quote! {
+                const #frames_name: &[#crate_prefix::display::tiled::AnimationFrame] = &[
+                    #(#frames),*
+                ];
+            },
+            quote! { #crate_prefix::display::tiled::TileAnimation::new(#tile, #frames_name) },
+        )
+    });
+    let (animation_frames, animations): (Vec<_>, Vec<_>) = animations.unzip();
+
+    let (tile_data_type, tile_set_type) = if compress {
+        (
+            quote! { #crate_prefix::display::tile_data::CompressedTileData },
+            quote! { #crate_prefix::display::tiled::CompressedTileSet },
+        )
+    } else if affine {
+        (
+            quote! { #crate_prefix::display::tile_data::AffineTileData },
+            quote! { #crate_prefix::display::tiled::TileSet },
+        )
+    } else if frame_durations.is_some() {
+        (
+            quote! { #crate_prefix::display::tile_data::AnimatedTileData },
+            quote! { #crate_prefix::display::tiled::TileSet },
+        )
+    } else {
+        (
+            quote! { #crate_prefix::display::tile_data::TileData },
+            quote! { #crate_prefix::display::tiled::TileSet },
+        )
+    };
+
+    let constructor = if affine {
+        // Affine maps only store the tile index, with no flips or palette
+        let tile_indices = dedup_data.iter().map(|data| data.new_index as u16);
+
+        quote! {
+            const TILE_INDICES: &[u16] = &[#(#tile_indices),*];
+
+            #tile_data_type::new(TILE_SET, TILE_INDICES)
+        }
+    } else if frame_durations.is_some() {
+        quote! {
+            const TILE_SETTINGS: &[#crate_prefix::display::tiled::TileSetting] = &[
+                #(#tile_settings),*
+            ];
+
+            #(#animation_frames)*
+
+            const ANIMATIONS: &[#crate_prefix::display::tiled::TileAnimation<'static>] = &[
+                #(#animations),*
+            ];
+
+            #tile_data_type::new(TILE_SET, TILE_SETTINGS, ANIMATIONS)
+        }
+    } else {
+        quote! {
+            const TILE_SETTINGS: &[#crate_prefix::display::tiled::TileSetting] = &[
+                #(#tile_settings),*
+            ];
+
+            #tile_data_type::new(TILE_SET, TILE_SETTINGS)
+        }
+    };
+
     quote! {
         #[allow(non_upper_case_globals)]
-        pub static #output_variable_name: #crate_prefix::display::tile_data::TileData = {
+        pub static #output_variable_name: #tile_data_type = {
             const _: &[u8] = include_bytes!(#image_filename);
 
             const TILE_DATA: &[u8] = {
@@ -139,13 +264,9 @@ pub(crate) fn generate_code(
                 &ALIGNED.bytes
             };
 
-            const TILE_SET: #crate_prefix::display::tiled::TileSet = #crate_prefix::display::tiled::TileSet::new(TILE_DATA, #tile_format);
-
-            const TILE_SETTINGS: &[#crate_prefix::display::tiled::TileSetting] = &[
-                #(#tile_settings),*
-            ];
+            const TILE_SET: #tile_set_type = #tile_set_type::new(TILE_DATA, #tile_format);
 
-            #crate_prefix::display::tile_data::TileData::new(TILE_SET, TILE_SETTINGS)
+            #constructor
         };
     }
 }
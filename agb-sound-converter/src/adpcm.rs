@@ -0,0 +1,187 @@
+//! Encoder for the 4-bit IMA ADPCM format played by agb's `AdpcmSource`.
+//!
+//! The output starts with an 8 byte header: the number of channels (1 or 2),
+//! 3 bytes of padding and then the number of sample frames as a little endian
+//! u32. After that come blocks of [`BLOCK_FRAMES`] frames. Each block starts
+//! with the decoder state for every channel (the predicted sample as a little
+//! endian i16, the step index and a byte of padding) followed by one nibble
+//! per sample, low nibble first, with stereo samples interleaved left then
+//! right. The last block is padded with zeros.
+//!
+//! Starting every block with the decoder state means playback can jump to the
+//! start of any block, which is how loops work.
+
+pub const BLOCK_FRAMES: usize = 1024;
+
+const INDEX_TABLE: [i8; 16] = [-1, -1, -1, -1, 2, 4, 6, 8, -1, -1, -1, -1, 2, 4, 6, 8];
+
+const STEP_TABLE: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+    73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449,
+    494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272,
+    2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493,
+    10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+
+#[derive(Clone, Copy, Default)]
+struct ChannelState {
+    predictor: i32,
+    index: usize,
+}
+
+impl ChannelState {
+    fn encode(&mut self, sample: i16) -> u8 {
+        let step = STEP_TABLE[self.index];
+        let mut diff = i32::from(sample) - self.predictor;
+
+        let mut nibble = 0;
+        if diff < 0 {
+            nibble = 8;
+            diff = -diff;
+        }
+
+        if diff >= step {
+            nibble |= 4;
+            diff -= step;
+        }
+        if diff >= step >> 1 {
+            nibble |= 2;
+            diff -= step >> 1;
+        }
+        if diff >= step >> 2 {
+            nibble |= 1;
+        }
+
+        // keep in step with what the decoder will produce
+        self.decode(nibble);
+        nibble
+    }
+
+    fn decode(&mut self, nibble: u8) -> i16 {
+        let step = STEP_TABLE[self.index];
+
+        let mut diff = step >> 3;
+        if nibble & 4 != 0 {
+            diff += step;
+        }
+        if nibble & 2 != 0 {
+            diff += step >> 1;
+        }
+        if nibble & 1 != 0 {
+            diff += step >> 2;
+        }
+
+        if nibble & 8 != 0 {
+            self.predictor -= diff;
+        } else {
+            self.predictor += diff;
+        }
+
+        self.predictor = self
+            .predictor
+            .clamp(i32::from(i16::MIN), i32::from(i16::MAX));
+        self.index =
+            (self.index as i32 + i32::from(INDEX_TABLE[nibble as usize])).clamp(0, 88) as usize;
+
+        self.predictor as i16
+    }
+}
+
+/// Encodes interleaved 16-bit `samples` with `channels` channels.
+pub fn encode(samples: &[i16], channels: usize) -> Vec<u8> {
+    assert!(
+        channels == 1 || channels == 2,
+        "ADPCM sounds must be mono or stereo"
+    );
+
+    let frames = samples.len() / channels;
+
+    let mut output = vec![channels as u8, 0, 0, 0];
+    output.extend_from_slice(&(frames as u32).to_le_bytes());
+
+    let mut states = [ChannelState::default(); 2];
+
+    for block in samples.chunks(BLOCK_FRAMES * channels) {
+        for state in &states[..channels] {
+            output.extend_from_slice(&(state.predictor as i16).to_le_bytes());
+            output.extend_from_slice(&[state.index as u8, 0]);
+        }
+
+        let mut nibbles = block
+            .iter()
+            .enumerate()
+            .map(|(i, &sample)| states[i % channels].encode(sample))
+            .chain(std::iter::repeat(0))
+            .take(BLOCK_FRAMES * channels);
+
+        while let (Some(low), Some(high)) = (nibbles.next(), nibbles.next()) {
+            output.push(low | (high << 4));
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(data: &[u8]) -> Vec<i16> {
+        let channels = data[0] as usize;
+        let frames = u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize;
+        let block_size = channels * 4 + BLOCK_FRAMES * channels / 2;
+
+        let mut output = vec![];
+        for block in data[8..].chunks(block_size) {
+            let mut states = [ChannelState::default(); 2];
+            for (channel, state) in states[..channels].iter_mut().enumerate() {
+                let header = &block[channel * 4..];
+                state.predictor = i32::from(i16::from_le_bytes([header[0], header[1]]));
+                state.index = header[2] as usize;
+            }
+
+            for (i, &byte) in block[channels * 4..].iter().enumerate() {
+                output.push(states[(i * 2) % channels].decode(byte & 0xf));
+                output.push(states[(i * 2 + 1) % channels].decode(byte >> 4));
+            }
+        }
+
+        output.truncate(frames * channels);
+        output
+    }
+
+    #[test]
+    fn encoded_sine_wave_decodes_closely() {
+        let samples: Vec<i16> = (0..3000)
+            .map(|i| ((i as f32 * 0.05).sin() * 20000.0) as i16)
+            .collect();
+
+        let encoded = encode(&samples, 1);
+        assert_eq!(encoded.len(), 8 + 3 * (4 + BLOCK_FRAMES / 2));
+
+        let decoded = decode(&encoded);
+        assert_eq!(decoded.len(), samples.len());
+
+        // give the predictor a little time to catch up with the start
+        for (&original, &decoded) in samples.iter().zip(&decoded).skip(50) {
+            assert!(
+                (i32::from(original) - i32::from(decoded)).abs() < 1000,
+                "{original} decoded as {decoded}"
+            );
+        }
+    }
+
+    #[test]
+    fn stereo_channels_are_encoded_separately() {
+        let samples: Vec<i16> = (0..200).flat_map(|i| [i * 100, -i * 100]).collect();
+
+        let decoded = decode(&encode(&samples, 2));
+
+        for (frame, decoded) in samples.chunks(2).zip(decoded.chunks(2)).skip(20) {
+            assert!(
+                decoded[0] > 0 && decoded[1] < 0,
+                "{frame:?} decoded as {decoded:?}"
+            );
+        }
+    }
+}
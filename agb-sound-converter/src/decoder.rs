@@ -0,0 +1,104 @@
+//! Decodes the compressed formats which `include_wav!` accepts alongside wav
+//! files.
+
+use std::path::Path;
+
+use symphonia::core::{
+    audio::SampleBuffer, codecs::DecoderOptions, errors::Error, formats::FormatOptions,
+    io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+};
+
+use crate::processing::Sound;
+
+/// Decodes the first audio track of an ogg (vorbis), flac or mp3 file.
+pub fn decode(path: &Path) -> Sound {
+    let display_path = path.to_string_lossy();
+
+    let file =
+        std::fs::File::open(path).unwrap_or_else(|_| panic!("Failed to load file {display_path}"));
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .unwrap_or_else(|e| panic!("Unsupported sound file {display_path}: {e}"));
+    let mut format = probed.format;
+
+    let track = format
+        .default_track()
+        .unwrap_or_else(|| panic!("No audio in {display_path}"));
+    let track_id = track.id;
+
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .unwrap_or_else(|e| panic!("Unsupported audio in {display_path}: {e}"));
+
+    let mut channels = track
+        .codec_params
+        .channels
+        .map_or(0, |channels| channels.count());
+    let mut sample_rate = track.codec_params.sample_rate.unwrap_or(0);
+    let mut samples = vec![];
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => panic!("Failed to read {display_path}: {e}"),
+        };
+
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // a corrupt packet is skipped rather than failing the whole sound
+            Err(Error::DecodeError(_)) => continue,
+            Err(e) => panic!("Failed to decode {display_path}: {e}"),
+        };
+
+        let spec = *decoded.spec();
+        channels = spec.channels.count();
+        sample_rate = spec.rate;
+
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+        samples.extend_from_slice(buffer.samples());
+    }
+
+    assert!(channels > 0, "No audio in {display_path}");
+
+    Sound {
+        channels,
+        sample_rate,
+        samples,
+        loop_points: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flac_files_are_decoded() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/ramp.flac");
+        let sound = decode(&path);
+
+        assert_eq!(sound.channels, 1);
+        assert_eq!(sound.sample_rate, 8000);
+
+        let expected: Vec<i16> = (0..16).map(|i| i * 1000 - 8000).collect();
+        assert_eq!(sound.to_i16(), expected);
+    }
+}
@@ -4,7 +4,11 @@ use proc_macro::TokenStream;
 use proc_macro2::Literal;
 use quote::{quote, ToTokens};
 use std::path::Path;
-use syn::parse_macro_input;
+use syn::{parse::Parse, parse_macro_input, LitInt, LitStr, Token};
+
+mod adpcm;
+mod decoder;
+mod processing;
 
 use quote::TokenStreamExt;
 struct ByteString<'a>(&'a [u8]);
@@ -14,21 +18,160 @@ impl ToTokens for ByteString<'_> {
     }
 }
 
+struct IncludeWavInput {
+    filename: LitStr,
+    compress: bool,
+    adpcm: bool,
+    resample: Option<u32>,
+    mono: bool,
+    normalise: bool,
+    trim_silence: bool,
+    smpl_loop: bool,
+}
+
+impl Parse for IncludeWavInput {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let mut result = Self {
+            filename: input.parse()?,
+            compress: false,
+            adpcm: false,
+            resample: None,
+            mono: false,
+            normalise: false,
+            trim_silence: false,
+            smpl_loop: false,
+        };
+
+        while input.peek(Token![,]) {
+            let _: Token![,] = input.parse()?;
+            let option: syn::Ident = input.parse()?;
+
+            if option == "compress" {
+                result.compress = true;
+            } else if option == "adpcm" {
+                result.adpcm = true;
+            } else if option == "mono" {
+                result.mono = true;
+            } else if option == "normalise" {
+                result.normalise = true;
+            } else if option == "trim_silence" {
+                result.trim_silence = true;
+            } else if option == "smpl_loop" {
+                result.smpl_loop = true;
+            } else if option == "resample" {
+                let _: Token![=] = input.parse()?;
+                let frequency: LitInt = input.parse()?;
+                let value = frequency.base10_parse()?;
+                if value == 0 {
+                    return Err(syn::Error::new_spanned(
+                        frequency,
+                        "Can't resample to a frequency of 0",
+                    ));
+                }
+                result.resample = Some(value);
+            } else {
+                return Err(syn::Error::new_spanned(
+                    option,
+                    "Options must be one of compress, adpcm, resample = <frequency>, mono, normalise, trim_silence or smpl_loop",
+                ));
+            }
+
+            if result.compress && result.adpcm {
+                return Err(syn::Error::new_spanned(
+                    option,
+                    "ADPCM sounds are played straight from ROM so can't also be compressed",
+                ));
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// Includes a sound file as 8-bit samples for the mixer. As well as wav files,
+/// this can read ogg (vorbis), flac and mp3 files, which are decoded at build
+/// time.
+///
+/// Options can be given after the file name, separated by commas:
+///
+/// * `resample = <frequency>` resamples the sound to the given frequency, which
+///   should be the frequency of your mixer, for example `resample = 18157`.
+/// * `mono` mixes every channel down to one.
+/// * `normalise` scales the sound so its loudest point is at full volume.
+/// * `trim_silence` removes silence from the start and end of the sound.
+/// * `smpl_loop` reads the first loop from the wav file's `smpl` chunk, and
+///   removes everything after the end of the loop. The macro then gives a
+///   tuple of the data and the frame the loop starts at, to pass to
+///   `SoundChannel::restart_point` or `AdpcmSource::looping`.
+/// * `compress` compresses the samples so they can be decompressed into RAM.
+/// * `adpcm` encodes the sound as 4-bit ADPCM, to be played with `AdpcmSource`.
+///
+/// ```rust,ignore
+/// static JUMP: &[u8] = include_wav!("sfx/jump.wav", resample = 18157, mono, trim_silence);
+/// static BGM: (&[u8], u32) = include_wav!("music/bgm.wav", resample = 18157, smpl_loop);
+/// ```
 #[proc_macro]
 pub fn include_wav(input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as syn::LitStr);
+    let input = parse_macro_input!(input as IncludeWavInput);
 
-    let filename = input.value();
+    let filename = input.filename.value();
 
     let root = std::env::var("CARGO_MANIFEST_DIR").expect("Failed to get cargo manifest dir");
     let path = Path::new(&root).join(&*filename);
 
     let include_path = path.to_string_lossy();
 
-    let wav_reader = hound::WavReader::open(&path)
-        .unwrap_or_else(|_| panic!("Failed to load file {include_path}"));
+    let is_wav = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("wav"));
+
+    let mut sound = if is_wav {
+        let wav_reader = hound::WavReader::open(&path)
+            .unwrap_or_else(|_| panic!("Failed to load file {include_path}"));
+
+        sound_from_reader(wav_reader)
+    } else {
+        decoder::decode(&path)
+    };
+
+    if input.smpl_loop {
+        assert!(is_wav, "smpl_loop only works with wav files");
+
+        let wav =
+            std::fs::read(&path).unwrap_or_else(|_| panic!("Failed to load file {include_path}"));
+        sound.loop_points = Some(
+            processing::read_loop_points(&wav)
+                .unwrap_or_else(|| panic!("No loop found in the smpl chunk of {include_path}")),
+        );
+        sound.cut_at_loop_end();
+    }
 
-    let samples: Vec<u8> = samples_from_reader(wav_reader).collect();
+    if input.trim_silence {
+        sound.trim_silence();
+    }
+    if input.mono {
+        sound.mix_to_mono();
+    }
+    if let Some(frequency) = input.resample {
+        sound.resample(frequency);
+    }
+    if input.normalise {
+        sound.normalise();
+    }
+
+    let samples = sound.to_i16();
+
+    let samples = if input.adpcm {
+        adpcm::encode(&samples, sound.channels)
+    } else {
+        let samples: Vec<u8> = samples.iter().map(|&sample| (sample >> 8) as u8).collect();
+
+        if input.compress {
+            agb_compression::compress_smallest(&samples, &agb_compression::Compression::ALL)
+        } else {
+            samples
+        }
+    };
     let samples = ByteString(&samples);
 
     let result = quote! {
@@ -42,26 +185,44 @@ pub fn include_wav(input: TokenStream) -> TokenStream {
         }
     };
 
+    // the loop start is only known once the sound has been processed, so it is returned alongside the data
+    let result = match sound.loop_points {
+        Some((loop_start, _)) if input.smpl_loop => {
+            let loop_start = loop_start as u32;
+            quote! {
+                (#result, #loop_start)
+            }
+        }
+        _ => result,
+    };
+
     TokenStream::from(result)
 }
 
-fn samples_from_reader<'a, R>(reader: hound::WavReader<R>) -> Box<dyn Iterator<Item = u8> + 'a>
+fn sound_from_reader<R>(reader: hound::WavReader<R>) -> processing::Sound
 where
-    R: std::io::Read + 'a,
+    R: std::io::Read,
 {
-    let bitrate = reader.spec().bits_per_sample;
-    let reduction = bitrate - 8;
+    let spec = reader.spec();
 
-    match reader.spec().sample_format {
-        hound::SampleFormat::Float => Box::new(
-            reader
-                .into_samples::<f32>()
-                .map(|sample| (sample.unwrap() * (i8::MAX as f32)) as u8),
-        ),
-        hound::SampleFormat::Int => Box::new(
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .into_samples::<f32>()
+            .map(|sample| sample.unwrap())
+            .collect(),
+        hound::SampleFormat::Int => {
+            let scale = (1u64 << (spec.bits_per_sample - 1)) as f32;
             reader
                 .into_samples::<i32>()
-                .map(move |sample| (sample.unwrap() >> reduction) as u8),
-        ),
+                .map(|sample| sample.unwrap() as f32 / scale)
+                .collect()
+        }
+    };
+
+    processing::Sound {
+        channels: spec.channels.into(),
+        sample_rate: spec.sample_rate,
+        samples,
+        loop_points: None,
     }
 }
@@ -0,0 +1,257 @@
+//! Changes made to a sound before it is converted for the mixer, so source
+//! files can be kept at whatever quality and layout they were made in.
+
+/// Samples quieter than this are below what the 8-bit mixer can play.
+const SILENCE_THRESHOLD: f32 = 1.0 / 256.0;
+
+/// Interleaved samples between -1 and 1.
+pub struct Sound {
+    pub channels: usize,
+    pub sample_rate: u32,
+    pub samples: Vec<f32>,
+    /// The first and last frame (inclusive) of the loop, if there is one
+    pub loop_points: Option<(usize, usize)>,
+}
+
+impl Sound {
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+
+    /// Removes everything after the end of the loop, so the sound goes back to
+    /// the start of the loop as soon as it reaches the end.
+    pub fn cut_at_loop_end(&mut self) {
+        if let Some((start, end)) = self.loop_points {
+            let end = end.min(self.frames().saturating_sub(1));
+            self.loop_points = Some((start.min(end), end));
+            self.samples.truncate((end + 1) * self.channels);
+        }
+    }
+
+    /// Removes silent frames from the start and end of the sound, moving the
+    /// loop points to match.
+    pub fn trim_silence(&mut self) {
+        let is_silent = |frame: &[f32]| frame.iter().all(|s| s.abs() < SILENCE_THRESHOLD);
+
+        let frames: Vec<&[f32]> = self.samples.chunks(self.channels).collect();
+        let start = frames
+            .iter()
+            .position(|frame| !is_silent(frame))
+            .unwrap_or(frames.len());
+        let end = frames
+            .iter()
+            .rposition(|frame| !is_silent(frame))
+            .map_or(start, |end| end + 1);
+
+        // never trim into the loop
+        let (start, end) = match self.loop_points {
+            Some((loop_start, loop_end)) => (start.min(loop_start), end.max(loop_end + 1)),
+            None => (start, end),
+        };
+
+        self.samples = self.samples[start * self.channels..end * self.channels].to_vec();
+        self.loop_points = self
+            .loop_points
+            .map(|(loop_start, loop_end)| (loop_start - start, loop_end - start));
+    }
+
+    /// Mixes every channel down to one.
+    pub fn mix_to_mono(&mut self) {
+        let channels = self.channels;
+
+        self.samples = self
+            .samples
+            .chunks(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect();
+        self.channels = 1;
+    }
+
+    /// Resamples to `sample_rate` using linear interpolation, scaling the
+    /// loop points to match. Anything after the end of the loop is removed so
+    /// it isn't played on every pass.
+    pub fn resample(&mut self, sample_rate: u32) {
+        if sample_rate == self.sample_rate || self.samples.is_empty() {
+            self.sample_rate = sample_rate;
+            return;
+        }
+
+        let ratio = f64::from(self.sample_rate) / f64::from(sample_rate);
+        let frames = self.frames();
+        let new_frames = ((frames as f64) / ratio).round().max(1.0) as usize;
+
+        let mut samples = Vec::with_capacity(new_frames * self.channels);
+        for frame in 0..new_frames {
+            let pos = frame as f64 * ratio;
+            let before = (pos.floor() as usize).min(frames - 1);
+            let after = (before + 1).min(frames - 1);
+            let amount = (pos - before as f64) as f32;
+
+            for channel in 0..self.channels {
+                let a = self.samples[before * self.channels + channel];
+                let b = self.samples[after * self.channels + channel];
+                samples.push(a + (b - a) * amount);
+            }
+        }
+
+        let scale = |frame: usize| (((frame as f64) / ratio).round() as usize).min(new_frames - 1);
+
+        self.samples = samples;
+        self.sample_rate = sample_rate;
+        self.loop_points = self.loop_points.map(|(start, end)| {
+            let start = scale(start);
+            // the loop ends at the end of its last frame, not the start
+            let end = ((((end + 1) as f64) / ratio).round() as usize).clamp(start + 1, new_frames);
+            (start, end - 1)
+        });
+
+        if let Some((_, end)) = self.loop_points {
+            self.samples.truncate((end + 1) * self.channels);
+        }
+    }
+
+    /// Scales the sound so its loudest sample is at full volume.
+    pub fn normalise(&mut self) {
+        let peak = self.samples.iter().fold(0f32, |peak, s| peak.max(s.abs()));
+        if peak == 0.0 {
+            return;
+        }
+
+        for sample in &mut self.samples {
+            *sample /= peak;
+        }
+    }
+
+    pub fn to_i16(&self) -> Vec<i16> {
+        self.samples
+            .iter()
+            .map(|s| (s * 32768.0).floor().clamp(-32768.0, 32767.0) as i16)
+            .collect()
+    }
+}
+
+/// Finds the first loop in the `smpl` chunk of a wav file, returning its first
+/// and last frame.
+pub fn read_loop_points(wav: &[u8]) -> Option<(usize, usize)> {
+    let read_u32 = |bytes: &[u8], offset: usize| -> Option<u32> {
+        Some(u32::from_le_bytes(
+            bytes.get(offset..offset + 4)?.try_into().ok()?,
+        ))
+    };
+
+    if wav.get(0..4)? != b"RIFF" || wav.get(8..12)? != b"WAVE" {
+        return None;
+    }
+
+    let mut offset = 12;
+    while offset + 8 <= wav.len() {
+        let id = &wav[offset..offset + 4];
+        let size = read_u32(wav, offset + 4)? as usize;
+        let chunk = wav.get(offset + 8..(offset + 8 + size).min(wav.len()))?;
+
+        if id == b"smpl" {
+            let loop_count = read_u32(chunk, 28)?;
+            if loop_count == 0 {
+                return None;
+            }
+
+            // skip the 36 byte header, the cue point id and the loop type
+            let start = read_u32(chunk, 36 + 8)? as usize;
+            let end = read_u32(chunk, 36 + 12)? as usize;
+            return Some((start, end));
+        }
+
+        // chunks are padded to an even length
+        offset += 8 + size + (size & 1);
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sound(channels: usize, samples: &[f32]) -> Sound {
+        Sound {
+            channels,
+            sample_rate: 32768,
+            samples: samples.to_vec(),
+            loop_points: None,
+        }
+    }
+
+    #[test]
+    fn trim_silence_keeps_the_loop() {
+        let mut sound = sound(1, &[0.0, 0.0, 0.5, 0.5, 0.0, 0.0, 0.0]);
+        sound.trim_silence();
+        assert_eq!(sound.samples, &[0.5, 0.5]);
+
+        let mut sound = sound_with_loop(&[0.0, 0.0, 0.5, 0.5, 0.0, 0.0, 0.0], (1, 4));
+        sound.trim_silence();
+        assert_eq!(sound.samples, &[0.0, 0.5, 0.5, 0.0]);
+        assert_eq!(sound.loop_points, Some((0, 3)));
+    }
+
+    fn sound_with_loop(samples: &[f32], loop_points: (usize, usize)) -> Sound {
+        Sound {
+            loop_points: Some(loop_points),
+            ..sound(1, samples)
+        }
+    }
+
+    #[test]
+    fn stereo_mixes_down_to_mono() {
+        let mut sound = sound(2, &[1.0, 0.0, -0.5, -0.5]);
+        sound.mix_to_mono();
+        assert_eq!(sound.channels, 1);
+        assert_eq!(sound.samples, &[0.5, -0.5]);
+    }
+
+    #[test]
+    fn resampling_interpolates_and_moves_loop_points() {
+        let mut sound = sound_with_loop(&[0.0, 1.0, 0.0, -1.0], (2, 3));
+        sound.sample_rate = 10000;
+        sound.resample(20000);
+
+        assert_eq!(sound.samples, &[0.0, 0.5, 1.0, 0.5, 0.0, -0.5, -1.0, -1.0]);
+        assert_eq!(sound.loop_points, Some((4, 7)));
+    }
+
+    #[test]
+    fn resampling_removes_the_tail_after_the_loop() {
+        let mut sound = sound_with_loop(&[0.0, 0.25, 0.5, 0.75, 1.0, 1.0], (0, 3));
+        sound.sample_rate = 20000;
+        sound.resample(10000);
+
+        assert_eq!(sound.samples, &[0.0, 0.5]);
+        assert_eq!(sound.loop_points, Some((0, 1)));
+    }
+
+    #[test]
+    fn normalise_scales_to_full_volume() {
+        let mut sound = sound(1, &[0.25, -0.5]);
+        sound.normalise();
+        assert_eq!(sound.samples, &[0.5, -1.0]);
+        assert_eq!(sound.to_i16(), &[16384, -32768]);
+    }
+
+    #[test]
+    fn loop_points_are_read_from_the_smpl_chunk() {
+        let mut smpl = vec![0u8; 36 + 24];
+        smpl[28..32].copy_from_slice(&1u32.to_le_bytes());
+        smpl[44..48].copy_from_slice(&100u32.to_le_bytes());
+        smpl[48..52].copy_from_slice(&2000u32.to_le_bytes());
+
+        let mut wav = b"RIFF\0\0\0\0WAVE".to_vec();
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&3u32.to_le_bytes());
+        wav.extend_from_slice(&[1, 2, 3, 0]);
+        wav.extend_from_slice(b"smpl");
+        wav.extend_from_slice(&(smpl.len() as u32).to_le_bytes());
+        wav.extend_from_slice(&smpl);
+
+        assert_eq!(read_loop_points(&wav), Some((100, 2000)));
+        assert_eq!(read_loop_points(&wav[..24]), None);
+    }
+}
@@ -0,0 +1,75 @@
+#![no_std]
+#![no_main]
+
+// Plays the same music as plain 8-bit samples and then as ADPCM, writing how
+// long `mixer.frame()` takes in each case to the emulator's debug output.
+
+use agb::{
+    include_wav,
+    sound::mixer::{AdpcmSource, Frequency, SoundChannel},
+    timing::{self, CpuMeter},
+    Gba,
+};
+
+// Music - "Dead Code" by Josh Woodward, free download at http://joshwoodward.com
+static DEAD_CODE: &[u8] = include_wav!("examples/JoshWoodward-DeadCode.wav");
+static DEAD_CODE_ADPCM: &[u8] = include_wav!("examples/JoshWoodward-DeadCode.wav", adpcm);
+
+// Music - "Let it in" by Josh Woodward, free download at http://joshwoodward.com
+static LET_IT_IN: &[u8] = include_wav!("examples/JoshWoodward-LetItIn.wav");
+static LET_IT_IN_ADPCM: &[u8] = include_wav!("examples/JoshWoodward-LetItIn.wav", adpcm);
+
+const FRAMES_PER_SOUND: u32 = 300;
+
+#[agb::entry]
+fn main(mut gba: Gba) -> ! {
+    let vblank_provider = agb::interrupt::VBlank::get();
+
+    let mut timers = gba.timers.timers();
+    let mut meter = CpuMeter::new(&mut timers);
+
+    let mut mixer = gba.mixer.mixer(Frequency::Hz10512);
+    mixer.enable();
+
+    let mut stereo = SoundChannel::new(LET_IT_IN);
+    stereo.stereo();
+
+    let sounds = [
+        ("mono 8-bit", SoundChannel::new(DEAD_CODE)),
+        (
+            "mono adpcm",
+            SoundChannel::from_adpcm(AdpcmSource::new(DEAD_CODE_ADPCM)),
+        ),
+        ("stereo 8-bit", stereo),
+        (
+            "stereo adpcm",
+            SoundChannel::from_adpcm(AdpcmSource::new(LET_IT_IN_ADPCM)),
+        ),
+    ];
+
+    for (name, sound) in sounds {
+        let channel_id = mixer.play_sound(sound).unwrap();
+        meter.reset();
+
+        while meter.frames() < FRAMES_PER_SOUND {
+            {
+                let _g = timing::scope(name);
+                mixer.frame();
+            }
+
+            meter.frame();
+            vblank_provider.wait_for_vblank();
+        }
+
+        meter.report();
+
+        if let Some(channel) = mixer.channel(&channel_id) {
+            channel.stop();
+        }
+    }
+
+    loop {
+        mixer.frame();
+        vblank_provider.wait_for_vblank();
+    }
+}
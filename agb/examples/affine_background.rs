@@ -11,7 +11,7 @@ use agb::{
     include_background_gfx,
 };
 
-include_background_gfx!(affine_tiles, "3f3f74", water_tiles => 256 "examples/water_tiles.png");
+include_background_gfx!(affine_tiles, "3f3f74", water_tiles => affine "examples/water_tiles.png");
 
 #[agb::entry]
 fn main(mut gba: agb::Gba) -> ! {
@@ -26,7 +26,12 @@ fn main(mut gba: agb::Gba) -> ! {
 
     for y in 0..32u16 {
         for x in 0..32u16 {
-            bg.set_tile(&mut vram, (x, y), tileset, 1);
+            bg.set_tile(
+                &mut vram,
+                (x, y),
+                tileset,
+                affine_tiles::water_tiles.tile_indices[1],
+            );
         }
     }
 
@@ -9,7 +9,7 @@ use agb::{
     include_background_gfx,
 };
 
-include_background_gfx!(water_tiles, water_tiles => "examples/water_tiles.png");
+include_background_gfx!(water_tiles, water_tiles => animated "examples/gfx/water_tiles.aseprite");
 
 #[agb::entry]
 fn main(mut gba: agb::Gba) -> ! {
@@ -40,11 +40,10 @@ fn main(mut gba: agb::Gba) -> ! {
     bg.commit(&mut vram);
     bg.set_visible(true);
 
-    let mut i = 0;
-    loop {
-        i = (i + 1) % 8;
+    vram.add_tile_animator(water_tiles::water_tiles.animator());
 
-        vram.replace_tile(tileset, 0, tileset, i);
+    loop {
+        vram.update_tile_animators();
 
         vblank.wait_for_vblank();
     }
@@ -23,6 +23,20 @@ fn main(gba: agb::Gba) -> ! {
         sound::dmg::DutyCycle::Half,
     );
 
+    const TRIANGLE: sound::dmg::Wave = [
+        0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54, 0x32,
+        0x10,
+    ];
+
+    let channel3 = gba.sound.channel3();
+    channel3.load_wave(sound::dmg::WaveBank::Bank0, &TRIANGLE);
+    channel3.play_sound(
+        1750,
+        Some(0),
+        sound::dmg::WaveVolume::Half,
+        sound::dmg::WaveBankMode::Single(sound::dmg::WaveBank::Bank0),
+    );
+
     gba.sound.noise().play_sound(
         Some(0),
         &sound::dmg::EnvelopeSettings::default(),
@@ -52,6 +52,15 @@ pub struct BlockAllocator {
     inner: UnsafeCell<BlockAllocatorInner>,
 }
 
+/// How much of the memory managed by a block allocator is in use, in bytes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct BlockAllocatorStats {
+    pub capacity: usize,
+    pub used: usize,
+    pub largest_free_block: usize,
+    pub free_blocks: usize,
+}
+
 unsafe impl Sync for BlockAllocator {}
 
 impl BlockAllocator {
@@ -79,6 +88,33 @@ impl BlockAllocator {
         self.with_inner(|inner| inner.dealloc(ptr, layout));
     }
 
+    /// Frees `layout.size()` bytes starting at `ptr`, which only needs to be
+    /// part of an allocation rather than the whole of one. This lets a large
+    /// allocation be freed a piece at a time.
+    ///
+    /// This works because an allocation whose size is a multiple of 8 takes up
+    /// exactly that many bytes, and free memory is tracked by address. Each piece
+    /// is freed through [`dealloc`](Self::dealloc), which merges it with any free
+    /// blocks directly either side of it, so once every piece of an allocation
+    /// has been freed it is a single free block again, exactly as if it had
+    /// been freed all at once. If `dealloc` stopped merging neighbouring blocks,
+    /// the pieces would stay as separate small blocks and could never be used
+    /// for an allocation of the original size.
+    ///
+    /// # Safety
+    ///
+    /// The bytes being freed must be within an allocation from this allocator
+    /// whose size is a multiple of 8, and must not have been freed already.
+    /// `ptr` must be 8 byte aligned and `layout.size()` a multiple of 8.
+    pub unsafe fn dealloc_part(&self, ptr: *mut u8, layout: Layout) {
+        debug_assert!(
+            (ptr as usize).is_multiple_of(8) && layout.size().is_multiple_of(8),
+            "parts of allocations must be freed in multiples of 8 bytes"
+        );
+
+        self.with_inner(|inner| inner.dealloc(ptr, layout));
+    }
+
     pub unsafe fn grow(
         &self,
         ptr: *mut u8,
@@ -87,6 +123,10 @@ impl BlockAllocator {
     ) -> Option<NonNull<u8>> {
         self.with_inner(|inner| inner.grow(ptr, layout, new_layout))
     }
+
+    pub unsafe fn stats(&self) -> BlockAllocatorStats {
+        self.with_inner(|inner| inner.stats())
+    }
 }
 
 impl BlockAllocatorInner {
@@ -99,6 +139,45 @@ impl BlockAllocatorInner {
         }
     }
 
+    fn stats(&self) -> BlockAllocatorStats {
+        let start = self.inner_allocator.start();
+        let end = self.inner_allocator.end();
+        let tip = self
+            .inner_allocator
+            .tip()
+            .map_or(start, |tip| tip.as_ptr() as usize);
+
+        // Memory past the tip of the bump allocator has never been allocated
+        let mut largest_free_block = end - tip;
+        let mut free_blocks = usize::from(end > tip);
+        let mut free_in_list = 0;
+
+        let mut current = self.state.first_free_block;
+        while let Some(block) = current {
+            let block_start = block.as_ptr() as usize;
+            let block = unsafe { block.as_ref() };
+
+            free_in_list += block.size;
+
+            if block_start + block.size == tip {
+                // This block is contiguous with the never allocated memory
+                largest_free_block = largest_free_block.max(block.size + end - tip);
+            } else {
+                largest_free_block = largest_free_block.max(block.size);
+                free_blocks += 1;
+            }
+
+            current = block.next;
+        }
+
+        BlockAllocatorStats {
+            capacity: end - start,
+            used: tip - start - free_in_list,
+            largest_free_block,
+            free_blocks,
+        }
+    }
+
     /// Requests a brand new block from the inner bump allocator
     fn new_block(&mut self, layout: Layout) -> Option<NonNull<u8>> {
         let overall_layout = Block::either_layout(layout);
@@ -25,6 +25,14 @@ impl BumpAllocatorInner {
         self.current_ptr.map(|x| x.0)
     }
 
+    pub fn start(&self) -> usize {
+        (self.start_end.start)()
+    }
+
+    pub fn end(&self) -> usize {
+        (self.start_end.end)()
+    }
+
     pub fn alloc(&mut self, layout: Layout) -> Option<NonNull<u8>> {
         let current_ptr = &mut self.current_ptr;
 
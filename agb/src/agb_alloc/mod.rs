@@ -148,6 +148,7 @@ mod test {
     use alloc::boxed::Box;
     use alloc::vec;
     use alloc::vec::Vec;
+    use core::alloc::Layout;
 
     #[test_case]
     fn test_box(_gba: &mut crate::Gba) {
@@ -284,6 +285,44 @@ mod test {
         }
     }
 
+    #[test_case]
+    fn freeing_an_allocation_in_parts_merges_it_back_together(_gba: &mut crate::Gba) {
+        static mut ARENA: [u64; 32] = [0; 32];
+
+        fn arena_start() -> usize {
+            core::ptr::addr_of!(ARENA) as usize
+        }
+        fn arena_end() -> usize {
+            arena_start() + 256
+        }
+
+        let allocator = unsafe {
+            BlockAllocator::new(StartEnd {
+                start: arena_start,
+                end: arena_end,
+            })
+        };
+
+        let layout = Layout::from_size_align(64, 8).unwrap();
+        let part_layout = Layout::from_size_align(8, 8).unwrap();
+
+        unsafe {
+            let whole = allocator.alloc(layout).unwrap();
+            // stops the freed memory being contiguous with the never allocated memory
+            let _after = allocator.alloc(layout).unwrap();
+
+            for part in [3, 0, 7, 1, 5, 2, 6, 4] {
+                allocator.dealloc_part(whole.as_ptr().add(part * 8), part_layout);
+            }
+
+            let stats = allocator.stats();
+            assert_eq!(stats.used, 64);
+            assert_eq!(stats.free_blocks, 2);
+
+            assert_eq!(allocator.alloc(layout), Some(whole));
+        }
+    }
+
     #[test_case]
     fn growth_works(_gba: &mut crate::Gba) {
         let mut growing_vector = Vec::with_capacity(1);
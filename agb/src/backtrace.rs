@@ -2,6 +2,8 @@ use core::{arch::asm, ops::Index};
 
 use alloc::vec::Vec;
 
+use crate::gwilym_encoding;
+
 // only works for code compiled as THUMB
 #[repr(C)]
 #[derive(Clone, Default, Debug)]
@@ -90,48 +92,6 @@ pub(crate) fn unwind_exception() -> Frames {
 
 impl core::fmt::Display for Frames {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        for frame in &self.frames {
-            if frame & 0xFFFF_0000 == 0x0800_0000 {
-                let frame = *frame as u16; // intentionally truncate
-                let frame_encoded = gwilym_encoding::encode_16(frame);
-                let frame_str = unsafe { core::str::from_utf8_unchecked(&frame_encoded) };
-
-                write!(f, "{frame_str}")?;
-            } else {
-                let frame_encoded = gwilym_encoding::encode_32(*frame);
-                let frame_str = unsafe { core::str::from_utf8_unchecked(&frame_encoded) };
-
-                write!(f, "{frame_str}")?;
-            }
-        }
-
-        write!(f, "v1")
-    }
-}
-
-mod gwilym_encoding {
-    static ALPHABET: &[u8] = b"0123456789=ABCDEFGHIJKLMNOPQRSTUVWXYZ_abcdefghijklmnopqrstuvwxyz";
-
-    pub fn encode_16(input: u16) -> [u8; 3] {
-        let input = input as usize;
-        [
-            ALPHABET[input >> (16 - 5)],
-            ALPHABET[(input >> (16 - 10)) & 0b11111],
-            ALPHABET[input & 0b111111],
-        ]
-    }
-
-    pub fn encode_32(input: u32) -> [u8; 6] {
-        let input = input as usize;
-        let output_lower_16 = encode_16(input as u16);
-        let input_upper_16 = input >> 16;
-        [
-            ALPHABET[(input_upper_16 >> (16 - 5)) | (1 << 5)],
-            ALPHABET[(input_upper_16 >> (16 - 10)) & 0b11111],
-            ALPHABET[input_upper_16 & 0b111111],
-            output_lower_16[0],
-            output_lower_16[1],
-            output_lower_16[2],
-        ]
+        gwilym_encoding::write_frames(f, &self.frames)
     }
 }
@@ -0,0 +1,145 @@
+//! Debug text output which works across the emulators you're likely to test with.
+//!
+//! The emulator is detected the first time any output is written, and the
+//! result is cached. Currently supported are:
+//! * [mgba](https://mgba.io), via its debug registers, see [`Mgba`],
+//! * [No$GBA](https://problemkaputt.de/gba.htm), via its debug message port,
+//!   see [`NoCash`].
+//!
+//! Everywhere else (including real hardware), output is discarded.
+//!
+//! [`println!`](crate::println), panic messages and the test runner all write
+//! using this module, so they work the same in every supported emulator.
+
+use core::fmt::Write;
+
+use portable_atomic::{AtomicU8, Ordering};
+
+use crate::{
+    memory_mapped::{MemoryMapped, MemoryMapped1DArray},
+    mgba::{DebugLevel, Mgba},
+};
+
+/// Where debug output is being sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// Running in mgba
+    Mgba,
+    /// Running in No$GBA
+    NoCash,
+    /// No known debug output is available, so output is discarded
+    Null,
+}
+
+const BACKEND_UNKNOWN: u8 = 0;
+const BACKEND_MGBA: u8 = 1;
+const BACKEND_NOCASH: u8 = 2;
+const BACKEND_NULL: u8 = 3;
+
+static DETECTED_BACKEND: AtomicU8 = AtomicU8::new(BACKEND_UNKNOWN);
+
+/// Returns the debug output available in the current emulator, detecting it
+/// the first time this is called.
+#[must_use]
+pub fn backend() -> Backend {
+    match DETECTED_BACKEND.load(Ordering::Relaxed) {
+        BACKEND_MGBA => Backend::Mgba,
+        BACKEND_NOCASH => Backend::NoCash,
+        BACKEND_NULL => Backend::Null,
+        _ => {
+            let backend = detect_backend();
+            DETECTED_BACKEND.store(
+                match backend {
+                    Backend::Mgba => BACKEND_MGBA,
+                    Backend::NoCash => BACKEND_NOCASH,
+                    Backend::Null => BACKEND_NULL,
+                },
+                Ordering::Relaxed,
+            );
+            backend
+        }
+    }
+}
+
+fn detect_backend() -> Backend {
+    if Mgba::new().is_some() {
+        Backend::Mgba
+    } else if NoCash::new().is_some() {
+        Backend::NoCash
+    } else {
+        Backend::Null
+    }
+}
+
+/// Writes a message to the debug output of the current emulator at the given
+/// level. Does nothing if there is no debug output available.
+pub fn print(output: core::fmt::Arguments, level: DebugLevel) -> Result<(), core::fmt::Error> {
+    match backend() {
+        Backend::Mgba => Mgba {}.print(output, level),
+        Backend::NoCash => NoCash {}.print(output, level),
+        Backend::Null => Ok(()),
+    }
+}
+
+const NOCASH_SIGNATURE: MemoryMapped1DArray<u8, 6> =
+    unsafe { MemoryMapped1DArray::new(0x04FF_FA00) };
+const NOCASH_CHARACTER_OUT: MemoryMapped<u8> = unsafe { MemoryMapped::new(0x04FF_FA1C) };
+
+/// Output to No$GBA's debug message window.
+///
+/// No$GBA has no concept of log levels, so anything other than
+/// [`DebugLevel::Info`] is prefixed with the level.
+#[non_exhaustive]
+pub struct NoCash {}
+
+impl NoCash {
+    /// Returns `Some` if running in No$GBA
+    #[must_use]
+    pub fn new() -> Option<Self> {
+        let is_running_in_nocash = (0..6).all(|i| NOCASH_SIGNATURE.get(i) == b"no$gba"[i]);
+
+        is_running_in_nocash.then_some(NoCash {})
+    }
+
+    /// Writes a single line of output.
+    pub fn print(
+        &mut self,
+        output: core::fmt::Arguments,
+        level: DebugLevel,
+    ) -> Result<(), core::fmt::Error> {
+        let mut writer = NoCashWriter;
+
+        let prefix = match level {
+            DebugLevel::Fatal => "[FATAL] ",
+            DebugLevel::Error => "[ERROR] ",
+            DebugLevel::Warning => "[WARN] ",
+            DebugLevel::Info => "",
+            DebugLevel::Debug => "[DEBUG] ",
+        };
+
+        writeln!(&mut writer, "{prefix}{output}")
+    }
+}
+
+struct NoCashWriter;
+
+impl Write for NoCashWriter {
+    fn write_str(&mut self, s: &str) -> Result<(), core::fmt::Error> {
+        for b in s.bytes() {
+            NOCASH_CHARACTER_OUT.set(b);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test_case]
+    fn detects_mgba(_gba: &mut crate::Gba) {
+        // the tests are run in mgba
+        assert_eq!(backend(), Backend::Mgba);
+        assert!(NoCash::new().is_none());
+    }
+}
@@ -1,7 +1,8 @@
-use crate::memory_mapped::MemoryMapped2DArray;
+use crate::{fixnum::Vector2D, memory_mapped::MemoryMapped2DArray};
 
 use super::{
-    set_graphics_mode, set_graphics_settings, DisplayMode, GraphicsSettings, HEIGHT, WIDTH,
+    canvas::Canvas, copy_bitmap, palette16::Palette16, set_graphics_mode, set_graphics_settings,
+    tile_data::BitmapData, DisplayMode, GraphicsSettings, HEIGHT, WIDTH,
 };
 
 use core::marker::PhantomData;
@@ -45,4 +46,45 @@ impl Bitmap3<'_> {
             }
         }
     }
+
+    /// Copies `bitmap` to the top left of the screen using DMA.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bitmap is larger than the screen, or if its data isn't
+    /// `width * height` pixels long.
+    pub fn draw_bitmap(&mut self, bitmap: &BitmapData) {
+        assert!(
+            i32::from(bitmap.width) <= WIDTH && i32::from(bitmap.height) <= HEIGHT,
+            "Bitmap is larger than the screen"
+        );
+
+        copy_bitmap(
+            bitmap.data,
+            (bitmap.width.into(), bitmap.height.into()),
+            BITMAP_MODE_3.as_ptr(),
+            (WIDTH as usize, HEIGHT as usize),
+        );
+    }
+}
+
+/// Draws directly to the screen, where colours are 15 bit RGB values.
+impl Canvas for Bitmap3<'_> {
+    type Colour = u16;
+
+    fn size(&self) -> Vector2D<i32> {
+        (WIDTH, HEIGHT).into()
+    }
+
+    fn write_pixel(&mut self, pos: Vector2D<i32>, colour: u16) {
+        BITMAP_MODE_3.set(pos.x as usize, pos.y as usize, colour);
+    }
+
+    fn read_pixel(&self, pos: Vector2D<i32>) -> u16 {
+        BITMAP_MODE_3.get(pos.x as usize, pos.y as usize)
+    }
+
+    fn colour_from_palette(palette: &Palette16, index: u8) -> u16 {
+        palette.colour(index as usize)
+    }
 }
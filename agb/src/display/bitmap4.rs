@@ -1,10 +1,13 @@
 use core::marker::PhantomData;
 
-use crate::memory_mapped::{MemoryMapped1DArray, MemoryMapped2DArray};
+use crate::{
+    fixnum::Vector2D,
+    memory_mapped::{MemoryMapped1DArray, MemoryMapped2DArray},
+};
 
 use super::{
-    set_graphics_mode, set_graphics_settings, DisplayMode, GraphicsSettings, DISPLAY_CONTROL,
-    HEIGHT, WIDTH,
+    canvas::Canvas, copy_bitmap, palette16::Palette16, set_graphics_mode, set_graphics_settings,
+    tile_data::PalettedBitmapData, DisplayMode, GraphicsSettings, DISPLAY_CONTROL, HEIGHT, WIDTH,
 };
 
 const BITMAP_PAGE_FRONT_MODE_4: MemoryMapped2DArray<
@@ -76,11 +79,109 @@ impl Bitmap4<'_> {
         self.draw_point_page(x, y, colour, page);
     }
 
+    /// Reads the colour index of the point on specified page at (x, y)
+    /// coordinates. Panics if (x, y) is out of the bounds of the screen.
+    #[must_use]
+    pub fn read_point_page(&self, x: i32, y: i32, page: Page) -> u8 {
+        let addr = match page {
+            Page::Front => BITMAP_PAGE_FRONT_MODE_4,
+            Page::Back => BITMAP_PAGE_BACK_MODE_4,
+        };
+
+        let c = addr.get((x / 2) as usize, y as usize);
+        if x & 0b1 != 0 {
+            (c >> 8) as u8
+        } else {
+            c as u8
+        }
+    }
+
+    /// Reads the colour index of the point on the non-current page at (x, y)
+    /// coordinates. Panics if (x, y) is out of the bounds of the screen.
+    #[must_use]
+    pub fn read_point(&self, x: i32, y: i32) -> u8 {
+        let display = DISPLAY_CONTROL.get();
+
+        // get other page
+        let page = if display & GraphicsSettings::PAGE_SELECT.bits() != 0 {
+            Page::Front
+        } else {
+            Page::Back
+        };
+
+        self.read_point_page(x, y, page)
+    }
+
     /// Sets the colour of colour index in the background palette.
     pub fn set_palette_entry(&mut self, entry: u32, colour: u16) {
         PALETTE_BACKGROUND.set(entry as usize, colour);
     }
 
+    /// Sets the background palette, starting from colour index 0.
+    pub fn set_palette(&mut self, palette: &[u16]) {
+        for (entry, &colour) in palette.iter().enumerate() {
+            PALETTE_BACKGROUND.set(entry, colour);
+        }
+    }
+
+    /// Copies `bitmap` to the top left of `page` using DMA.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bitmap is larger than the screen, has an odd width, or
+    /// if its data isn't `width * height` pixels long.
+    pub fn draw_bitmap_page(&mut self, bitmap: &PalettedBitmapData, page: Page) {
+        assert!(
+            i32::from(bitmap.width) <= WIDTH && i32::from(bitmap.height) <= HEIGHT,
+            "Bitmap is larger than the screen"
+        );
+        assert!(
+            bitmap.width.is_multiple_of(2),
+            "Paletted bitmaps must have an even width"
+        );
+        assert_eq!(
+            bitmap.data.len(),
+            usize::from(bitmap.width) * usize::from(bitmap.height),
+            "Bitmap data doesn't match the bitmap's size"
+        );
+
+        let addr = match page {
+            Page::Front => BITMAP_PAGE_FRONT_MODE_4,
+            Page::Back => BITMAP_PAGE_BACK_MODE_4,
+        };
+
+        // Video RAM can only be written 2 bytes at a time, so copy pairs of pixels
+        let data = unsafe {
+            core::slice::from_raw_parts(bitmap.data.as_ptr().cast::<u16>(), bitmap.data.len() / 2)
+        };
+
+        copy_bitmap(
+            data,
+            (usize::from(bitmap.width) / 2, bitmap.height.into()),
+            addr.as_ptr(),
+            ((WIDTH / 2) as usize, HEIGHT as usize),
+        );
+    }
+
+    /// Copies `bitmap` to the top left of the non-current page using DMA.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bitmap is larger than the screen, has an odd width, or
+    /// if its data isn't `width * height` pixels long.
+    pub fn draw_bitmap(&mut self, bitmap: &PalettedBitmapData) {
+        let display = DISPLAY_CONTROL.get();
+
+        // get other page
+        let page = if display & GraphicsSettings::PAGE_SELECT.bits() != 0 {
+            Page::Front
+        } else {
+            Page::Back
+        };
+
+        self.draw_bitmap_page(bitmap, page);
+    }
+
     /// Flips page, changing the Gameboy advance to draw the contents of the
     /// other page
     pub fn flip_page(&mut self) {
@@ -149,3 +250,25 @@ impl Bitmap4<'_> {
         self.clear_page(colour, page);
     }
 }
+
+/// Draws to the non-current page, where colours are indices into the
+/// background palette.
+impl Canvas for Bitmap4<'_> {
+    type Colour = u8;
+
+    fn size(&self) -> Vector2D<i32> {
+        (WIDTH, HEIGHT).into()
+    }
+
+    fn write_pixel(&mut self, pos: Vector2D<i32>, colour: u8) {
+        self.draw_point(pos.x, pos.y, colour);
+    }
+
+    fn read_pixel(&self, pos: Vector2D<i32>) -> u8 {
+        self.read_point(pos.x, pos.y)
+    }
+
+    fn colour_from_palette(_palette: &Palette16, index: u8) -> u8 {
+        index
+    }
+}
@@ -0,0 +1,201 @@
+use core::marker::PhantomData;
+
+use crate::{fixnum::Vector2D, memory_mapped::MemoryMapped2DArray};
+
+use super::{
+    bitmap4::Page, canvas::Canvas, copy_bitmap, palette16::Palette16, set_graphics_mode,
+    set_graphics_settings, tile_data::BitmapData, DisplayMode, GraphicsSettings, DISPLAY_CONTROL,
+};
+
+/// The width of a mode 5 page in pixels
+pub const WIDTH: i32 = 160;
+/// The height of a mode 5 page in pixels
+pub const HEIGHT: i32 = 128;
+
+const BITMAP_PAGE_FRONT_MODE_5: MemoryMapped2DArray<u16, { WIDTH as usize }, { HEIGHT as usize }> =
+    unsafe { MemoryMapped2DArray::new(0x600_0000) };
+const BITMAP_PAGE_BACK_MODE_5: MemoryMapped2DArray<u16, { WIDTH as usize }, { HEIGHT as usize }> =
+    unsafe { MemoryMapped2DArray::new(0x600_A000) };
+
+/// Two 160x128 pages of 16-bit colour. The page is shown in the top left of
+/// the screen, and can be moved or scaled using the affine settings of
+/// background 2.
+#[non_exhaustive]
+pub struct Bitmap5<'gba> {
+    phantom: PhantomData<&'gba ()>,
+}
+
+impl Bitmap5<'_> {
+    pub(crate) unsafe fn new() -> Self {
+        set_graphics_mode(DisplayMode::Bitmap5);
+        set_graphics_settings(GraphicsSettings::LAYER_BG2);
+        Bitmap5 {
+            phantom: PhantomData,
+        }
+    }
+
+    fn non_current_page() -> Page {
+        let display = DISPLAY_CONTROL.get();
+
+        if display & GraphicsSettings::PAGE_SELECT.bits() != 0 {
+            Page::Front
+        } else {
+            Page::Back
+        }
+    }
+
+    fn page_memory(
+        page: Page,
+    ) -> MemoryMapped2DArray<u16, { WIDTH as usize }, { HEIGHT as usize }> {
+        match page {
+            Page::Front => BITMAP_PAGE_FRONT_MODE_5,
+            Page::Back => BITMAP_PAGE_BACK_MODE_5,
+        }
+    }
+
+    /// Draws point on specified page at (x, y) coordinates with colour. Panics
+    /// if (x, y) is out of the bounds of the page.
+    pub fn draw_point_page(&mut self, x: i32, y: i32, colour: u16, page: Page) {
+        Self::page_memory(page).set(x as usize, y as usize, colour);
+    }
+
+    /// Draws point on the non-current page at (x, y) coordinates with colour.
+    /// Panics if (x, y) is out of the bounds of the page.
+    pub fn draw_point(&mut self, x: i32, y: i32, colour: u16) {
+        self.draw_point_page(x, y, colour, Self::non_current_page());
+    }
+
+    /// Reads the colour of the point on specified page at (x, y) coordinates.
+    /// Panics if (x, y) is out of the bounds of the page.
+    #[must_use]
+    pub fn read_point_page(&self, x: i32, y: i32, page: Page) -> u16 {
+        Self::page_memory(page).get(x as usize, y as usize)
+    }
+
+    /// Reads the colour of the point on the non-current page at (x, y)
+    /// coordinates. Panics if (x, y) is out of the bounds of the page.
+    #[must_use]
+    pub fn read_point(&self, x: i32, y: i32) -> u16 {
+        self.read_point_page(x, y, Self::non_current_page())
+    }
+
+    /// Copies `bitmap` to the top left of `page` using DMA.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bitmap is larger than the page, or if its data isn't
+    /// `width * height` pixels long.
+    pub fn draw_bitmap_page(&mut self, bitmap: &BitmapData, page: Page) {
+        assert!(
+            i32::from(bitmap.width) <= WIDTH && i32::from(bitmap.height) <= HEIGHT,
+            "Bitmap is larger than the page"
+        );
+
+        copy_bitmap(
+            bitmap.data,
+            (bitmap.width.into(), bitmap.height.into()),
+            Self::page_memory(page).as_ptr(),
+            (WIDTH as usize, HEIGHT as usize),
+        );
+    }
+
+    /// Copies `bitmap` to the top left of the non-current page using DMA.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bitmap is larger than the page, or if its data isn't
+    /// `width * height` pixels long.
+    pub fn draw_bitmap(&mut self, bitmap: &BitmapData) {
+        self.draw_bitmap_page(bitmap, Self::non_current_page());
+    }
+
+    /// Flips page, changing the Gameboy advance to draw the contents of the
+    /// other page
+    pub fn flip_page(&mut self) {
+        let display = DISPLAY_CONTROL.get();
+        let swapped = display ^ GraphicsSettings::PAGE_SELECT.bits();
+        DISPLAY_CONTROL.set(swapped);
+    }
+
+    /// Fills specified page with colour.
+    pub fn clear_page(&mut self, colour: u16, page: Page) {
+        let addr = Self::page_memory(page);
+
+        for y in 0..(HEIGHT as usize) {
+            for x in 0..(WIDTH as usize) {
+                addr.set(x, y, colour);
+            }
+        }
+    }
+
+    /// Fills non-current page with colour.
+    pub fn clear(&mut self, colour: u16) {
+        self.clear_page(colour, Self::non_current_page());
+    }
+}
+
+/// Draws to the non-current page, where colours are 15-bit RGB values.
+impl Canvas for Bitmap5<'_> {
+    type Colour = u16;
+
+    fn size(&self) -> Vector2D<i32> {
+        (WIDTH, HEIGHT).into()
+    }
+
+    fn write_pixel(&mut self, pos: Vector2D<i32>, colour: u16) {
+        self.draw_point(pos.x, pos.y, colour);
+    }
+
+    fn read_pixel(&self, pos: Vector2D<i32>) -> u16 {
+        self.read_point(pos.x, pos.y)
+    }
+
+    fn colour_from_palette(palette: &Palette16, index: u8) -> u16 {
+        palette.colour(index.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static STRIPES: &[u16] = &[
+        0x001f, 0x03e0, 0x7c00, 0x7fff, 0x7c00, 0x03e0, 0x001f, 0x0000,
+    ];
+
+    #[test_case]
+    fn draw_bitmap_copies_rows_to_the_back_page(gba: &mut crate::Gba) {
+        let mut bitmap = gba.display.video.bitmap5();
+        bitmap.clear_page(0x1234, Page::Back);
+
+        bitmap.draw_bitmap_page(&BitmapData::new(4, 2, STRIPES), Page::Back);
+
+        for y in 0..2 {
+            for x in 0..4 {
+                assert_eq!(
+                    bitmap.read_point_page(x, y, Page::Back),
+                    STRIPES[(y * 4 + x) as usize]
+                );
+            }
+        }
+        assert_eq!(bitmap.read_point_page(4, 0, Page::Back), 0x1234);
+        assert_eq!(bitmap.read_point_page(0, 2, Page::Back), 0x1234);
+    }
+
+    static WATER: BitmapData = crate::include_bitmap!("examples/water_tiles.png");
+
+    #[test_case]
+    fn included_bitmaps_can_be_drawn(gba: &mut crate::Gba) {
+        let mut bitmap = gba.display.video.bitmap5();
+        bitmap.draw_bitmap_page(&WATER, Page::Front);
+
+        let width = i32::from(WATER.width);
+        let last = WATER.data.len() - 1;
+
+        assert_eq!(bitmap.read_point_page(0, 0, Page::Front), WATER.data[0]);
+        assert_eq!(
+            bitmap.read_point_page(width - 1, i32::from(WATER.height) - 1, Page::Front),
+            WATER.data[last]
+        );
+    }
+}
@@ -0,0 +1,442 @@
+//! Software drawing primitives shared by everything you can draw pixels onto.
+//!
+//! The [`Canvas`] trait is implemented by the bitmap modes, [`Bitmap3`](super::bitmap3::Bitmap3),
+//! [`Bitmap4`](super::bitmap4::Bitmap4) and [`Bitmap5`](super::bitmap5::Bitmap5), and by [`TileCanvas`](super::tiled::TileCanvas)
+//! which draws onto a region of a tiled background. Implementors only need to
+//! provide reading and writing single pixels, and get lines, rectangles, circles,
+//! flood fills and blits for free.
+//!
+//! All the drawing functions clip to the canvas, so it is fine to draw shapes
+//! which are partially (or entirely) off the edge.
+
+use alloc::vec::Vec;
+
+use crate::fixnum::{Rect, Vector2D};
+
+use super::{object::Sprite, palette16::Palette16};
+
+/// Something which can be drawn on one pixel at a time. Only the first few
+/// methods need implementing, and the rest draw shapes in terms of them.
+pub trait Canvas {
+    /// The type of a single pixel. Either a palette index or a 15 bit colour.
+    type Colour: Copy + PartialEq;
+
+    /// The size of the canvas in pixels
+    fn size(&self) -> Vector2D<i32>;
+
+    /// Sets the pixel at `pos`, which is guaranteed to be within the canvas.
+    /// Use [`draw_pixel`](Canvas::draw_pixel) to draw a pixel which may not be.
+    fn write_pixel(&mut self, pos: Vector2D<i32>, colour: Self::Colour);
+
+    /// Reads the pixel at `pos`, which is guaranteed to be within the canvas.
+    fn read_pixel(&self, pos: Vector2D<i32>) -> Self::Colour;
+
+    /// Converts colour `index` of a sprite's palette to a colour which can be
+    /// drawn on this canvas, used by [`blit_sprite`](Canvas::blit_sprite).
+    fn colour_from_palette(palette: &Palette16, index: u8) -> Self::Colour;
+
+    /// Whether `pos` is within the canvas
+    #[must_use]
+    fn contains(&self, pos: Vector2D<i32>) -> bool {
+        let size = self.size();
+        pos.x >= 0 && pos.y >= 0 && pos.x < size.x && pos.y < size.y
+    }
+
+    /// Draws a single pixel, doing nothing if `pos` is outside the canvas.
+    fn draw_pixel(&mut self, pos: Vector2D<i32>, colour: Self::Colour) {
+        if self.contains(pos) {
+            self.write_pixel(pos, colour);
+        }
+    }
+
+    /// The colour of the pixel at `pos`, or `None` if it is outside the canvas.
+    #[must_use]
+    fn pixel(&self, pos: Vector2D<i32>) -> Option<Self::Colour> {
+        self.contains(pos).then(|| self.read_pixel(pos))
+    }
+
+    /// Draws a line from `start` to `end`, including both end points.
+    fn draw_line(&mut self, start: Vector2D<i32>, end: Vector2D<i32>, colour: Self::Colour) {
+        let size = self.size();
+
+        // Step one pixel at a time along whichever axis the line is longer in,
+        // working out the position on the other axis as we go. Only the steps
+        // which are within the canvas are visited, so lines which are mostly
+        // off the edge cost no more than ones which aren't.
+        let x_major = (i64::from(end.x) - i64::from(start.x)).abs()
+            >= (i64::from(end.y) - i64::from(start.y)).abs();
+        let (major, minor, major_size) = if x_major {
+            ((start.x, end.x), (start.y, end.y), size.x)
+        } else {
+            ((start.y, end.y), (start.x, end.x), size.y)
+        };
+
+        let (major_start, minor_start) = (i64::from(major.0), i64::from(minor.0));
+        let length = (i64::from(major.1) - major_start).abs();
+        let minor_length = (i64::from(minor.1) - minor_start).abs();
+        let direction = if major.1 < major.0 { -1 } else { 1 };
+        let minor_direction = if minor.1 < minor.0 { -1 } else { 1 };
+
+        // the first and last steps which are within the canvas on the major axis
+        let (first, last) = if direction > 0 {
+            (-major_start, i64::from(major_size) - 1 - major_start)
+        } else {
+            (major_start - i64::from(major_size) + 1, major_start)
+        };
+        let (first, last) = (first.max(0), last.min(length));
+
+        if first > last {
+            return;
+        }
+
+        // At step `i`, the minor axis is `(2 * i * minor_length + length) / (2 * length)`
+        // (rounded down) from the start, so halfway points round towards the end. Keep
+        // track of the quotient and remainder of that as we go rather than dividing
+        // every step.
+        let divisor = 2 * length.max(1);
+        let numerator = 2 * i128::from(first) * i128::from(minor_length) + i128::from(length);
+        let mut offset = (numerator / i128::from(divisor)) as i64;
+        let mut remainder = (numerator % i128::from(divisor)) as i64;
+
+        for step in first..=last {
+            // both are between the start and end, so fit in an i32
+            let major_pos = (major_start + step * direction) as i32;
+            let minor_pos = (minor_start + offset * minor_direction) as i32;
+
+            let pos = if x_major {
+                (major_pos, minor_pos)
+            } else {
+                (minor_pos, major_pos)
+            };
+            self.draw_pixel(pos.into(), colour);
+
+            remainder += 2 * minor_length;
+            if remainder >= divisor {
+                remainder -= divisor;
+                offset += 1;
+            }
+        }
+    }
+
+    /// Draws a horizontal line of `length` pixels starting at `start` and going right.
+    fn draw_horizontal_line(&mut self, start: Vector2D<i32>, length: i32, colour: Self::Colour) {
+        let size = self.size();
+        if start.y < 0 || start.y >= size.y {
+            return;
+        }
+
+        for x in start.x.max(0)..start.x.saturating_add(length).min(size.x) {
+            self.write_pixel((x, start.y).into(), colour);
+        }
+    }
+
+    /// Draws the outline of `rect`, one pixel wide.
+    fn draw_rect(&mut self, rect: Rect<i32>, colour: Self::Colour) {
+        if rect.size.x <= 0 || rect.size.y <= 0 {
+            return;
+        }
+
+        let size = self.size();
+        let top_left = rect.position;
+        let bottom_right = Vector2D::new(
+            rect.position.x.saturating_add(rect.size.x - 1),
+            rect.position.y.saturating_add(rect.size.y - 1),
+        );
+
+        self.draw_horizontal_line(top_left, rect.size.x, colour);
+        self.draw_horizontal_line((top_left.x, bottom_right.y).into(), rect.size.x, colour);
+
+        // only the part of the sides which is on the canvas
+        for y in top_left.y.saturating_add(1).max(0)..bottom_right.y.min(size.y) {
+            self.draw_pixel((top_left.x, y).into(), colour);
+            self.draw_pixel((bottom_right.x, y).into(), colour);
+        }
+    }
+
+    /// Fills `rect` with `colour`.
+    fn fill_rect(&mut self, rect: Rect<i32>, colour: Self::Colour) {
+        let size = self.size();
+        let top_left = Vector2D::new(rect.position.x.max(0), rect.position.y.max(0));
+        let bottom_right = Vector2D::new(
+            rect.position.x.saturating_add(rect.size.x).min(size.x),
+            rect.position.y.saturating_add(rect.size.y).min(size.y),
+        );
+
+        for y in top_left.y..bottom_right.y {
+            self.draw_horizontal_line((top_left.x, y).into(), bottom_right.x - top_left.x, colour);
+        }
+    }
+
+    /// Fills the whole canvas with `colour`.
+    fn clear_with(&mut self, colour: Self::Colour) {
+        let size = self.size();
+        self.fill_rect(Rect::new((0, 0).into(), size), colour);
+    }
+
+    /// Draws the outline of a circle centred on `centre`.
+    fn draw_circle(&mut self, centre: Vector2D<i32>, radius: i32, colour: Self::Colour) {
+        let size = self.size();
+        if radius < 0 || !ring_touches_canvas(size, centre, radius) {
+            return;
+        }
+
+        let bounds = CircleBounds::new(radius);
+
+        // Only the rows of the canvas the circle crosses are visited, so the
+        // work done is bounded by the size of the canvas rather than the circle.
+        for (y, dy_squared) in circle_rows(size, centre, radius) {
+            let outer_half_width = (bounds.outer - dy_squared).isqrt();
+            let inner_half_width = ceil_sqrt((bounds.inner - dy_squared).max(0));
+
+            if inner_half_width > outer_half_width {
+                continue;
+            }
+
+            let centre_x = i64::from(centre.x);
+            draw_clipped_span(
+                self,
+                y,
+                centre_x - outer_half_width,
+                centre_x - inner_half_width,
+                colour,
+            );
+            draw_clipped_span(
+                self,
+                y,
+                centre_x + inner_half_width,
+                centre_x + outer_half_width,
+                colour,
+            );
+        }
+    }
+
+    /// Draws a filled circle centred on `centre`.
+    fn fill_circle(&mut self, centre: Vector2D<i32>, radius: i32, colour: Self::Colour) {
+        let size = self.size();
+        if radius < 0 {
+            return;
+        }
+
+        let bounds = CircleBounds::new(radius);
+
+        for (y, dy_squared) in circle_rows(size, centre, radius) {
+            let half_width = (bounds.outer - dy_squared).isqrt();
+
+            let centre_x = i64::from(centre.x);
+            draw_clipped_span(
+                self,
+                y,
+                centre_x - half_width,
+                centre_x + half_width,
+                colour,
+            );
+        }
+    }
+
+    /// Replaces the area of pixels which are the same colour as the one at `pos`
+    /// and connected to it horizontally or vertically with `colour`.
+    fn flood_fill(&mut self, pos: Vector2D<i32>, colour: Self::Colour) {
+        let Some(target) = self.pixel(pos) else {
+            return;
+        };
+
+        if target == colour {
+            return;
+        }
+
+        let width = self.size().x;
+        let mut to_fill = Vec::new();
+        to_fill.push(pos);
+
+        // Fills a whole horizontal span at a time, queueing the spans above and below
+        while let Some(seed) = to_fill.pop() {
+            if self.pixel(seed) != Some(target) {
+                continue;
+            }
+
+            let mut left = seed.x;
+            while left > 0 && self.read_pixel((left - 1, seed.y).into()) == target {
+                left -= 1;
+            }
+
+            let mut right = seed.x;
+            while right < width - 1 && self.read_pixel((right + 1, seed.y).into()) == target {
+                right += 1;
+            }
+
+            for x in left..=right {
+                self.write_pixel((x, seed.y).into(), colour);
+            }
+
+            for y in [seed.y - 1, seed.y + 1] {
+                let mut in_span = false;
+
+                for x in left..=right {
+                    let matches = self.pixel((x, y).into()) == Some(target);
+                    if matches && !in_span {
+                        to_fill.push((x, y).into());
+                    }
+                    in_span = matches;
+                }
+            }
+        }
+    }
+
+    /// Copies `pixels`, which is an image `width` pixels wide stored row by
+    /// row, to the canvas with its top left corner at `pos`. Pixels which are
+    /// `transparent` aren't drawn.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is zero or the length of `pixels` isn't a multiple of it.
+    fn blit(
+        &mut self,
+        pos: Vector2D<i32>,
+        width: usize,
+        pixels: &[Self::Colour],
+        transparent: Option<Self::Colour>,
+    ) {
+        assert!(
+            width > 0 && pixels.len() % width == 0,
+            "Pixels must be a whole number of rows"
+        );
+
+        for (y, row) in pixels.chunks_exact(width).enumerate() {
+            for (x, &pixel) in row.iter().enumerate() {
+                if Some(pixel) != transparent {
+                    self.draw_pixel(pos + (x as i32, y as i32).into(), pixel);
+                }
+            }
+        }
+    }
+
+    /// Draws `sprite` with its top left corner at `pos`. Colour 0 of the sprite
+    /// is transparent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sprite was compressed with `include_aseprite!`'s
+    /// `compress` option.
+    fn blit_sprite(&mut self, pos: Vector2D<i32>, sprite: &Sprite) {
+        assert!(!sprite.compressed, "Cannot blit compressed sprites");
+
+        let (width, height) = sprite.size.to_width_height();
+        let tiles_per_row = width / 8;
+
+        for y in 0..height {
+            for x in 0..width {
+                // Sprites are stored as 4bpp tiles, one row of tiles after another
+                let tile = (y / 8) * tiles_per_row + x / 8;
+                let byte = sprite.data[tile * 32 + (y % 8) * 4 + (x % 8) / 2];
+                let index = if x % 2 == 0 { byte & 0xf } else { byte >> 4 };
+
+                if index != 0 {
+                    self.draw_pixel(
+                        pos + (x as i32, y as i32).into(),
+                        Self::colour_from_palette(sprite.palette, index),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The range of squared distances from the centre of a circle of pixels which
+/// are part of it. A pixel is on the outline if its distance from the centre
+/// rounds to the radius, and inside the circle if it rounds to at most the radius.
+struct CircleBounds {
+    inner: i64,
+    outer: i64,
+}
+
+impl CircleBounds {
+    fn new(radius: i32) -> Self {
+        let radius = i64::from(radius);
+
+        // (radius - 0.5)^2 <= distance^2 < (radius + 0.5)^2, for whole number distances squared
+        Self {
+            inner: if radius == 0 {
+                0
+            } else {
+                radius * radius - radius + 1
+            },
+            outer: radius * radius + radius,
+        }
+    }
+}
+
+/// The rows of the canvas which a circle crosses, along with the square of
+/// their distance from the centre.
+fn circle_rows(
+    size: Vector2D<i32>,
+    centre: Vector2D<i32>,
+    radius: i32,
+) -> impl Iterator<Item = (i32, i64)> {
+    let (centre_y, radius) = (i64::from(centre.y), i64::from(radius));
+
+    let first = (centre_y - radius).max(0);
+    let last = (centre_y + radius).min(i64::from(size.y) - 1);
+
+    (first..=last).map(move |y| {
+        let dy = y - centre_y;
+        // every row is on the canvas, so fits in an i32
+        (y as i32, dy * dy)
+    })
+}
+
+/// Whether any of the outline of a circle could be on the canvas. The outline
+/// misses if the nearest point of the canvas is outside the circle, or if the
+/// whole canvas fits in the hole in the middle.
+fn ring_touches_canvas(size: Vector2D<i32>, centre: Vector2D<i32>, radius: i32) -> bool {
+    // The distances can be larger than an i32 in each direction, so squaring
+    // them needs more than an i64
+    let squared_distance = |x: i32, y: i32| {
+        let dx = i128::from(x) - i128::from(centre.x);
+        let dy = i128::from(y) - i128::from(centre.y);
+        dx * dx + dy * dy
+    };
+
+    let bounds = CircleBounds::new(radius);
+
+    let nearest = squared_distance(centre.x.clamp(0, size.x - 1), centre.y.clamp(0, size.y - 1));
+    let farthest = [
+        (0, 0),
+        (size.x - 1, 0),
+        (0, size.y - 1),
+        (size.x - 1, size.y - 1),
+    ]
+    .into_iter()
+    .map(|(x, y)| squared_distance(x, y))
+    .max()
+    .unwrap_or(0);
+
+    nearest <= i128::from(bounds.outer) && farthest >= i128::from(bounds.inner)
+}
+
+/// The smallest number whose square is at least `value`
+fn ceil_sqrt(value: i64) -> i64 {
+    let root = value.isqrt();
+    if root * root == value {
+        root
+    } else {
+        root + 1
+    }
+}
+
+/// Draws the pixels from `start` to `end` inclusive on row `y`, clipping them
+/// to the canvas first since they may not fit in an i32.
+fn draw_clipped_span<C: Canvas + ?Sized>(
+    canvas: &mut C,
+    y: i32,
+    start: i64,
+    end: i64,
+    colour: C::Colour,
+) {
+    let start = start.max(0);
+    let end = end.min(i64::from(canvas.size().x) - 1);
+
+    if start <= end {
+        // both ends are on the canvas, so fit in an i32
+        canvas.draw_horizontal_line((start as i32, y).into(), (end - start + 1) as i32, colour);
+    }
+}
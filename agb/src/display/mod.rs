@@ -1,4 +1,4 @@
-use crate::memory_mapped::MemoryMapped;
+use crate::{fixnum::Num, memory_mapped::MemoryMapped};
 
 use bilge::prelude::*;
 use bitflags::bitflags;
@@ -15,6 +15,8 @@ use self::{
 pub mod bitmap3;
 /// Graphics mode 4. Bitmap 4 provides two 8-bit paletted framebuffers with page switching.
 pub mod bitmap4;
+/// Graphics mode 5. Bitmap 5 provides two smaller 16-bit colour framebuffers with page switching.
+pub mod bitmap5;
 /// Test logo of agb.
 pub mod example_logo;
 pub mod object;
@@ -29,6 +31,8 @@ pub mod video;
 
 pub mod affine;
 pub mod blend;
+pub mod canvas;
+pub mod palette_effects;
 pub mod window;
 
 pub mod font;
@@ -61,6 +65,98 @@ pub const WIDTH: i32 = 240;
 /// Height of the Gameboy advance screen in pixels
 pub const HEIGHT: i32 = 160;
 
+/// How much of an area of video RAM is in use, as returned by
+/// [`VRamManager::usage`](tiled::VRamManager::usage) and
+/// [`SpriteLoader::sprite_usage`](object::SpriteLoader::sprite_usage). All
+/// values are in bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct VRamUsage {
+    /// The total amount of memory available
+    pub capacity: usize,
+    /// The amount of memory currently allocated
+    pub used: usize,
+    /// The size of the largest contiguous free area, which is the largest
+    /// allocation which can currently succeed
+    pub largest_free_block: usize,
+    /// The number of separate free areas
+    pub free_blocks: usize,
+}
+
+impl VRamUsage {
+    pub(crate) fn new(stats: crate::agb_alloc::block_allocator::BlockAllocatorStats) -> Self {
+        Self {
+            capacity: stats.capacity,
+            used: stats.used,
+            largest_free_block: stats.largest_free_block,
+            free_blocks: stats.free_blocks,
+        }
+    }
+
+    /// The amount of memory which isn't allocated
+    #[must_use]
+    pub fn free(&self) -> usize {
+        self.capacity - self.used
+    }
+
+    /// The proportion of free memory which isn't part of the largest free
+    /// block. 0 means all the free memory is contiguous, and values close to 1
+    /// mean that large allocations will fail even though there is plenty of
+    /// memory free.
+    #[must_use]
+    pub fn fragmentation(&self) -> Num<i32, 8> {
+        let free = self.free();
+        if free == 0 {
+            return 0.into();
+        }
+
+        Num::new((free - self.largest_free_block.min(free)) as i32) / free as i32
+    }
+}
+
+/// Copies an image made of `height` rows of `width` halfwords into the top left
+/// of a bitmap page `page_width` halfwords wide and `page_height` rows tall,
+/// using DMA.
+///
+/// # Panics
+///
+/// Panics if the image is larger than the page, or if `data` isn't exactly
+/// `width * height` halfwords long.
+fn copy_bitmap(
+    data: &[u16],
+    (width, height): (usize, usize),
+    page: *mut u16,
+    (page_width, page_height): (usize, usize),
+) {
+    // These are what keep the copies below inside the page
+    assert!(
+        width <= page_width && height <= page_height,
+        "Bitmap is larger than the page"
+    );
+    assert_eq!(
+        data.len(),
+        width * height,
+        "Bitmap data doesn't match the bitmap's size"
+    );
+
+    if data.is_empty() {
+        return;
+    }
+
+    // SAFETY: `data` is `height` rows of `width` halfwords, and both of those
+    // fit in the page, so every copy stays inside it
+    crate::dma::dma3_exclusive(|| unsafe {
+        if width == page_width {
+            crate::dma::dma_copy16(data.as_ptr(), page, data.len());
+            return;
+        }
+
+        for (y, row) in data.chunks_exact(width).enumerate() {
+            crate::dma::dma_copy16(row.as_ptr(), page.add(y * page_width), width);
+        }
+    });
+}
+
 #[allow(dead_code)]
 enum DisplayMode {
     Tiled0 = 0,
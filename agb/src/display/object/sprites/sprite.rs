@@ -9,6 +9,7 @@ pub struct Sprite {
     pub(crate) palette: &'static Palette16,
     pub(crate) data: &'static [u8],
     pub(crate) size: Size,
+    pub(crate) compressed: bool,
 }
 
 impl Sprite {
@@ -24,6 +25,29 @@ impl Sprite {
             palette,
             data,
             size,
+            compressed: false,
+        }
+    }
+
+    #[doc(hidden)]
+    /// Creates a sprite from compressed data, used internally by
+    /// [include_aseprite] when using the `compress` option and should
+    /// generally not be used outside it.
+    ///
+    /// # Safety
+    /// The data should be aligned to a 4 byte boundary, and decompress to the
+    /// correct size for the sprite
+    #[must_use]
+    pub const unsafe fn new_compressed(
+        palette: &'static Palette16,
+        data: &'static [u8],
+        size: Size,
+    ) -> Self {
+        Self {
+            palette,
+            data,
+            size,
+            compressed: true,
         }
     }
 
@@ -92,6 +116,17 @@ macro_rules! align_bytes {
 /// name in code. You should ensure tags are unique as this is not enforced by
 /// aseprite.
 ///
+/// Passing `compress` as the first argument compresses each sprite, which is
+/// then decompressed straight into video RAM when it is loaded. This saves ROM
+/// space at the cost of some time whenever a sprite is loaded.
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # use agb::{display::object::Graphics, include_aseprite};
+/// static GRAPHICS: &Graphics = include_aseprite!(compress, "examples/gfx/boss.aseprite");
+/// ```
+///
 /// Including from the out directory is supported through the `$OUT_DIR` token.
 ///
 /// ```rust,ignore
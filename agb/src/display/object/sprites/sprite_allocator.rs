@@ -1,14 +1,21 @@
-use core::{alloc::Allocator, ptr::NonNull};
+use core::{alloc::Allocator, cell::Cell, ptr::NonNull, slice};
 
 use alloc::{
     boxed::Box,
     rc::{Rc, Weak},
 };
+use critical_section::Mutex;
 
 use crate::{
-    agb_alloc::{block_allocator::BlockAllocator, bump_allocator::StartEnd, impl_zst_allocator},
-    display::palette16::Palette16,
+    agb_alloc::{
+        block_allocator::{BlockAllocator, BlockAllocatorStats},
+        bump_allocator::StartEnd,
+        impl_zst_allocator,
+    },
+    display::{palette16::Palette16, VRamUsage},
     hash_map::HashMap,
+    memory_mapped::MemoryMapped1DArray,
+    syscall,
 };
 
 use super::{
@@ -30,16 +37,40 @@ pub struct SpriteAllocator;
 
 impl_zst_allocator!(SpriteAllocator, SPRITE_ALLOCATOR);
 
-static PALETTE_ALLOCATOR: BlockAllocator = unsafe {
-    BlockAllocator::new(StartEnd {
-        start: || PALETTE_SPRITE,
-        end: || PALETTE_SPRITE + 0x200,
-    })
-};
+/// The number of object palettes, each of which has 16 colours
+pub const PALETTE_SLOTS: usize = 16;
+
+const PALETTE_OBJECT: MemoryMapped1DArray<u16, 256> =
+    unsafe { MemoryMapped1DArray::new(PALETTE_SPRITE) };
 
-pub struct PaletteAllocator;
+/// Which of the object palettes are in use, one bit per palette. Palettes are
+/// all the same size, so these are handed out directly rather than through a
+/// block allocator, which also lets them be placed in a particular slot.
+static USED_PALETTE_SLOTS: Mutex<Cell<u16>> = Mutex::new(Cell::new(0));
 
-impl_zst_allocator!(PaletteAllocator, PALETTE_ALLOCATOR);
+fn allocate_palette_slot(slot: Option<usize>) -> Result<Location, LoaderError> {
+    critical_section::with(|cs| {
+        let used = USED_PALETTE_SLOTS.borrow(cs);
+
+        let slot = match slot {
+            Some(slot) if used.get() & (1 << slot) != 0 => {
+                return Err(LoaderError::PaletteSlotInUse)
+            }
+            Some(slot) => slot,
+            None => match (!used.get()).trailing_zeros() as usize {
+                PALETTE_SLOTS => return Err(LoaderError::PaletteFull),
+                slot => slot,
+            },
+        };
+
+        used.set(used.get() | (1 << slot));
+        Ok(Location(slot))
+    })
+}
+
+fn used_palette_slots() -> u16 {
+    critical_section::with(|cs| USED_PALETTE_SLOTS.borrow(cs).get())
+}
 
 /// The Sprite Id is a thin wrapper around the pointer to the sprite in
 /// rom and is therefore a unique identifier to a sprite
@@ -76,11 +107,8 @@ impl Location {
     fn from_sprite_ptr(d: NonNull<u8>) -> Self {
         Self(((d.as_ptr() as usize) - TILE_SPRITE) / BYTES_PER_TILE_4BPP)
     }
-    fn from_palette_ptr(d: NonNull<u8>) -> Self {
-        Self((d.as_ptr() as usize - PALETTE_SPRITE) / Palette16::layout().size())
-    }
-    fn as_palette_ptr(self) -> *mut u8 {
-        (self.0 * Palette16::layout().size() + PALETTE_SPRITE) as *mut u8
+    fn as_palette_ptr(self) -> *mut u16 {
+        (self.0 * Palette16::layout().size() + PALETTE_SPRITE) as *mut u16
     }
     fn as_sprite_ptr(self) -> *mut u8 {
         (self.0 * BYTES_PER_TILE_4BPP + TILE_SPRITE) as *mut u8
@@ -94,11 +122,17 @@ struct PaletteVramData {
 
 impl Drop for PaletteVramData {
     fn drop(&mut self) {
-        unsafe { PALETTE_ALLOCATOR.dealloc(self.location.as_palette_ptr(), Palette16::layout()) }
+        critical_section::with(|cs| {
+            let used = USED_PALETTE_SLOTS.borrow(cs);
+            used.set(used.get() & !(1 << self.location.0));
+        });
     }
 }
 
 /// A palette in vram, this is reference counted so it is cheap to Clone.
+///
+/// Changing the colours of a palette changes them for every sprite which uses
+/// it, which is useful for effects like flashing when hit or team colours.
 #[derive(Debug, Clone)]
 pub struct PaletteVram {
     data: Rc<PaletteVramData>,
@@ -107,21 +141,79 @@ pub struct PaletteVram {
 impl PaletteVram {
     /// Attempts to allocate a new palette in sprite vram
     pub fn new(palette: &Palette16) -> Result<PaletteVram, LoaderError> {
-        let allocated = unsafe { PALETTE_ALLOCATOR.alloc(Palette16::layout()) }
-            .ok_or(LoaderError::PaletteFull)?;
+        Ok(Self::from_slot(palette, allocate_palette_slot(None)?))
+    }
+
+    /// Attempts to allocate a new palette in a particular one of the 16 object
+    /// palette slots, failing with [`LoaderError::PaletteSlotInUse`] if
+    /// something is already using it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `slot` is 16 or more.
+    pub fn new_in_slot(palette: &Palette16, slot: usize) -> Result<PaletteVram, LoaderError> {
+        assert!(slot < PALETTE_SLOTS, "There are only 16 object palettes");
+
+        Ok(Self::from_slot(palette, allocate_palette_slot(Some(slot))?))
+    }
+
+    fn from_slot(palette: &Palette16, location: Location) -> PaletteVram {
+        let palette_vram = PaletteVram {
+            data: Rc::new(PaletteVramData { location }),
+        };
+
+        palette_vram.set_palette(palette);
+        palette_vram
+    }
+
+    /// Which of the 16 object palettes this is
+    #[must_use]
+    pub fn slot(&self) -> usize {
+        self.data.location.0
+    }
 
+    /// Replaces every colour of the palette, immediately changing the colours
+    /// of all the sprites which use it.
+    pub fn set_palette(&self, palette: &Palette16) {
         unsafe {
-            allocated
-                .as_ptr()
-                .cast::<u16>()
+            self.data
+                .location
+                .as_palette_ptr()
                 .copy_from_nonoverlapping(palette.colours.as_ptr(), palette.colours.len());
         }
+    }
 
-        Ok(PaletteVram {
-            data: Rc::new(PaletteVramData {
-                location: Location::from_palette_ptr(allocated),
-            }),
-        })
+    /// Changes a single colour of the palette
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is 16 or more.
+    pub fn set_colour(&self, index: usize, colour: u16) {
+        assert!(index < 16, "Palettes only have 16 colours");
+        PALETTE_OBJECT.set(self.slot() * 16 + index, colour);
+    }
+
+    /// Reads a single colour back from the palette
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is 16 or more.
+    #[must_use]
+    pub fn colour(&self, index: usize) -> u16 {
+        assert!(index < 16, "Palettes only have 16 colours");
+        PALETTE_OBJECT.get(self.slot() * 16 + index)
+    }
+
+    /// Reads the whole palette back from palette RAM
+    #[must_use]
+    pub fn palette(&self) -> Palette16 {
+        Palette16::new(core::array::from_fn(|index| self.colour(index)))
+    }
+
+    /// Gets the index of the colour in this palette, or None if it doesn't exist
+    #[must_use]
+    pub fn find_colour_index(&self, colour: u16) -> Option<usize> {
+        (0..16).find(|&index| self.colour(index) == colour)
     }
 }
 
@@ -138,11 +230,18 @@ impl Drop for SpriteVramData {
     }
 }
 
+/// Why a sprite or palette couldn't be loaded into video RAM
 #[non_exhaustive]
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum LoaderError {
+    /// There is no free area of sprite video RAM large enough for the sprite
     SpriteFull,
+    /// All 16 object palettes are in use
     PaletteFull,
+    /// The palette slot asked for is already in use
+    PaletteSlotInUse,
+    /// The palette is already loaded into a different slot
+    PaletteAlreadyLoaded,
 }
 
 /// A sprite that is currently loaded into vram.
@@ -160,14 +259,27 @@ pub struct SpriteVram {
 }
 
 impl SpriteVram {
-    fn new(data: &[u8], size: Size, palette: PaletteVram) -> Result<SpriteVram, LoaderError> {
+    fn new(sprite: &Sprite, palette: PaletteVram) -> Result<SpriteVram, LoaderError> {
+        let size = sprite.size;
         let allocated =
             unsafe { SPRITE_ALLOCATOR.alloc(size.layout()) }.ok_or(LoaderError::SpriteFull)?;
-        unsafe {
-            allocated
-                .as_ptr()
-                .copy_from_nonoverlapping(data.as_ptr(), data.len());
+
+        if sprite.compressed {
+            let destination = unsafe {
+                slice::from_raw_parts_mut(
+                    allocated.as_ptr().cast::<u32>(),
+                    size.layout().size() / core::mem::size_of::<u32>(),
+                )
+            };
+            syscall::decompress_vram(sprite.data, destination);
+        } else {
+            unsafe {
+                allocated
+                    .as_ptr()
+                    .copy_from_nonoverlapping(sprite.data.as_ptr(), sprite.data.len());
+            }
         }
+
         Ok(unsafe { Self::from_location_size(allocated, size, palette) })
     }
 
@@ -205,7 +317,7 @@ impl SpriteLoader {
     ) -> Result<(Weak<SpriteVramData>, SpriteVram), LoaderError> {
         let palette = Self::try_get_vram_palette_asoc(palette_map, sprite.palette)?;
 
-        let sprite = SpriteVram::new(sprite.data, sprite.size, palette)?;
+        let sprite = SpriteVram::new(sprite, palette)?;
         Ok((Rc::downgrade(&sprite.data), sprite))
     }
 
@@ -267,6 +379,67 @@ impl SpriteLoader {
         Self::try_get_vram_palette_asoc(&mut self.static_palette_map, palette)
     }
 
+    /// Loads a palette into a particular one of the 16 object palette slots, so
+    /// that sprites using it which are loaded later use that slot. The palette
+    /// stays pinned to the slot for as long as the returned [`PaletteVram`] or
+    /// any sprite using it is alive.
+    ///
+    /// Fails with [`LoaderError::PaletteSlotInUse`] if another palette is in the
+    /// slot, or [`LoaderError::PaletteAlreadyLoaded`] if this palette is already
+    /// loaded into a different slot.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `slot` is 16 or more.
+    pub fn pin_palette(
+        &mut self,
+        palette: &'static Palette16,
+        slot: usize,
+    ) -> Result<PaletteVram, LoaderError> {
+        let id = PaletteId::from_static_palette(palette);
+
+        if let Some(data) = self.static_palette_map.get(&id).and_then(Weak::upgrade) {
+            return if data.location.0 == slot {
+                Ok(PaletteVram { data })
+            } else {
+                Err(LoaderError::PaletteAlreadyLoaded)
+            };
+        }
+
+        let palette_vram = PaletteVram::new_in_slot(palette, slot)?;
+        self.static_palette_map
+            .insert(id, Rc::downgrade(&palette_vram.data));
+
+        Ok(palette_vram)
+    }
+
+    /// Which of the 16 object palette slots are in use
+    #[must_use]
+    pub fn palette_slots(&self) -> [bool; PALETTE_SLOTS] {
+        let used = used_palette_slots();
+        core::array::from_fn(|slot| used & (1 << slot) != 0)
+    }
+
+    /// Reads a colour from any of the 16 object palettes, whether or not it is
+    /// in use.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `slot` or `index` is 16 or more.
+    #[must_use]
+    pub fn palette_colour(&self, slot: usize, index: usize) -> u16 {
+        assert!(slot < PALETTE_SLOTS, "There are only 16 object palettes");
+        assert!(index < 16, "Palettes only have 16 colours");
+
+        PALETTE_OBJECT.get(slot * 16 + index)
+    }
+
+    /// Gets the index of the colour for a given object palette, or None if it doesn't exist
+    #[must_use]
+    pub fn find_colour_index_16(&self, slot: usize, colour: u16) -> Option<usize> {
+        (0..16).find(|&index| self.palette_colour(slot, index) == colour)
+    }
+
     /// Allocates a sprite to vram, panics if it cannot fit.
     pub fn get_vram_sprite(&mut self, sprite: &'static Sprite) -> SpriteVram {
         self.try_get_vram_sprite(sprite)
@@ -286,6 +459,65 @@ impl SpriteLoader {
         }
     }
 
+    /// How much of the video RAM for sprite tiles is in use. A
+    /// [`LoaderError::SpriteFull`] means that there was no free block as large
+    /// as the sprite being loaded.
+    #[must_use]
+    pub fn sprite_usage(&self) -> VRamUsage {
+        VRamUsage::new(unsafe { SPRITE_ALLOCATOR.stats() })
+    }
+
+    /// How much of the sprite palette memory is in use. Each palette takes 32
+    /// bytes, and there is space for 16 of them.
+    #[must_use]
+    pub fn palette_usage(&self) -> VRamUsage {
+        let used = used_palette_slots();
+        let palette_size = Palette16::layout().size();
+
+        let mut largest_free_block = 0;
+        let mut free_blocks = 0;
+        let mut run = 0;
+
+        for slot in 0..=PALETTE_SLOTS {
+            if slot < PALETTE_SLOTS && used & (1 << slot) == 0 {
+                run += 1;
+                continue;
+            }
+
+            if run > 0 {
+                largest_free_block = largest_free_block.max(run * palette_size);
+                free_blocks += 1;
+                run = 0;
+            }
+        }
+
+        VRamUsage::new(BlockAllocatorStats {
+            capacity: PALETTE_SLOTS * palette_size,
+            used: used.count_ones() as usize * palette_size,
+            largest_free_block,
+            free_blocks,
+        })
+    }
+
+    /// The number of [`SpriteVram`]s referring to the copy of `sprite` in
+    /// video RAM, which is 0 if it isn't loaded.
+    #[must_use]
+    pub fn sprite_reference_count(&self, sprite: &'static Sprite) -> usize {
+        self.static_sprite_map
+            .get(&SpriteId::from_static_sprite(sprite))
+            .map_or(0, Weak::strong_count)
+    }
+
+    /// The number of references to the copy of `palette` in palette RAM, which
+    /// is 0 if it isn't loaded. Every sprite in video RAM which uses the palette
+    /// counts as a reference along with any [`PaletteVram`]s for it.
+    #[must_use]
+    pub fn palette_reference_count(&self, palette: &'static Palette16) -> usize {
+        self.static_palette_map
+            .get(&PaletteId::from_static_palette(palette))
+            .map_or(0, Weak::strong_count)
+    }
+
     /// Remove internal references to sprites that no longer exist in vram. If
     /// you neglect calling this, memory will leak over time in relation to the
     /// total number of different sprites used. It will not leak vram.
@@ -403,3 +635,60 @@ impl DynamicSprite {
         unsafe { SpriteVram::from_location_size(data.cast(), self.size, palette) }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::display::object::{include_aseprite, Graphics};
+
+    use super::*;
+
+    static PINNED_PALETTE: Palette16 = Palette16::new([0x1234; 16]);
+
+    static TEST_SPRITES: &Graphics = include_aseprite!("examples/gfx/tall.aseprite");
+    static TEST_SPRITE: &Sprite = &TEST_SPRITES.sprites()[0];
+
+    #[test_case]
+    fn counts_references_to_sprites_and_palettes(gba: &mut crate::Gba) {
+        let (_, mut loader) = gba.display.object.get_unmanaged();
+
+        assert_eq!(loader.sprite_reference_count(TEST_SPRITE), 0);
+        assert_eq!(loader.palette_reference_count(TEST_SPRITE.palette), 0);
+
+        let sprite = loader.get_vram_sprite(TEST_SPRITE);
+        let sprite_clone = sprite.clone();
+        assert_eq!(loader.sprite_reference_count(TEST_SPRITE), 2);
+        assert_eq!(loader.palette_reference_count(TEST_SPRITE.palette), 1);
+
+        drop(sprite);
+        drop(sprite_clone);
+        assert_eq!(loader.sprite_reference_count(TEST_SPRITE), 0);
+        assert_eq!(loader.palette_reference_count(TEST_SPRITE.palette), 0);
+    }
+
+    #[test_case]
+    fn palettes_can_be_pinned_and_replaced(gba: &mut crate::Gba) {
+        let (_, mut loader) = gba.display.object.get_unmanaged();
+
+        let pinned = loader.pin_palette(&PINNED_PALETTE, 15).unwrap();
+        assert_eq!(pinned.slot(), 15);
+        assert!(loader.palette_slots()[15]);
+        assert_eq!(loader.get_vram_palette(&PINNED_PALETTE).slot(), 15);
+
+        assert_eq!(
+            PaletteVram::new_in_slot(&Palette16::new([0; 16]), 15).unwrap_err(),
+            LoaderError::PaletteSlotInUse
+        );
+
+        pinned.set_colour(3, 0x7fff);
+        assert_eq!(pinned.colour(3), 0x7fff);
+        assert_eq!(loader.palette_colour(15, 3), 0x7fff);
+        assert_eq!(pinned.find_colour_index(0x7fff), Some(3));
+        assert_eq!(loader.find_colour_index_16(15, 0x1234), Some(0));
+
+        pinned.set_palette(&Palette16::new([0x42; 16]));
+        assert_eq!(pinned.palette().colours, [0x42; 16]);
+
+        drop(pinned);
+        assert!(!loader.palette_slots()[15]);
+    }
+}
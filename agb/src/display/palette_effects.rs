@@ -0,0 +1,499 @@
+//! Fades, cross-fades and colour cycling done by changing the palettes.
+//!
+//! [`PaletteEffects`] keeps a copy of the background and object palettes in
+//! work RAM. Effects are worked out on this copy each time you call
+//! [`update`](PaletteEffects::update), and then copied to palette RAM in one go
+//! with [`commit`](PaletteEffects::commit), which you should call just after
+//! waiting for vblank so the change isn't visible halfway down the screen.
+//!
+//! Unlike [`Blend::set_fade`](super::blend::Blend::set_fade), which can only
+//! fade towards black or white, palette fades can go towards any colour or to a
+//! completely different set of palettes.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! use agb::display::{palette16::Palette16, palette_effects::PaletteEffects};
+//!
+//! # fn foo() {
+//! let vblank = agb::interrupt::VBlank::get();
+//! let mut effects = PaletteEffects::new();
+//!
+//! // fade everything to red over half a second
+//! effects.background().fade_to_colour(0x001f, 30);
+//! effects.objects().fade_to_colour(0x001f, 30);
+//!
+//! while effects.is_fading() {
+//!     effects.update();
+//!
+//!     vblank.wait_for_vblank();
+//!     effects.commit();
+//! }
+//! # }
+//! ```
+
+use alloc::{boxed::Box, vec::Vec};
+use core::ops::Range;
+
+use crate::{dma, fixnum::Num, memory_mapped::MemoryMapped1DArray};
+
+use super::palette16::Palette16;
+
+const PALETTE_BACKGROUND: MemoryMapped1DArray<u16, 256> =
+    unsafe { MemoryMapped1DArray::new(0x0500_0000) };
+const PALETTE_OBJECT: MemoryMapped1DArray<u16, 256> =
+    unsafe { MemoryMapped1DArray::new(0x0500_0200) };
+
+/// Mixes two 15 bit colours, giving `from` when `amount` is 0 and `to` when
+/// `amount` is 1. Amounts outside that range are clamped.
+#[must_use]
+pub fn interpolate_colour(from: u16, to: u16, amount: Num<i32, 8>) -> u16 {
+    let amount = amount.to_raw().clamp(0, 1 << 8);
+
+    (0..3).fold(0, |colour, channel| {
+        let shift = channel * 5;
+        let from = i32::from((from >> shift) & 0x1f);
+        let to = i32::from((to >> shift) & 0x1f);
+
+        let mixed = from + (((to - from) * amount) >> 8);
+        colour | ((mixed as u16) << shift)
+    })
+}
+
+enum FadeTarget {
+    Colour(u16),
+    Palettes(Box<[u16; 256]>),
+}
+
+impl FadeTarget {
+    fn colour(&self, index: usize) -> u16 {
+        match self {
+            FadeTarget::Colour(colour) => *colour,
+            FadeTarget::Palettes(palettes) => palettes[index],
+        }
+    }
+}
+
+struct Fade {
+    target: FadeTarget,
+    from: Num<i32, 8>,
+    to: Num<i32, 8>,
+    frame: u16,
+    frames: u16,
+}
+
+impl Fade {
+    fn amount(&self) -> Num<i32, 8> {
+        if self.is_finished() {
+            return self.to;
+        }
+
+        self.from + (self.to - self.from) * i32::from(self.frame) / i32::from(self.frames)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.frame >= self.frames
+    }
+}
+
+struct ColourCycle {
+    colours: Range<usize>,
+    frames_per_step: u16,
+    timer: u16,
+}
+
+/// The copy of either the background or object palettes kept by
+/// [`PaletteEffects`].
+pub struct ShadowPalette {
+    hardware: MemoryMapped1DArray<u16, 256>,
+
+    base: [u16; 256],
+    output: [u16; 256],
+
+    fade: Option<Fade>,
+    cycles: Vec<ColourCycle>,
+
+    dirty: bool,
+    uncommitted: bool,
+}
+
+impl ShadowPalette {
+    fn new(hardware: MemoryMapped1DArray<u16, 256>) -> Self {
+        let mut palette = Self {
+            hardware,
+            base: [0; 256],
+            output: [0; 256],
+            fade: None,
+            cycles: Vec::new(),
+            dirty: false,
+            uncommitted: false,
+        };
+
+        palette.capture();
+        palette
+    }
+
+    /// Replaces the copy of the palettes with what is currently in palette RAM,
+    /// and stops any fade.
+    pub fn capture(&mut self) {
+        for (i, colour) in self.base.iter_mut().enumerate() {
+            *colour = self.hardware.get(i);
+        }
+
+        self.output = self.base;
+        self.fade = None;
+        self.dirty = false;
+        self.uncommitted = false;
+    }
+
+    /// Sets the palettes which effects start from, starting at palette 0.
+    pub fn set_palettes(&mut self, palettes: &[Palette16]) {
+        for (palette, colours) in palettes.iter().zip(self.base.chunks_exact_mut(16)) {
+            colours.copy_from_slice(&palette.colours);
+        }
+
+        self.dirty = true;
+    }
+
+    /// Sets a single colour of the palettes which effects start from.
+    pub fn set_colour(&mut self, index: usize, colour: u16) {
+        self.base[index] = colour;
+        self.dirty = true;
+    }
+
+    /// The colour before any fade is applied
+    #[must_use]
+    pub fn colour(&self, index: usize) -> u16 {
+        self.base[index]
+    }
+
+    /// The colour which will be copied to palette RAM by the next commit
+    #[must_use]
+    pub fn output_colour(&self, index: usize) -> u16 {
+        self.output[index]
+    }
+
+    /// Fades every colour towards `colour` over `frames` frames. The palettes
+    /// stay that colour once the fade finishes, until you call
+    /// [`fade_in`](ShadowPalette::fade_in).
+    pub fn fade_to_colour(&mut self, colour: u16, frames: u16) {
+        self.fade = Some(Fade {
+            target: FadeTarget::Colour(colour),
+            from: 0.into(),
+            to: 1.into(),
+            frame: 0,
+            frames: frames.max(1),
+        });
+        self.dirty = true;
+    }
+
+    /// Fades from the current palettes to `palettes` over `frames` frames, after
+    /// which `palettes` become the palettes effects start from.
+    pub fn cross_fade(&mut self, palettes: &[Palette16], frames: u16) {
+        let mut target = Box::new(self.base);
+        for (palette, colours) in palettes.iter().zip(target.chunks_exact_mut(16)) {
+            colours.copy_from_slice(&palette.colours);
+        }
+
+        self.fade = Some(Fade {
+            target: FadeTarget::Palettes(target),
+            from: 0.into(),
+            to: 1.into(),
+            frame: 0,
+            frames: frames.max(1),
+        });
+        self.dirty = true;
+    }
+
+    /// Fades back from the colour given to
+    /// [`fade_to_colour`](ShadowPalette::fade_to_colour) over `frames` frames.
+    /// Does nothing if the palettes aren't faded.
+    pub fn fade_in(&mut self, frames: u16) {
+        let Some(fade) = &mut self.fade else {
+            return;
+        };
+
+        // Carry on from wherever the fade out had got to
+        fade.from = fade.amount();
+        fade.to = 0.into();
+        fade.frame = 0;
+        fade.frames = frames.max(1);
+    }
+
+    /// Sets how far through the current fade the palettes are, where 0 is the
+    /// starting palettes and 1 is the target, for when you want to control the
+    /// fade yourself rather than have it move on every frame. The fade then
+    /// stays at this amount until another fade is started. Does nothing if
+    /// there is no fade.
+    pub fn set_fade_amount(&mut self, amount: Num<i32, 8>) {
+        if let Some(fade) = &mut self.fade {
+            let amount = amount.clamp(0.into(), 1.into());
+
+            fade.from = amount;
+            fade.to = amount;
+            fade.frame = 0;
+            fade.frames = 0;
+            self.dirty = true;
+        }
+    }
+
+    /// Whether a fade is in progress
+    #[must_use]
+    pub fn is_fading(&self) -> bool {
+        self.fade.as_ref().is_some_and(|fade| !fade.is_finished())
+    }
+
+    /// Rotates the `colours` (indices into all 256 colours) by one place every
+    /// `frames_per_step` frames, the classic way of animating water and
+    /// waterfalls.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is empty or extends past the end of the palettes.
+    pub fn add_colour_cycle(&mut self, colours: Range<usize>, frames_per_step: u16) {
+        assert!(
+            colours.start < colours.end,
+            "Colour cycle must contain at least one colour"
+        );
+        assert!(
+            colours.end <= 256,
+            "Colour cycle must be within the palettes"
+        );
+
+        self.cycles.push(ColourCycle {
+            colours,
+            frames_per_step,
+            timer: 0,
+        });
+    }
+
+    /// Stops every colour cycle, leaving the colours where they are.
+    pub fn clear_colour_cycles(&mut self) {
+        self.cycles.clear();
+    }
+
+    fn update(&mut self) {
+        for cycle in &mut self.cycles {
+            cycle.timer += 1;
+            if cycle.timer >= cycle.frames_per_step {
+                cycle.timer = 0;
+                self.base[cycle.colours.clone()].rotate_right(1);
+                self.dirty = true;
+            }
+        }
+
+        if let Some(fade) = &mut self.fade {
+            if !fade.is_finished() {
+                fade.frame += 1;
+                self.dirty = true;
+            }
+        }
+
+        // Fades controlled by set_fade_amount have no frames and never finish by themselves
+        if self
+            .fade
+            .as_ref()
+            .is_some_and(|fade| fade.frames > 0 && fade.is_finished())
+        {
+            match self.fade.take() {
+                Some(fade) if fade.to == 0.into() => {}
+                Some(Fade {
+                    target: FadeTarget::Palettes(target),
+                    ..
+                }) => self.base = *target,
+                // Fades to a colour stay faded until faded back in
+                fade => self.fade = fade,
+            }
+        }
+
+        self.update_output();
+    }
+
+    fn update_output(&mut self) {
+        if !self.dirty {
+            return;
+        }
+
+        self.dirty = false;
+        self.uncommitted = true;
+
+        match &self.fade {
+            Some(fade) => {
+                let amount = fade.amount();
+                for (i, output) in self.output.iter_mut().enumerate() {
+                    *output = interpolate_colour(self.base[i], fade.target.colour(i), amount);
+                }
+            }
+            None => self.output = self.base,
+        }
+    }
+
+    fn commit(&mut self) {
+        self.update_output();
+
+        if !self.uncommitted {
+            return;
+        }
+
+        // VRamManager::background_palette_colour_dma is a target for changing a
+        // single background colour each scanline, so copy the whole palette
+        // (background or object) directly instead.
+        dma::dma3_exclusive(|| unsafe {
+            dma::dma_copy16(
+                self.output.as_ptr(),
+                self.hardware.as_ptr(),
+                self.output.len(),
+            );
+        });
+
+        self.uncommitted = false;
+    }
+}
+
+/// Keeps copies of the background and object palettes and applies fades and
+/// colour cycles to them. See the [module level documentation](crate::display::palette_effects) for an
+/// example.
+///
+/// Object palettes are allocated as sprites are loaded, so call
+/// [`capture`](ShadowPalette::capture) on [`objects`](PaletteEffects::objects)
+/// after loading new sprites to stop their palettes being overwritten by the
+/// old copy.
+pub struct PaletteEffects {
+    background: ShadowPalette,
+    objects: ShadowPalette,
+}
+
+impl PaletteEffects {
+    /// Creates palette effects starting from whatever is currently in palette RAM
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            background: ShadowPalette::new(PALETTE_BACKGROUND),
+            objects: ShadowPalette::new(PALETTE_OBJECT),
+        }
+    }
+
+    /// The copy of the background palettes
+    pub fn background(&mut self) -> &mut ShadowPalette {
+        &mut self.background
+    }
+
+    /// The copy of the object palettes
+    pub fn objects(&mut self) -> &mut ShadowPalette {
+        &mut self.objects
+    }
+
+    /// Whether either the background or object palettes are part way through a fade
+    #[must_use]
+    pub fn is_fading(&self) -> bool {
+        self.background.is_fading() || self.objects.is_fading()
+    }
+
+    /// Moves every fade and colour cycle on by one frame. Call this once per frame.
+    pub fn update(&mut self) {
+        self.background.update();
+        self.objects.update();
+    }
+
+    /// Copies the palettes which have changed since the last commit to palette
+    /// RAM using DMA. Call this straight after waiting for vblank.
+    pub fn commit(&mut self) {
+        self.background.commit();
+        self.objects.commit();
+    }
+}
+
+impl Default for PaletteEffects {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test_case]
+    fn interpolates_each_channel(_gba: &mut crate::Gba) {
+        let from = 0b00000_11111_00000;
+        let to = 0b11111_00000_01000;
+
+        assert_eq!(interpolate_colour(from, to, 0.into()), from);
+        assert_eq!(interpolate_colour(from, to, 1.into()), to);
+        assert_eq!(interpolate_colour(from, to, 2.into()), to);
+        assert_eq!(
+            interpolate_colour(from, to, Num::new(1) / 2),
+            0b01111_01111_00100
+        );
+    }
+
+    #[test_case]
+    fn fades_to_colour_and_back(_gba: &mut crate::Gba) {
+        let mut effects = PaletteEffects::new();
+        let background = effects.background();
+
+        background.set_palettes(&[Palette16::new([0; 16])]);
+        background.fade_to_colour(0x7fff, 4);
+
+        for _ in 0..2 {
+            effects.update();
+        }
+
+        assert!(effects.is_fading());
+        assert_eq!(effects.background().output_colour(3), 0b01111_01111_01111);
+
+        for _ in 0..2 {
+            effects.update();
+        }
+
+        assert!(!effects.is_fading());
+        assert_eq!(effects.background().output_colour(3), 0x7fff);
+
+        effects.background().fade_in(2);
+        for _ in 0..2 {
+            effects.update();
+        }
+
+        assert_eq!(effects.background().output_colour(3), 0);
+        assert!(effects.background().fade.is_none());
+
+        effects.commit();
+        assert_eq!(PALETTE_BACKGROUND.get(3), 0);
+    }
+
+    #[test_case]
+    fn cross_fade_replaces_palettes(_gba: &mut crate::Gba) {
+        let mut effects = PaletteEffects::new();
+        let objects = effects.objects();
+
+        objects.set_palettes(&[Palette16::new([0; 16])]);
+        objects.cross_fade(&[Palette16::new([0x1f; 16])], 2);
+
+        effects.update();
+        effects.update();
+
+        assert_eq!(effects.objects().colour(5), 0x1f);
+        assert_eq!(effects.objects().output_colour(5), 0x1f);
+        assert!(!effects.is_fading());
+    }
+
+    #[test_case]
+    fn colour_cycles_rotate(_gba: &mut crate::Gba) {
+        let mut effects = PaletteEffects::new();
+        let background = effects.background();
+
+        background.set_palettes(&[Palette16::new([
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+        ])]);
+        background.add_colour_cycle(1..4, 2);
+
+        effects.update();
+        assert_eq!(effects.background().output_colour(1), 1);
+
+        effects.update();
+        assert_eq!(
+            [1, 2, 3].map(|i| effects.background().output_colour(i)),
+            [3, 1, 2]
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use super::tiled::{TileSet, TileSetting};
+use super::tiled::{CompressedTileSet, TileAnimation, TileAnimator, TileSet, TileSetting};
 
 #[non_exhaustive]
 pub struct TileData {
@@ -15,3 +15,168 @@ impl TileData {
         }
     }
 }
+
+/// Tile data created with the `compress` option of
+/// [`include_background_gfx!`](crate::include_background_gfx). The tile settings
+/// refer to tiles in the compressed tile set, so should be converted with
+/// [`VRamTileSet::tile_setting`](super::tiled::VRamTileSet::tile_setting) once
+/// the tiles are loaded.
+#[non_exhaustive]
+pub struct CompressedTileData {
+    pub tiles: CompressedTileSet<'static>,
+    pub tile_settings: &'static [TileSetting],
+}
+
+impl CompressedTileData {
+    #[must_use]
+    pub const fn new(
+        tiles: CompressedTileSet<'static>,
+        tile_settings: &'static [TileSetting],
+    ) -> Self {
+        CompressedTileData {
+            tiles,
+            tile_settings,
+        }
+    }
+}
+
+/// Tile data created with the `affine` option of
+/// [`include_background_gfx!`](crate::include_background_gfx), for use with an
+/// [`AffineMap`](super::tiled::AffineMap). The tiles are always 256 colours and
+/// are never flipped, so each entry of `tile_indices` can be passed straight to
+/// [`AffineMap::set_tile`](super::tiled::AffineMap::set_tile).
+#[non_exhaustive]
+pub struct AffineTileData {
+    pub tiles: TileSet<'static>,
+    pub tile_indices: &'static [u16],
+}
+
+impl AffineTileData {
+    #[must_use]
+    pub const fn new(tiles: TileSet<'static>, tile_indices: &'static [u16]) -> Self {
+        AffineTileData {
+            tiles,
+            tile_indices,
+        }
+    }
+}
+
+/// Tile data created with the `animated` option of
+/// [`include_background_gfx!`](crate::include_background_gfx). The tile settings
+/// are for the first frame of the animation, and `animations` lists the tiles
+/// which change in later frames.
+#[non_exhaustive]
+pub struct AnimatedTileData {
+    pub tiles: TileSet<'static>,
+    pub tile_settings: &'static [TileSetting],
+    pub animations: &'static [TileAnimation<'static>],
+}
+
+impl AnimatedTileData {
+    #[must_use]
+    pub const fn new(
+        tiles: TileSet<'static>,
+        tile_settings: &'static [TileSetting],
+        animations: &'static [TileAnimation<'static>],
+    ) -> Self {
+        AnimatedTileData {
+            tiles,
+            tile_settings,
+            animations,
+        }
+    }
+
+    /// Creates a [`TileAnimator`] which plays the animations of this tile data
+    #[must_use]
+    pub fn animator(&self) -> TileAnimator<'_> {
+        TileAnimator::new(&self.tiles, self.animations)
+    }
+}
+
+/// A 15 bit colour image created by [`include_bitmap!`](crate::include_bitmap),
+/// which can be drawn with [`Bitmap3::draw_bitmap`](super::bitmap3::Bitmap3::draw_bitmap)
+/// or [`Bitmap5::draw_bitmap`](super::bitmap5::Bitmap5::draw_bitmap).
+#[non_exhaustive]
+pub struct BitmapData {
+    pub width: u16,
+    pub height: u16,
+    /// The colour of each pixel, one row after another
+    pub data: &'static [u16],
+}
+
+impl BitmapData {
+    /// Creates a bitmap `width` pixels wide and `height` pixels tall, with
+    /// the colour of each pixel in `data`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` isn't `width * height` pixels long. In a `const` or
+    /// `static` this is a compile error:
+    ///
+    /// ```rust,compile_fail
+    /// # use agb::display::tile_data::BitmapData;
+    /// static TOO_SHORT: BitmapData = BitmapData::new(4, 2, &[0; 7]);
+    /// ```
+    #[must_use]
+    pub const fn new(width: u16, height: u16, data: &'static [u16]) -> Self {
+        assert!(
+            data.len() == width as usize * height as usize,
+            "Bitmap data doesn't match the bitmap's size"
+        );
+
+        BitmapData {
+            width,
+            height,
+            data,
+        }
+    }
+}
+
+/// A paletted image created by [`include_bitmap!`](crate::include_bitmap) with
+/// the `256` option, which can be drawn with
+/// [`Bitmap4::draw_bitmap`](super::bitmap4::Bitmap4::draw_bitmap). Load
+/// `palette` into the background palette with
+/// [`Bitmap4::set_palette`](super::bitmap4::Bitmap4::set_palette) to show it
+/// in the right colours.
+#[non_exhaustive]
+pub struct PalettedBitmapData {
+    pub width: u16,
+    pub height: u16,
+    /// The palette index of each pixel, one row after another. Aligned to a 2
+    /// byte boundary so it can be copied straight into video RAM.
+    pub data: &'static [u8],
+    pub palette: &'static [u16],
+}
+
+impl PalettedBitmapData {
+    /// Creates paletted bitmap data. This is used by
+    /// [`include_bitmap!`](crate::include_bitmap) and should generally not be
+    /// used outside it.
+    ///
+    /// # Safety
+    ///
+    /// `data` must be aligned to a 2 byte boundary.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` isn't `width * height` pixels long.
+    #[must_use]
+    pub const unsafe fn new(
+        width: u16,
+        height: u16,
+        data: &'static [u8],
+        palette: &'static [u16],
+    ) -> Self {
+        assert!(
+            data.len() == width as usize * height as usize,
+            "Bitmap data doesn't match the bitmap's size"
+        );
+
+        PalettedBitmapData {
+            width,
+            height,
+            data,
+            palette,
+        }
+    }
+}
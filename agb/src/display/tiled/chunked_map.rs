@@ -0,0 +1,383 @@
+use core::cell::RefCell;
+
+use alloc::{boxed::Box, vec::Vec};
+
+use super::{PartialUpdateStatus, TileSet, TileSetting};
+use crate::{display, fixnum::Vector2D, ExternalAllocator};
+
+/// The width and height of a chunk in tiles.
+pub const CHUNK_SIZE: usize = 16;
+
+/// The number of tiles in a single chunk.
+pub const TILES_PER_CHUNK: usize = CHUNK_SIZE * CHUNK_SIZE;
+
+/// The fewest chunks a [`ChunkedMap`] can cache. The screen plus the border which
+/// [`InfiniteScrolledMap`](super::InfiniteScrolledMap) draws around it is 264x184
+/// pixels, which can touch 4x3 chunks when it isn't lined up with them.
+pub const MIN_CACHED_CHUNKS: usize = 12;
+
+/// Provides the tiles for a [`ChunkedMap`], one chunk of
+/// [`CHUNK_SIZE`] x [`CHUNK_SIZE`] tiles at a time.
+///
+/// This is where you would decompress or otherwise generate your level data.
+/// It is only called when a chunk is needed which isn't already cached.
+pub trait ChunkSource<'a> {
+    /// The tileset which every tile setting returned by [`load_chunk`](ChunkSource::load_chunk)
+    /// refers to.
+    fn tileset(&self) -> &'a TileSet<'a>;
+
+    /// Fills `tiles` with the tile settings for the chunk at position `chunk`,
+    /// in row major order. `chunk` is measured in chunks, so the top left tile
+    /// of the chunk is at `chunk * CHUNK_SIZE` in tile coordinates.
+    ///
+    /// This is called for any chunk the map needs, including ones outside of
+    /// your world, so you should fill those with something sensible such as
+    /// [`TileSetting::BLANK`].
+    fn load_chunk(&mut self, chunk: Vector2D<i32>, tiles: &mut [TileSetting; TILES_PER_CHUNK]);
+}
+
+/// A map which is split into chunks that are loaded on demand from a
+/// [`ChunkSource`] and kept in a least recently used cache in ewram.
+///
+/// This allows for worlds which are far larger than could fit in RAM
+/// decompressed, while each tile lookup only needs to decompress anything when
+/// a new chunk comes into view.
+///
+/// Use [`InfiniteScrolledMap::new_chunked`](super::InfiniteScrolledMap::new_chunked)
+/// to display it, and call [`prefetch`](ChunkedMap::prefetch) when you have some
+/// spare time in a frame to load chunks before they are needed.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// use agb::display::tiled::{
+///     ChunkSource, ChunkedMap, InfiniteScrolledMap, RegularBackgroundSize, TileFormat, TileSet,
+///     TileSetting, TILES_PER_CHUNK,
+/// };
+/// use agb::display::Priority;
+/// use agb::fixnum::Vector2D;
+///
+/// agb::include_background_gfx!(water_tiles, tiles => "examples/water_tiles.png");
+///
+/// struct CheckerboardWorld;
+///
+/// impl ChunkSource<'static> for CheckerboardWorld {
+///     fn tileset(&self) -> &'static TileSet<'static> {
+///         &water_tiles::tiles.tiles
+///     }
+///
+///     fn load_chunk(&mut self, chunk: Vector2D<i32>, tiles: &mut [TileSetting; TILES_PER_CHUNK]) {
+///         // this is where you would decompress your level data
+///         let setting = water_tiles::tiles.tile_settings[((chunk.x + chunk.y) & 1) as usize];
+///         tiles.fill(setting);
+///     }
+/// }
+///
+/// # fn foo(mut gba: agb::Gba) {
+/// let (gfx, mut vram) = gba.display.video.tiled0();
+/// let chunks = ChunkedMap::new(CheckerboardWorld, 12);
+///
+/// let mut backdrop = InfiniteScrolledMap::new_chunked(
+///     gfx.background(Priority::P2, RegularBackgroundSize::Background32x32, TileFormat::FourBpp),
+///     &chunks,
+/// );
+///
+/// backdrop.init(&mut vram, (0, 0).into(), &mut || {});
+/// backdrop.commit(&mut vram);
+/// backdrop.set_visible(true);
+/// # }
+/// ```
+pub struct ChunkedMap<'a, S> {
+    tileset: &'a TileSet<'a>,
+    cache: RefCell<ChunkCache<S>>,
+}
+
+struct ChunkCache<S> {
+    source: S,
+    capacity: usize,
+    chunks: Vec<CachedChunk, ExternalAllocator>,
+    most_recent: usize,
+    clock: u32,
+    loads: usize,
+}
+
+struct CachedChunk {
+    position: Vector2D<i32>,
+    last_used: u32,
+    tiles: Box<[TileSetting; TILES_PER_CHUNK], ExternalAllocator>,
+}
+
+impl<'a, S: ChunkSource<'a>> ChunkedMap<'a, S> {
+    /// Creates a new chunked map which caches up to `capacity` decompressed
+    /// chunks in ewram at once. Each chunk takes 512 bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is less than [`MIN_CACHED_CHUNKS`], since the
+    /// chunks needed for a single redraw would evict each other.
+    #[must_use]
+    pub fn new(source: S, capacity: usize) -> Self {
+        assert!(
+            capacity >= MIN_CACHED_CHUNKS,
+            "Must be able to cache at least {MIN_CACHED_CHUNKS} chunks"
+        );
+
+        Self {
+            tileset: source.tileset(),
+            cache: RefCell::new(ChunkCache {
+                source,
+                capacity,
+                chunks: Vec::with_capacity_in(capacity, ExternalAllocator),
+                most_recent: 0,
+                clock: 0,
+                loads: 0,
+            }),
+        }
+    }
+
+    /// Returns the tile at the given tile position, loading its chunk if it
+    /// isn't already cached. This has the same signature as the function passed
+    /// to [`InfiniteScrolledMap::new`](super::InfiniteScrolledMap::new).
+    pub fn tile(&self, pos: Vector2D<i32>) -> (&'a TileSet<'a>, TileSetting) {
+        let chunk_size = CHUNK_SIZE as i32;
+        let chunk = Vector2D::new(pos.x.div_euclid(chunk_size), pos.y.div_euclid(chunk_size));
+        let index_in_chunk =
+            pos.x.rem_euclid(chunk_size) + pos.y.rem_euclid(chunk_size) * chunk_size;
+
+        let mut cache = self.cache.borrow_mut();
+        let cached = cache.chunk(chunk);
+
+        (self.tileset, cached.tiles[index_in_chunk as usize])
+    }
+
+    /// Loads up to `max_loads` of the chunks which would be needed to display
+    /// the screen with its top left corner at `pos` (in pixels) which aren't
+    /// already cached, including the border around the screen.
+    ///
+    /// Returns [`PartialUpdateStatus::Done`] if every chunk needed is now
+    /// cached, or [`PartialUpdateStatus::Continue`] if there are more to load.
+    pub fn prefetch(&self, pos: Vector2D<i32>, max_loads: usize) -> PartialUpdateStatus {
+        let chunk_pixels = (CHUNK_SIZE * 8) as i32;
+
+        // the first and last pixels which InfiniteScrolledMap draws, one tile
+        // either side of the screen
+        let top_left = pos - (8, 8).into();
+        let bottom_right = pos + (display::WIDTH + 15, display::HEIGHT + 15).into();
+
+        let chunks = || {
+            (top_left.y.div_euclid(chunk_pixels)..=bottom_right.y.div_euclid(chunk_pixels))
+                .flat_map(move |chunk_y| {
+                    (top_left.x.div_euclid(chunk_pixels)..=bottom_right.x.div_euclid(chunk_pixels))
+                        .map(move |chunk_x| Vector2D::new(chunk_x, chunk_y))
+                })
+        };
+
+        let mut cache = self.cache.borrow_mut();
+
+        // Mark every chunk which is already cached as used first, so loading the
+        // missing ones can't evict them
+        for chunk in chunks() {
+            if cache.is_cached(chunk) {
+                cache.chunk(chunk);
+            }
+        }
+
+        let mut loads = 0;
+        for chunk in chunks() {
+            if cache.is_cached(chunk) {
+                continue;
+            }
+
+            if loads == max_loads {
+                return PartialUpdateStatus::Continue;
+            }
+
+            cache.chunk(chunk);
+            loads += 1;
+        }
+
+        PartialUpdateStatus::Done
+    }
+
+    /// Removes every chunk from the cache, for example if the level data has
+    /// changed.
+    pub fn invalidate(&self) {
+        self.cache.borrow_mut().chunks.clear();
+    }
+
+    /// The number of times a chunk has been loaded from the [`ChunkSource`].
+    /// Useful for tuning the cache capacity.
+    #[must_use]
+    pub fn chunks_loaded(&self) -> usize {
+        self.cache.borrow().loads
+    }
+
+    /// Gives access to the underlying chunk source.
+    ///
+    /// If you change the level data, you should call [`invalidate`](ChunkedMap::invalidate)
+    /// afterwards.
+    pub fn with_source<T>(&self, f: impl FnOnce(&mut S) -> T) -> T {
+        f(&mut self.cache.borrow_mut().source)
+    }
+}
+
+impl<'a, S: ChunkSource<'a>> ChunkCache<S> {
+    fn is_cached(&self, position: Vector2D<i32>) -> bool {
+        self.chunks.iter().any(|chunk| chunk.position == position)
+    }
+
+    fn chunk(&mut self, position: Vector2D<i32>) -> &CachedChunk {
+        self.clock = self.clock.wrapping_add(1);
+
+        // Consecutive lookups are very likely to be in the same chunk
+        let index = if self
+            .chunks
+            .get(self.most_recent)
+            .is_some_and(|chunk| chunk.position == position)
+        {
+            self.most_recent
+        } else if let Some(index) = self
+            .chunks
+            .iter()
+            .position(|chunk| chunk.position == position)
+        {
+            index
+        } else {
+            self.load(position)
+        };
+
+        self.most_recent = index;
+
+        let chunk = &mut self.chunks[index];
+        chunk.last_used = self.clock;
+        chunk
+    }
+
+    fn load(&mut self, position: Vector2D<i32>) -> usize {
+        self.loads += 1;
+
+        let index = if self.chunks.len() < self.capacity {
+            self.chunks.push(CachedChunk {
+                position,
+                last_used: self.clock,
+                tiles: Box::new_in([TileSetting::BLANK; TILES_PER_CHUNK], ExternalAllocator),
+            });
+
+            self.chunks.len() - 1
+        } else {
+            let clock = self.clock;
+            self.chunks
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, chunk)| clock.wrapping_sub(chunk.last_used))
+                .map(|(index, _)| index)
+                .expect("Cache should never be empty here")
+        };
+
+        let chunk = &mut self.chunks[index];
+        chunk.position = position;
+        self.source.load_chunk(position, &mut chunk.tiles);
+
+        index
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::display::tiled::TileFormat;
+
+    static TILESET: TileSet<'static> = TileSet::new(&[], TileFormat::FourBpp);
+
+    struct CountingSource;
+
+    impl ChunkSource<'static> for CountingSource {
+        fn tileset(&self) -> &'static TileSet<'static> {
+            &TILESET
+        }
+
+        fn load_chunk(&mut self, chunk: Vector2D<i32>, tiles: &mut [TileSetting; TILES_PER_CHUNK]) {
+            for (i, tile) in tiles.iter_mut().enumerate() {
+                let x = chunk.x * CHUNK_SIZE as i32 + (i % CHUNK_SIZE) as i32;
+                let y = chunk.y * CHUNK_SIZE as i32 + (i / CHUNK_SIZE) as i32;
+
+                *tile = TileSetting::from_raw((x + y * 100) as u16);
+            }
+        }
+    }
+
+    #[test_case]
+    fn tiles_come_from_the_right_chunk(_gba: &mut crate::Gba) {
+        let map = ChunkedMap::new(CountingSource, MIN_CACHED_CHUNKS);
+
+        for &(x, y) in &[(0, 0), (15, 3), (16, 3), (-1, -1), (40, 17), (-20, 33)] {
+            let (_, tile) = map.tile((x, y).into());
+            assert_eq!(tile.0, (x + y * 100) as u16);
+        }
+    }
+
+    #[test_case]
+    fn chunks_are_only_loaded_once_while_cached(_gba: &mut crate::Gba) {
+        let map = ChunkedMap::new(CountingSource, MIN_CACHED_CHUNKS);
+
+        for y in 0..32 {
+            for x in 0..32 {
+                let _ = map.tile((x, y).into());
+            }
+        }
+
+        assert_eq!(map.chunks_loaded(), 4);
+    }
+
+    #[test_case]
+    fn least_recently_used_chunk_is_evicted(_gba: &mut crate::Gba) {
+        let map = ChunkedMap::new(CountingSource, MIN_CACHED_CHUNKS);
+
+        for chunk in 0..MIN_CACHED_CHUNKS as i32 {
+            let _ = map.tile((chunk * CHUNK_SIZE as i32, 0).into());
+        }
+
+        // touch the first chunk so the second is the least recently used
+        let _ = map.tile((0, 0).into());
+        let _ = map.tile((0, CHUNK_SIZE as i32 * 5).into());
+
+        assert_eq!(map.chunks_loaded(), MIN_CACHED_CHUNKS + 1);
+
+        let _ = map.tile((0, 0).into());
+        assert_eq!(map.chunks_loaded(), MIN_CACHED_CHUNKS + 1);
+
+        let _ = map.tile((CHUNK_SIZE as i32, 0).into());
+        assert_eq!(map.chunks_loaded(), MIN_CACHED_CHUNKS + 2);
+    }
+
+    #[test_case]
+    fn prefetch_respects_the_load_budget(_gba: &mut crate::Gba) {
+        let map = ChunkedMap::new(CountingSource, MIN_CACHED_CHUNKS);
+
+        assert_eq!(
+            map.prefetch((135, 135).into(), 2),
+            PartialUpdateStatus::Continue
+        );
+        assert_eq!(map.chunks_loaded(), 2);
+
+        // The screen touches 4x3 chunks at both of these positions, and most of
+        // the chunks for the second are cached by the first
+        for pos in [Vector2D::new(135, 135), Vector2D::new(263, 135)] {
+            let mut calls = 0;
+            while map.prefetch(pos, 2) == PartialUpdateStatus::Continue {
+                calls += 1;
+                assert!(calls < MIN_CACHED_CHUNKS, "prefetch never finished");
+            }
+
+            let loaded = map.chunks_loaded();
+            for y in pos.y / 8 - 1..=pos.y / 8 + 21 {
+                for x in pos.x / 8 - 1..=pos.x / 8 + 31 {
+                    let _ = map.tile((x, y).into());
+                }
+            }
+            assert_eq!(map.chunks_loaded(), loaded);
+        }
+    }
+}
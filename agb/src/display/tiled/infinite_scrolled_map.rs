@@ -1,8 +1,8 @@
 use alloc::boxed::Box;
 
 use super::{
-    BackgroundID, BackgroundSizePrivate, MapLoan, RegularMap, TileSet, TileSetting, TiledMap,
-    VRamManager,
+    BackgroundID, BackgroundSizePrivate, ChunkSource, ChunkedMap, MapLoan, RegularMap, TileSet,
+    TileSetting, TiledMap, VRamManager,
 };
 
 use crate::{
@@ -109,6 +109,18 @@ impl<'a> InfiniteScrolledMap<'a> {
         }
     }
 
+    /// Creates a new infinite scrolled map wrapping the provided background which gets its tiles from
+    /// a [`ChunkedMap`], only loading chunks as they come into view.
+    ///
+    /// See [`ChunkedMap`] for an example.
+    #[must_use]
+    pub fn new_chunked<'s: 'a, S: ChunkSource<'s> + 'a>(
+        map: MapLoan<'a, RegularMap>,
+        chunks: &'a ChunkedMap<'s, S>,
+    ) -> Self {
+        Self::new(map, Box::new(move |pos| chunks.tile(pos)))
+    }
+
     /// Initialises the map and fills it, calling the between_updates occasionally to allow you to ensure that
     /// music keeps playing without interruption.
     ///
@@ -11,7 +11,7 @@ use crate::memory_mapped::MemoryMapped;
 
 use super::{
     AffineBackgroundSize, BackgroundID, BackgroundSize, BackgroundSizePrivate,
-    RegularBackgroundSize, Tile, TileFormat, TileSet, TileSetting, VRamManager,
+    RegularBackgroundSize, Tile, TileFormat, TileSet, TileSetting, VRamError, VRamManager,
 };
 
 use alloc::{vec, vec::Vec};
@@ -268,16 +268,27 @@ impl RegularMap {
             for x in 0..30 {
                 let tile_id = y * 30 + x;
                 let tile_pos = y * 32 + x;
-                self.set_tile_at_pos(
+                self.try_set_tile_at_pos(
                     vram,
                     tile_pos,
                     &tile_data.tiles,
                     tile_data.tile_settings[tile_id],
-                );
+                )
+                .expect("Ran out of video RAM for tiles");
             }
         }
     }
 
+    /// The format of the tiles this background uses
+    #[must_use]
+    pub fn colours(&self) -> TileFormat {
+        self.colours
+    }
+
+    /// # Panics
+    ///
+    /// Panics if there is no video RAM left for the tile. Use
+    /// [`try_set_tile`](RegularMap::try_set_tile) to handle this.
     pub fn set_tile(
         &mut self,
         vram: &mut VRamManager,
@@ -285,6 +296,19 @@ impl RegularMap {
         tileset: &TileSet<'_>,
         tile_setting: TileSetting,
     ) {
+        self.try_set_tile(vram, pos, tileset, tile_setting)
+            .expect("Ran out of video RAM for tiles");
+    }
+
+    /// Sets the tile at `pos`, or returns an error if there is no video RAM left
+    /// for it. The tile at `pos` is left unchanged if this fails.
+    pub fn try_set_tile(
+        &mut self,
+        vram: &mut VRamManager,
+        pos: impl Into<Vector2D<u16>>,
+        tileset: &TileSet<'_>,
+        tile_setting: TileSetting,
+    ) -> Result<(), VRamError> {
         assert_eq!(
             tileset.format(),
             self.colours(),
@@ -294,37 +318,40 @@ impl RegularMap {
         );
 
         let pos = self.map_size().gba_offset(pos.into());
-        self.set_tile_at_pos(vram, pos, tileset, tile_setting);
+        self.try_set_tile_at_pos(vram, pos, tileset, tile_setting)
     }
 
-    fn set_tile_at_pos(
+    fn try_set_tile_at_pos(
         &mut self,
         vram: &mut VRamManager,
         pos: usize,
         tileset: &TileSet<'_>,
         tile_setting: TileSetting,
-    ) {
-        let old_tile = self.tiles_mut()[pos];
-        if old_tile != Tile::default() {
-            vram.remove_tile(old_tile.tile_index(self.colours()));
-        }
-
+    ) -> Result<(), VRamError> {
         let tile_index = tile_setting.index();
 
+        // Add the new tile before removing the old one, so nothing changes if it fails
         let new_tile = if tile_index != TRANSPARENT_TILE_INDEX {
-            let new_tile_idx = vram.add_tile(tileset, tile_index);
+            let new_tile_idx = vram.try_add_tile(tileset, tile_index)?;
             Tile::new(new_tile_idx, tile_setting)
         } else {
             Tile::default()
         };
 
+        let old_tile = self.tiles_mut()[pos];
+        if old_tile != Tile::default() {
+            vram.remove_tile(old_tile.tile_index(self.colours()));
+        }
+
         if old_tile == new_tile {
             // no need to mark as dirty if nothing changes
-            return;
+            return Ok(());
         }
 
         self.tiles_mut()[pos] = new_tile;
         *self.tiles_dirty() = true;
+
+        Ok(())
     }
 
     /// Returns the latest map priority set  
@@ -427,6 +454,10 @@ impl AffineMap {
         }
     }
 
+    /// # Panics
+    ///
+    /// Panics if there is no video RAM left for the tile. Use
+    /// [`try_set_tile`](AffineMap::try_set_tile) to handle this.
     pub fn set_tile(
         &mut self,
         vram: &mut VRamManager,
@@ -434,28 +465,44 @@ impl AffineMap {
         tileset: &TileSet<'_>,
         tile_index: u16,
     ) {
+        self.try_set_tile(vram, pos, tileset, tile_index)
+            .expect("Ran out of video RAM for tiles");
+    }
+
+    /// Sets the tile at `pos`, or returns an error if there is no video RAM left
+    /// for it. The tile at `pos` is left unchanged if this fails.
+    pub fn try_set_tile(
+        &mut self,
+        vram: &mut VRamManager,
+        pos: impl Into<Vector2D<u16>>,
+        tileset: &TileSet<'_>,
+        tile_index: u16,
+    ) -> Result<(), VRamError> {
         let pos = self.map_size().gba_offset(pos.into());
         let colours = self.colours();
 
-        let old_tile = self.tiles_mut()[pos];
-        if old_tile != Tile::default() {
-            vram.remove_tile(old_tile.tile_index(colours));
-        }
-
+        // Add the new tile before removing the old one, so nothing changes if it fails
         let new_tile = if tile_index != TRANSPARENT_TILE_INDEX {
-            let new_tile_idx = vram.add_tile(tileset, tile_index);
+            let new_tile_idx = vram.try_add_tile(tileset, tile_index)?;
             Tile::new(new_tile_idx, TileSetting(0))
         } else {
             Tile::default()
         };
 
+        let old_tile = self.tiles_mut()[pos];
+        if old_tile != Tile::default() {
+            vram.remove_tile(old_tile.tile_index(colours));
+        }
+
         if old_tile == new_tile {
             // no need to mark as dirty if nothing changes
-            return;
+            return Ok(());
         }
 
         self.tiles_mut()[pos] = new_tile;
         *self.tiles_dirty() = true;
+
+        Ok(())
     }
 
     pub fn set_transform(&mut self, transformation: impl Into<AffineMatrixBackground>) {
@@ -1,5 +1,8 @@
+mod chunked_map;
 mod infinite_scrolled_map;
 mod map;
+mod tile_animation;
+mod tile_canvas;
 mod tiled0;
 mod tiled1;
 mod tiled2;
@@ -8,13 +11,19 @@ mod vram_manager;
 use crate::bitarray::Bitarray;
 use crate::display::Priority;
 use agb_fixnum::Vector2D;
+pub use chunked_map::{ChunkSource, ChunkedMap, CHUNK_SIZE, MIN_CACHED_CHUNKS, TILES_PER_CHUNK};
 use core::cell::RefCell;
 pub use infinite_scrolled_map::{InfiniteScrolledMap, PartialUpdateStatus};
 pub use map::{AffineMap, MapLoan, RegularMap, TiledMap};
+pub use tile_animation::{AnimationFrame, TileAnimation, TileAnimator};
+pub use tile_canvas::TileCanvas;
 pub use tiled0::Tiled0;
 pub use tiled1::Tiled1;
 pub use tiled2::Tiled2;
-pub use vram_manager::{DynamicTile, OwnedTileSet, TileFormat, TileIndex, TileSet, VRamManager};
+pub use vram_manager::{
+    CompressedTileSet, DynamicTile, DynamicTileSet, OwnedTileSet, TileAnimatorId, TileFormat,
+    TileIndex, TileSet, TileSetUsage, VRamError, VRamManager, VRamTileSet,
+};
 
 use map::TRANSPARENT_TILE_INDEX;
 
@@ -197,6 +206,10 @@ impl TileSetting {
         self.0 & ((1 << 10) - 1)
     }
 
+    const fn with_index(self, index: u16) -> Self {
+        Self((self.0 & !((1 << 10) - 1)) | (index & ((1 << 10) - 1)))
+    }
+
     fn setting(self) -> u16 {
         self.0 & !((1 << 10) - 1)
     }
@@ -0,0 +1,245 @@
+use alloc::{vec, vec::Vec};
+
+use super::{TileSet, VRamManager};
+
+/// One frame of a [`TileAnimation`], showing tile `tile` of the tile set for
+/// `duration` frames (each 1/60th of a second).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AnimationFrame {
+    tile: u16,
+    duration: u16,
+}
+
+impl AnimationFrame {
+    /// Creates a frame which shows tile `tile` for `duration` frames.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `duration` is 0, since every frame has to be shown for at
+    /// least one frame. In a `const` or `static` this is a compile error:
+    ///
+    /// ```rust,compile_fail
+    /// # use agb::display::tiled::AnimationFrame;
+    /// static FRAME: AnimationFrame = AnimationFrame::new(0, 0);
+    /// ```
+    #[must_use]
+    pub const fn new(tile: u16, duration: u16) -> Self {
+        assert!(duration > 0, "animation frames must last at least 1 frame");
+        Self { tile, duration }
+    }
+
+    /// The index in the tile set of the tile shown during this frame
+    #[must_use]
+    pub const fn tile(self) -> u16 {
+        self.tile
+    }
+
+    /// How many frames this frame is shown for
+    #[must_use]
+    pub const fn duration(self) -> u16 {
+        self.duration
+    }
+}
+
+/// The frames which the tile `tile` of a tile set cycles through. This is
+/// generated for you by the `animated` option of
+/// [`include_background_gfx!`](crate::include_background_gfx), but can also be
+/// built by hand, for example from the tile animations in a Tiled tile set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileAnimation<'a> {
+    tile: u16,
+    frames: &'a [AnimationFrame],
+}
+
+impl<'a> TileAnimation<'a> {
+    /// # Panics
+    ///
+    /// Panics if `frames` is empty.
+    #[must_use]
+    pub const fn new(tile: u16, frames: &'a [AnimationFrame]) -> Self {
+        assert!(
+            !frames.is_empty(),
+            "A tile animation needs at least one frame"
+        );
+        Self { tile, frames }
+    }
+
+    /// The index in the tile set of the tile which is placed on the map
+    #[must_use]
+    pub const fn tile(&self) -> u16 {
+        self.tile
+    }
+
+    #[must_use]
+    pub const fn frames(&self) -> &'a [AnimationFrame] {
+        self.frames
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct AnimationState {
+    frame: usize,
+    timer: u16,
+}
+
+/// Plays [`TileAnimation`]s by copying the current frame of each animated
+/// tile over the tile in video RAM. Every place the tile is used on any
+/// background animates at once, without needing to change the maps.
+///
+/// Tiles which aren't currently in video RAM are skipped, and show their first
+/// frame when they are placed. Call [`refresh`](TileAnimator::refresh) after
+/// placing new animated tiles to bring them in line with the rest.
+///
+/// Usually the animator is given to the [`VRamManager`] with
+/// [`add_tile_animator`](VRamManager::add_tile_animator), which then plays it
+/// along with every other animator on each call to
+/// [`update_tile_animators`](VRamManager::update_tile_animators).
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// use agb::display::{
+///     tiled::{RegularBackgroundSize, TileFormat, TiledMap},
+///     Priority,
+/// };
+///
+/// agb::include_background_gfx!(water, water_tiles => animated "examples/gfx/water_tiles.aseprite");
+///
+/// # fn foo(mut gba: agb::Gba) {
+/// let (gfx, mut vram) = gba.display.video.tiled0();
+/// let vblank = agb::interrupt::VBlank::get();
+///
+/// vram.set_background_palettes(water::PALETTES);
+/// let mut bg = gfx.background(Priority::P0, RegularBackgroundSize::Background32x32, TileFormat::FourBpp);
+///
+/// bg.set_tile(&mut vram, (0u16, 0u16), &water::water_tiles.tiles, water::water_tiles.tile_settings[0]);
+/// bg.commit(&mut vram);
+/// bg.set_visible(true);
+///
+/// vram.add_tile_animator(water::water_tiles.animator());
+/// loop {
+///     vblank.wait_for_vblank();
+///     vram.update_tile_animators();
+/// }
+/// # }
+/// ```
+pub struct TileAnimator<'a> {
+    tile_set: &'a TileSet<'a>,
+    animations: &'a [TileAnimation<'a>],
+    states: Vec<AnimationState>,
+}
+
+impl<'a> TileAnimator<'a> {
+    /// Creates an animator for tiles of `tile_set`, with every animation on
+    /// its first frame.
+    #[must_use]
+    pub fn new(tile_set: &'a TileSet<'a>, animations: &'a [TileAnimation<'a>]) -> Self {
+        Self {
+            tile_set,
+            animations,
+            states: vec![AnimationState::default(); animations.len()],
+        }
+    }
+
+    /// Advances every animation by one frame, copying the tiles whose frame
+    /// changed into video RAM. Call this once per frame, ideally just after
+    /// waiting for vblank.
+    pub fn update(&mut self, vram: &mut VRamManager) {
+        for (animation, state) in self.animations.iter().zip(self.states.iter_mut()) {
+            state.timer += 1;
+            if state.timer < animation.frames[state.frame].duration {
+                continue;
+            }
+
+            state.timer = 0;
+            state.frame = (state.frame + 1) % animation.frames.len();
+
+            vram.replace_tile(
+                self.tile_set,
+                animation.tile,
+                self.tile_set,
+                animation.frames[state.frame].tile,
+            );
+        }
+    }
+
+    /// Copies the current frame of every animation into video RAM, whether or
+    /// not it has changed.
+    pub fn refresh(&self, vram: &mut VRamManager) {
+        for (animation, state) in self.animations.iter().zip(&self.states) {
+            vram.replace_tile(
+                self.tile_set,
+                animation.tile,
+                self.tile_set,
+                animation.frames[state.frame].tile,
+            );
+        }
+    }
+
+    /// Puts every animation back on its first frame.
+    pub fn reset(&mut self, vram: &mut VRamManager) {
+        self.states.fill(AnimationState::default());
+        self.refresh(vram);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::display::tiled::TileFormat;
+
+    use super::*;
+
+    static TILES: &[u8] = &[0; 32 * 3];
+
+    static SLOW_FRAMES: &[AnimationFrame] = &[
+        AnimationFrame::new(0, 2),
+        AnimationFrame::new(1, 1),
+        AnimationFrame::new(2, 3),
+    ];
+    static FAST_FRAMES: &[AnimationFrame] = &[AnimationFrame::new(1, 1), AnimationFrame::new(2, 1)];
+
+    #[test_case]
+    fn animations_advance_after_their_duration(gba: &mut crate::Gba) {
+        let (_, mut vram) = gba.display.video.tiled0();
+
+        let tile_set = TileSet::new(TILES, TileFormat::FourBpp);
+        let animations = [
+            TileAnimation::new(0, SLOW_FRAMES),
+            TileAnimation::new(1, FAST_FRAMES),
+        ];
+
+        let mut animator = TileAnimator::new(&tile_set, &animations);
+
+        let mut frames = [[0; 2]; 7];
+        for frame in &mut frames {
+            *frame = [animator.states[0].frame, animator.states[1].frame];
+            animator.update(&mut vram);
+        }
+
+        assert_eq!(
+            frames,
+            [[0, 0], [0, 1], [1, 0], [2, 1], [2, 0], [2, 1], [0, 0]]
+        );
+
+        animator.reset(&mut vram);
+        assert_eq!(animator.states[0].frame, 0);
+        assert_eq!(animator.states[1].frame, 0);
+    }
+
+    static TILE_SET: TileSet<'static> = TileSet::new(TILES, TileFormat::FourBpp);
+    static ANIMATIONS: &[TileAnimation<'static>] = &[TileAnimation::new(1, FAST_FRAMES)];
+
+    #[test_case]
+    fn vram_manager_plays_its_animators(gba: &mut crate::Gba) {
+        let (_, mut vram) = gba.display.video.tiled0();
+
+        let id = vram.add_tile_animator(TileAnimator::new(&TILE_SET, ANIMATIONS));
+        let other_id = vram.add_tile_animator(TileAnimator::new(&TILE_SET, ANIMATIONS));
+
+        vram.update_tile_animators();
+        vram.reset_tile_animator(other_id);
+
+        assert_eq!(vram.remove_tile_animator(id).states[0].frame, 1);
+        assert_eq!(vram.remove_tile_animator(other_id).states[0].frame, 0);
+    }
+}
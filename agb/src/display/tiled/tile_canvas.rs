@@ -0,0 +1,358 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    display::{canvas::Canvas, palette16::Palette16},
+    fixnum::Vector2D,
+};
+
+use super::{DynamicTileSet, RegularMap, TileFormat, TileSetting, VRamManager};
+
+/// A rectangle of tiles on a [`RegularMap`] which you can draw onto a pixel at a
+/// time using the primitives in [`Canvas`].
+///
+/// Drawing happens in a copy of the tiles in work RAM, and only the tiles which
+/// have been drawn on since the last call to [`commit`](TileCanvas::commit) are
+/// copied to video RAM.
+///
+/// Colours are indices into the palette given to [`new`](TileCanvas::new) for
+/// 4bpp backgrounds, or into the whole background palette for 8bpp backgrounds.
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// use agb::display::{
+///     canvas::Canvas,
+///     tiled::{RegularBackgroundSize, TileCanvas, TileFormat, TiledMap},
+///     Priority,
+/// };
+///
+/// # fn foo(mut gba: agb::Gba) {
+/// let (gfx, mut vram) = gba.display.video.tiled0();
+/// let mut bg = gfx.background(Priority::P0, RegularBackgroundSize::Background32x32, TileFormat::FourBpp);
+///
+/// let mut canvas = TileCanvas::new(&mut vram, &mut bg, (2u16, 2u16), (8u16, 8u16), 0);
+/// canvas.draw_line((0, 0).into(), (63, 63).into(), 1);
+/// canvas.draw_circle((32, 32).into(), 20, 2);
+/// canvas.commit();
+///
+/// bg.commit(&mut vram);
+/// bg.set_visible(true);
+/// # }
+/// ```
+pub struct TileCanvas {
+    tiles: DynamicTileSet,
+    position: Vector2D<u16>,
+    size: Vector2D<u16>,
+
+    data: Vec<u32>,
+    dirty: Vec<bool>,
+}
+
+impl TileCanvas {
+    /// Creates a canvas `size` tiles large with its top left corner at tile
+    /// `position` of `map`, and places its tiles on the map. The canvas starts
+    /// filled with colour 0.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there isn't enough contiguous video RAM for the tiles.
+    #[must_use]
+    pub fn new(
+        vram: &mut VRamManager,
+        map: &mut RegularMap,
+        position: impl Into<Vector2D<u16>>,
+        size: impl Into<Vector2D<u16>>,
+        palette_id: u8,
+    ) -> Self {
+        let position = position.into();
+        let size = size.into();
+        let format = map.colours();
+
+        let tile_count = size.x as usize * size.y as usize;
+        let tiles = vram.new_dynamic_tile_set(tile_count, format);
+
+        let tile_set = tiles.tile_set();
+        for y in 0..size.y {
+            for x in 0..size.x {
+                map.set_tile(
+                    vram,
+                    position + (x, y).into(),
+                    &tile_set,
+                    tiles.tile_setting(y * size.x + x).palette(palette_id),
+                );
+            }
+        }
+
+        Self {
+            data: vec![0; tile_count * words_per_tile(format)],
+            dirty: vec![true; tile_count],
+
+            tiles,
+            position,
+            size,
+        }
+    }
+
+    /// Copies every tile which has changed since the last commit to video RAM.
+    pub fn commit(&mut self) {
+        let words_per_tile = words_per_tile(self.tiles.format());
+        let mut tile = 0;
+
+        // Upload runs of consecutive dirty tiles in one go
+        while tile < self.dirty.len() {
+            if !self.dirty[tile] {
+                tile += 1;
+                continue;
+            }
+
+            let run_start = tile;
+            while tile < self.dirty.len() && self.dirty[tile] {
+                self.dirty[tile] = false;
+                tile += 1;
+            }
+
+            self.tiles.upload_from(
+                run_start as u16,
+                &self.data[run_start * words_per_tile..tile * words_per_tile],
+            );
+        }
+    }
+
+    /// Whether anything has been drawn since the last [`commit`](TileCanvas::commit)
+    #[must_use]
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.contains(&true)
+    }
+
+    /// The position of the top left corner of the canvas on the map in tiles
+    #[must_use]
+    pub fn position(&self) -> Vector2D<u16> {
+        self.position
+    }
+
+    /// Removes the canvas from the map, and frees its tiles.
+    pub fn remove(self, vram: &mut VRamManager, map: &mut RegularMap) {
+        for y in 0..self.size.y {
+            for x in 0..self.size.x {
+                map.set_tile(
+                    vram,
+                    self.position + (x, y).into(),
+                    &self.tiles.tile_set(),
+                    TileSetting::BLANK,
+                );
+            }
+        }
+
+        vram.remove_dynamic_tile_set(self.tiles);
+    }
+
+    /// The index of the tile containing the pixel, the index into `data` of the
+    /// word containing the pixel, and the shift of the pixel within that word
+    fn locate(&self, pos: Vector2D<i32>) -> (usize, usize, u32) {
+        let format = self.tiles.format();
+        let bits_per_pixel = bits_per_pixel(format);
+
+        let (x, y) = (pos.x as usize, pos.y as usize);
+        let tile = (y / 8) * self.size.x as usize + x / 8;
+        let bit = ((y % 8) * 8 + x % 8) * bits_per_pixel;
+
+        (
+            tile,
+            tile * words_per_tile(format) + bit / 32,
+            (bit % 32) as u32,
+        )
+    }
+}
+
+fn bits_per_pixel(format: TileFormat) -> usize {
+    match format {
+        TileFormat::FourBpp => 4,
+        TileFormat::EightBpp => 8,
+    }
+}
+
+fn words_per_tile(format: TileFormat) -> usize {
+    format.tile_size() / core::mem::size_of::<u32>()
+}
+
+impl Canvas for TileCanvas {
+    type Colour = u8;
+
+    fn size(&self) -> Vector2D<i32> {
+        (self.size.x as i32 * 8, self.size.y as i32 * 8).into()
+    }
+
+    fn write_pixel(&mut self, pos: Vector2D<i32>, colour: u8) {
+        let (tile, word, shift) = self.locate(pos);
+        let mask = (1 << bits_per_pixel(self.tiles.format())) - 1;
+
+        let value = (self.data[word] & !(mask << shift)) | ((u32::from(colour) & mask) << shift);
+        if value != self.data[word] {
+            self.data[word] = value;
+            self.dirty[tile] = true;
+        }
+    }
+
+    fn read_pixel(&self, pos: Vector2D<i32>) -> u8 {
+        let (_, word, shift) = self.locate(pos);
+        let mask = (1 << bits_per_pixel(self.tiles.format())) - 1;
+
+        ((self.data[word] >> shift) & mask) as u8
+    }
+
+    fn colour_from_palette(_palette: &Palette16, index: u8) -> u8 {
+        index
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        display::{tiled::RegularBackgroundSize, Priority},
+        fixnum::Rect,
+    };
+
+    use super::*;
+
+    #[test_case]
+    fn only_commits_tiles_which_were_drawn_on(gba: &mut crate::Gba) {
+        let (gfx, mut vram) = gba.display.video.tiled0();
+        let mut bg = gfx.background(
+            Priority::P0,
+            RegularBackgroundSize::Background32x32,
+            TileFormat::FourBpp,
+        );
+
+        let mut canvas = TileCanvas::new(&mut vram, &mut bg, (0u16, 0u16), (4u16, 2u16), 0);
+        canvas.commit();
+        assert!(!canvas.is_dirty());
+
+        canvas.draw_pixel((9, 1).into(), 5);
+        canvas.draw_pixel((100, 100).into(), 5);
+        assert_eq!(
+            canvas.dirty,
+            [false, true, false, false, false, false, false, false]
+        );
+        assert_eq!(canvas.pixel((9, 1).into()), Some(5));
+        assert_eq!(canvas.pixel((100, 100).into()), None);
+
+        canvas.commit();
+        assert_eq!(canvas.tiles.tile_data(1)[1], 5 << 4);
+
+        canvas.remove(&mut vram, &mut bg);
+    }
+
+    #[test_case]
+    fn draws_shapes_in_8bpp(gba: &mut crate::Gba) {
+        let (gfx, mut vram) = gba.display.video.tiled0();
+        let mut bg = gfx.background(
+            Priority::P0,
+            RegularBackgroundSize::Background32x32,
+            TileFormat::EightBpp,
+        );
+
+        let mut canvas = TileCanvas::new(&mut vram, &mut bg, (1u16, 1u16), (4u16, 4u16), 0);
+
+        canvas.draw_line((0, 0).into(), (31, 31).into(), 200);
+        assert!((0..32).all(|i| canvas.pixel((i, i).into()) == Some(200)));
+        assert_eq!(canvas.pixel((1, 0).into()), Some(0));
+
+        canvas.draw_rect(Rect::new((4, 20).into(), (10, 6).into()), 3);
+        canvas.flood_fill((6, 22).into(), 4);
+        assert_eq!(canvas.pixel((4, 20).into()), Some(3));
+        assert_eq!(canvas.pixel((12, 24).into()), Some(4));
+        assert_eq!(canvas.pixel((15, 24).into()), Some(0));
+
+        canvas.fill_circle((24, 8).into(), 3, 9);
+        assert_eq!(canvas.pixel((24, 5).into()), Some(9));
+        assert_eq!(canvas.pixel((27, 8).into()), Some(9));
+        assert_eq!(canvas.pixel((27, 5).into()), Some(0));
+
+        canvas.remove(&mut vram, &mut bg);
+    }
+
+    #[test_case]
+    fn shapes_off_the_edge_are_clipped(gba: &mut crate::Gba) {
+        let (gfx, mut vram) = gba.display.video.tiled0();
+        let mut bg = gfx.background(
+            Priority::P0,
+            RegularBackgroundSize::Background32x32,
+            TileFormat::FourBpp,
+        );
+
+        let mut canvas = TileCanvas::new(&mut vram, &mut bg, (0u16, 0u16), (2u16, 2u16), 0);
+
+        canvas.draw_rect(Rect::new((-4, -4).into(), (10, 10).into()), 3);
+        canvas.draw_line((i32::MIN, 3).into(), (i32::MAX, 3).into(), 1);
+        canvas.fill_rect(Rect::new((-1_000_000, 10).into(), (i32::MAX, 2).into()), 2);
+        canvas.draw_rect(
+            Rect::new((i32::MIN, 0).into(), (i32::MAX, i32::MAX).into()),
+            4,
+        );
+
+        assert!((0..16).all(|x| canvas.pixel((x, 3).into()) == Some(1)));
+        assert!((0..16).all(|x| canvas.pixel((x, 10).into()) == Some(2)));
+        assert!((0..16).all(|x| canvas.pixel((x, 11).into()) == Some(2)));
+        assert_eq!(canvas.pixel((0, 12).into()), Some(0));
+
+        assert_eq!(canvas.pixel((5, 1).into()), Some(3));
+        assert_eq!(canvas.pixel((0, 5).into()), Some(3));
+        assert_eq!(canvas.pixel((1, 1).into()), Some(0));
+
+        canvas.remove(&mut vram, &mut bg);
+    }
+
+    #[test_case]
+    fn huge_circles_are_clipped(gba: &mut crate::Gba) {
+        let (gfx, mut vram) = gba.display.video.tiled0();
+        let mut bg = gfx.background(
+            Priority::P0,
+            RegularBackgroundSize::Background32x32,
+            TileFormat::FourBpp,
+        );
+
+        let mut canvas = TileCanvas::new(&mut vram, &mut bg, (0u16, 0u16), (2u16, 2u16), 0);
+
+        canvas.draw_circle((i32::MIN, i32::MAX).into(), i32::MAX, 1);
+        canvas.fill_circle((i32::MAX, 0).into(), i32::MAX - 100, 1);
+        assert!(!canvas.is_dirty());
+
+        canvas.fill_circle((8, 8).into(), i32::MAX, 2);
+        assert!((0..16).all(|x| (0..16).all(|y| canvas.pixel((x, y).into()) == Some(2))));
+
+        canvas.fill_circle((-1000, 8).into(), 1005, 3);
+        assert_eq!(canvas.pixel((5, 8).into()), Some(3));
+        assert_eq!(canvas.pixel((6, 8).into()), Some(2));
+
+        canvas.remove(&mut vram, &mut bg);
+    }
+
+    #[test_case]
+    fn huge_rings_only_visit_the_canvas(gba: &mut crate::Gba) {
+        let (gfx, mut vram) = gba.display.video.tiled0();
+        let mut bg = gfx.background(
+            Priority::P0,
+            RegularBackgroundSize::Background32x32,
+            TileFormat::FourBpp,
+        );
+
+        let mut canvas = TileCanvas::new(&mut vram, &mut bg, (0u16, 0u16), (2u16, 2u16), 0);
+
+        // the whole canvas is in the hole in the middle of the ring
+        canvas.draw_circle((8, 8).into(), i32::MAX, 1);
+        // the ring passes nowhere near the canvas
+        canvas.draw_circle((0, -2_000_000_000).into(), 1_000_000_000, 1);
+        assert!(!canvas.is_dirty());
+
+        canvas.draw_circle((8, -1_000_000_000).into(), 1_000_000_008, 2);
+        assert_eq!(canvas.pixel((8, 8).into()), Some(2));
+        assert_eq!(canvas.pixel((8, 7).into()), Some(0));
+        assert_eq!(canvas.pixel((8, 9).into()), Some(0));
+
+        canvas.fill_circle((0, -2_000_000_000).into(), 2_000_000_010, 3);
+        assert!((0..16).all(|x| canvas.pixel((x, 10).into()) == Some(3)));
+        assert!((0..16).all(|x| canvas.pixel((x, 11).into()) != Some(3)));
+
+        canvas.remove(&mut vram, &mut bg);
+    }
+}
@@ -4,13 +4,14 @@ use alloc::{slice, vec::Vec};
 
 use crate::{
     agb_alloc::{block_allocator::BlockAllocator, bump_allocator::StartEnd},
-    display::palette16,
+    display::{palette16, VRamUsage},
     dma,
     hash_map::{Entry, HashMap},
     memory_mapped::MemoryMapped1DArray,
+    syscall,
 };
 
-use super::TileSetting;
+use super::{TileAnimator, TileSetting, TRANSPARENT_TILE_INDEX};
 
 const TILE_RAM_START: usize = 0x0600_0000;
 
@@ -69,6 +70,46 @@ impl<'a> TileSet<'a> {
     }
 }
 
+/// A set of tiles compressed with one of the formats the BIOS understands, as
+/// created by the `compress` option of
+/// [`include_background_gfx!`](crate::include_background_gfx).
+///
+/// Compressed tiles can't be used a tile at a time, so either decompress them
+/// into work RAM with [`decompress`](CompressedTileSet::decompress), or
+/// directly into video RAM with [`VRamManager::load_compressed_tiles`].
+pub struct CompressedTileSet<'a> {
+    data: &'a [u8],
+    format: TileFormat,
+}
+
+impl<'a> CompressedTileSet<'a> {
+    /// Creates a compressed tile set from word aligned compressed data.
+    #[must_use]
+    pub const fn new(data: &'a [u8], format: TileFormat) -> Self {
+        Self { data, format }
+    }
+
+    #[must_use]
+    pub const fn format(&self) -> TileFormat {
+        self.format
+    }
+
+    /// The number of tiles in the tile set once decompressed
+    #[must_use]
+    pub fn tile_count(&self) -> usize {
+        syscall::decompressed_len(self.data) / self.format.tile_size()
+    }
+
+    /// Decompresses the tile set into work RAM
+    #[must_use]
+    pub fn decompress(&self) -> OwnedTileSet {
+        OwnedTileSet {
+            tiles: syscall::decompress(self.data),
+            format: self.format,
+        }
+    }
+}
+
 pub struct OwnedTileSet {
     tiles: Vec<u8>,
     format: TileFormat,
@@ -190,50 +231,246 @@ impl TileReferenceCount {
 #[non_exhaustive]
 pub struct DynamicTile<'a> {
     pub tile_data: &'a mut [u32],
+    format: TileFormat,
 }
 
 impl DynamicTile<'_> {
     #[must_use]
     pub fn fill_with(self, colour_index: u8) -> Self {
-        let colour_index = u32::from(colour_index);
-
-        let mut value = 0;
-        for i in 0..8 {
-            value |= colour_index << (i * 4);
-        }
-
-        self.tile_data.fill(value);
+        self.tile_data.fill(fill_value(colour_index, self.format));
         self
     }
+
+    #[must_use]
+    pub fn format(&self) -> TileFormat {
+        self.format
+    }
 }
 
 impl DynamicTile<'_> {
     #[must_use]
     pub fn tile_set(&self) -> TileSet<'_> {
-        let tiles = unsafe {
-            slice::from_raw_parts_mut(
-                TILE_RAM_START as *mut u8,
-                1024 * TileFormat::FourBpp.tile_size(),
-            )
-        };
-
-        TileSet::new(tiles, TileFormat::FourBpp)
+        vram_tile_set(self.format)
     }
 
     #[must_use]
     pub fn tile_setting(&self) -> TileSetting {
         let difference = self.tile_data.as_ptr() as usize - TILE_RAM_START;
-        let tile_id = (difference / TileFormat::FourBpp.tile_size()) as u16;
+        let tile_id = (difference / self.format.tile_size()) as u16;
 
         TileSetting::new(tile_id, false, false, 0)
     }
 }
 
+/// The word which fills a row of a tile with `colour_index`
+fn fill_value(colour_index: u8, format: TileFormat) -> u32 {
+    match format {
+        TileFormat::FourBpp => u32::from(colour_index & 0xf) * 0x1111_1111,
+        TileFormat::EightBpp => u32::from(colour_index) * 0x0101_0101,
+    }
+}
+
+/// A contiguous block of tiles in video RAM which you can draw into directly,
+/// created by [`VRamManager::new_dynamic_tile_set`].
+///
+/// This is useful for software rendered regions such as minimaps or text
+/// canvases, where you want to upload many tiles at once. Since the tiles are
+/// contiguous, the whole set can be updated with a single DMA transfer using
+/// [`upload`](DynamicTileSet::upload).
+///
+/// The tiles stay in video RAM until passed to [`VRamManager::remove_dynamic_tile_set`].
+pub struct DynamicTileSet {
+    first_tile: u16,
+    tile_count: u16,
+    format: TileFormat,
+}
+
+impl DynamicTileSet {
+    /// The tile set to pass to [`RegularMap::set_tile`](super::RegularMap::set_tile)
+    /// along with settings from [`tile_setting`](DynamicTileSet::tile_setting).
+    #[must_use]
+    pub fn tile_set(&self) -> TileSet<'static> {
+        vram_tile_set(self.format)
+    }
+
+    /// The tile setting for the `tile`th tile in this set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tile` is out of range.
+    #[must_use]
+    pub fn tile_setting(&self, tile: u16) -> TileSetting {
+        assert!(tile < self.tile_count, "Tile index out of range");
+        TileSetting::new(self.first_tile + tile, false, false, 0)
+    }
+
+    #[must_use]
+    pub fn format(&self) -> TileFormat {
+        self.format
+    }
+
+    /// The number of tiles in the tile set
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.tile_count as usize
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.tile_count == 0
+    }
+
+    fn words_per_tile(&self) -> usize {
+        self.format.tile_size() / core::mem::size_of::<u32>()
+    }
+
+    /// The data for every tile in the set, one after another. Video RAM can't
+    /// be written a byte at a time, so the data is accessed as words.
+    #[must_use]
+    pub fn data(&mut self) -> &mut [u32] {
+        let start = TILE_RAM_START + self.first_tile as usize * self.format.tile_size();
+
+        unsafe { slice::from_raw_parts_mut(start as *mut u32, self.len() * self.words_per_tile()) }
+    }
+
+    /// The data for the `tile`th tile in the set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tile` is out of range.
+    #[must_use]
+    pub fn tile_data(&mut self, tile: u16) -> &mut [u32] {
+        assert!(tile < self.tile_count, "Tile index out of range");
+
+        let words_per_tile = self.words_per_tile();
+        let start = tile as usize * words_per_tile;
+        &mut self.data()[start..start + words_per_tile]
+    }
+
+    /// Fills every tile in the set with `colour_index`.
+    pub fn fill_with(&mut self, colour_index: u8) {
+        let value = fill_value(colour_index, self.format);
+        self.data().fill(value);
+    }
+
+    /// Copies `tiles` over the whole set using DMA.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tiles` isn't exactly the size of the tile set.
+    pub fn upload(&mut self, tiles: &[u32]) {
+        self.upload_from(0, tiles);
+    }
+
+    /// Copies `tiles` into the set starting at the `first_tile`th tile using DMA.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tiles` isn't a whole number of tiles, or runs past the end of
+    /// the tile set.
+    pub fn upload_from(&mut self, first_tile: u16, tiles: &[u32]) {
+        let words_per_tile = self.words_per_tile();
+        assert_eq!(
+            tiles.len() % words_per_tile,
+            0,
+            "Must upload a whole number of tiles"
+        );
+
+        let start = first_tile as usize * words_per_tile;
+        let destination = &mut self.data()[start..start + tiles.len()];
+
+        dma::dma3_exclusive(|| unsafe {
+            dma::dma_copy32(tiles.as_ptr(), destination.as_mut_ptr(), tiles.len());
+        });
+    }
+}
+
+/// Tiles which have been decompressed directly into video RAM by
+/// [`VRamManager::load_compressed_tiles`].
+///
+/// These stay in video RAM until passed to [`VRamManager::unload_tiles`].
+pub struct VRamTileSet {
+    first_tile: u16,
+    tile_count: u16,
+    format: TileFormat,
+}
+
+impl VRamTileSet {
+    /// The tile set to pass to [`RegularMap::set_tile`](super::RegularMap::set_tile)
+    /// along with settings from [`tile_setting`](VRamTileSet::tile_setting).
+    #[must_use]
+    pub fn tile_set(&self) -> TileSet<'static> {
+        vram_tile_set(self.format)
+    }
+
+    /// Converts a tile setting for the compressed tile set, such as those in
+    /// [`CompressedTileData::tile_settings`](crate::display::tile_data::CompressedTileData::tile_settings),
+    /// to one which refers to where the tile is in video RAM.
+    #[must_use]
+    pub fn tile_setting(&self, tile_setting: TileSetting) -> TileSetting {
+        let index = tile_setting.index();
+        if index == TRANSPARENT_TILE_INDEX {
+            return tile_setting;
+        }
+
+        assert!(index < self.tile_count, "Tile index out of range");
+        tile_setting.with_index(self.first_tile + index)
+    }
+
+    /// The number of tiles in the tile set
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.tile_count as usize
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.tile_count == 0
+    }
+}
+
+fn vram_tile_set(format: TileFormat) -> TileSet<'static> {
+    let tiles =
+        unsafe { slice::from_raw_parts_mut(TILE_RAM_START as *mut u8, 1024 * format.tile_size()) };
+
+    TileSet::new(tiles, format)
+}
+
+/// The error returned by the `try_` functions on [`VRamManager`] and
+/// [`RegularMap`](super::RegularMap) when there isn't enough video RAM.
+#[non_exhaustive]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VRamError {
+    /// There isn't a large enough contiguous area of background tile memory
+    /// free. See [`VRamManager::usage`] for how much is in use.
+    TilesFull,
+}
+
+/// How many tiles of a tile set are in video RAM, as returned by
+/// [`VRamManager::tile_set_usage`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TileSetUsage {
+    /// The number of different tiles from the tile set which are in video RAM
+    pub tiles: usize,
+    /// The total number of references to those tiles from backgrounds, dynamic
+    /// tiles and tile sets loaded into video RAM
+    pub references: usize,
+}
+
+/// Identifies a [`TileAnimator`] added to the [`VRamManager`] with
+/// [`VRamManager::add_tile_animator`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileAnimatorId(u32);
+
 pub struct VRamManager {
     tile_set_to_vram: HashMap<TileInTileSetReference, TileReference>,
     reference_counts: Vec<TileReferenceCount>,
 
     indices_to_gc: Vec<TileIndex>,
+
+    tile_animators: Vec<(TileAnimatorId, TileAnimator<'static>)>,
+    next_tile_animator_id: u32,
 }
 
 impl VRamManager {
@@ -245,6 +482,8 @@ impl VRamManager {
             tile_set_to_vram,
             reference_counts: Default::default(),
             indices_to_gc: Default::default(),
+            tile_animators: Vec::new(),
+            next_tile_animator_id: 0,
         }
     }
 
@@ -258,45 +497,265 @@ impl VRamManager {
         TileReference(NonNull::new(ptr as *mut _).unwrap())
     }
 
+    /// How much of the video RAM for background tiles is in use.
     #[must_use]
-    pub fn new_dynamic_tile<'a>(&mut self) -> DynamicTile<'a> {
-        // TODO: format param?
-        let tile_format = TileFormat::FourBpp;
-        let new_reference: NonNull<u32> = unsafe { TILE_ALLOCATOR.alloc(layout_of(tile_format)) }
-            .unwrap()
-            .cast();
-        let tile_reference = TileReference(new_reference);
-
-        let index = Self::index_from_reference(tile_reference, tile_format);
-        let key = index.refcount_key();
+    pub fn usage(&self) -> VRamUsage {
+        VRamUsage::new(unsafe { TILE_ALLOCATOR.stats() })
+    }
 
-        let tiles = unsafe {
-            slice::from_raw_parts_mut(TILE_RAM_START as *mut u8, 1024 * tile_format.tile_size())
+    /// How many tiles from `tile_set` are currently in video RAM, and how many
+    /// references there are to them.
+    #[must_use]
+    pub fn tile_set_usage(&self, tile_set: &TileSet<'_>) -> TileSetUsage {
+        let tile_set_reference = tile_set.reference();
+        let mut usage = TileSetUsage {
+            tiles: 0,
+            references: 0,
         };
 
-        let tile_set = TileSet::new(tiles, tile_format);
+        for (tile, &reference) in &self.tile_set_to_vram {
+            if !core::ptr::addr_eq(tile.tileset.as_ptr(), tile_set_reference.as_ptr()) {
+                continue;
+            }
 
-        self.tile_set_to_vram.insert(
-            TileInTileSetReference::new(&tile_set, index.raw_index()),
-            tile_reference,
-        );
+            let key = Self::index_from_reference(reference, tile_set.format).refcount_key();
+            let references = self.reference_counts[key].current_count() as usize;
 
-        self.reference_counts
-            .resize(self.reference_counts.len().max(key + 1), Default::default());
-        self.reference_counts[key] =
-            TileReferenceCount::new(TileInTileSetReference::new(&tile_set, index.raw_index()));
+            if references > 0 {
+                usage.tiles += 1;
+                usage.references += references;
+            }
+        }
 
-        DynamicTile {
+        usage
+    }
+
+    /// The number of references to `tile` of `tile_set`, which is 0 if it isn't
+    /// in video RAM.
+    #[must_use]
+    pub fn tile_reference_count(&self, tile_set: &TileSet<'_>, tile: u16) -> usize {
+        self.tile_set_to_vram
+            .get(&TileInTileSetReference::new(tile_set, tile))
+            .map_or(0, |&reference| {
+                let key = Self::index_from_reference(reference, tile_set.format).refcount_key();
+                self.reference_counts[key].current_count() as usize
+            })
+    }
+
+    /// Allocates a new 4bpp tile in video RAM which you can draw into directly.
+    /// See [`new_dynamic_tile_with_format`](VRamManager::new_dynamic_tile_with_format)
+    /// for 8bpp tiles.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no video RAM left for the tile. Use
+    /// [`try_new_dynamic_tile`](VRamManager::try_new_dynamic_tile) to handle this.
+    #[must_use]
+    pub fn new_dynamic_tile<'a>(&mut self) -> DynamicTile<'a> {
+        self.new_dynamic_tile_with_format(TileFormat::FourBpp)
+    }
+
+    /// Allocates a new 4bpp tile in video RAM, or returns an error if there is
+    /// no video RAM left.
+    pub fn try_new_dynamic_tile<'a>(&mut self) -> Result<DynamicTile<'a>, VRamError> {
+        self.try_new_dynamic_tile_with_format(TileFormat::FourBpp)
+    }
+
+    /// Allocates a new tile of the given format in video RAM which you can draw
+    /// into directly. Free it with [`remove_dynamic_tile`](VRamManager::remove_dynamic_tile).
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no video RAM left for the tile.
+    #[must_use]
+    pub fn new_dynamic_tile_with_format<'a>(&mut self, tile_format: TileFormat) -> DynamicTile<'a> {
+        self.try_new_dynamic_tile_with_format(tile_format)
+            .expect("Ran out of video RAM for tiles")
+    }
+
+    /// Allocates a new tile of the given format in video RAM, or returns an
+    /// error if there is no video RAM left.
+    pub fn try_new_dynamic_tile_with_format<'a>(
+        &mut self,
+        tile_format: TileFormat,
+    ) -> Result<DynamicTile<'a>, VRamError> {
+        let index = self.try_allocate_tiles(1, tile_format)?;
+
+        Ok(DynamicTile {
             tile_data: unsafe {
                 slice::from_raw_parts_mut(
-                    tiles
-                        .as_mut_ptr()
-                        .add(index.raw_index() as usize * tile_format.tile_size())
-                        .cast(),
+                    Self::reference_from_index(index).0.as_ptr(),
                     tile_format.tile_size() / core::mem::size_of::<u32>(),
                 )
             },
+            format: tile_format,
+        })
+    }
+
+    /// Allocates `tile_count` contiguous tiles in video RAM which you can draw into
+    /// directly. Free them with [`remove_dynamic_tile_set`](VRamManager::remove_dynamic_tile_set).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tile_count` is zero, or if there isn't enough contiguous video
+    /// RAM for the tiles.
+    #[must_use]
+    pub fn new_dynamic_tile_set(
+        &mut self,
+        tile_count: usize,
+        tile_format: TileFormat,
+    ) -> DynamicTileSet {
+        self.try_new_dynamic_tile_set(tile_count, tile_format)
+            .expect("Ran out of video RAM for tiles")
+    }
+
+    /// Allocates `tile_count` contiguous tiles in video RAM, or returns an error
+    /// if there isn't a large enough contiguous area free.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tile_count` is zero.
+    pub fn try_new_dynamic_tile_set(
+        &mut self,
+        tile_count: usize,
+        tile_format: TileFormat,
+    ) -> Result<DynamicTileSet, VRamError> {
+        assert!(tile_count > 0, "Must allocate at least one tile");
+        let first_tile = self.try_allocate_tiles(tile_count, tile_format)?;
+
+        Ok(DynamicTileSet {
+            first_tile: first_tile.raw_index(),
+            tile_count: tile_count as u16,
+            format: tile_format,
+        })
+    }
+
+    /// Releases tiles allocated by [`new_dynamic_tile_set`](VRamManager::new_dynamic_tile_set).
+    /// Tiles which are still used by a background will be freed once they are
+    /// no longer in use.
+    // This needs to take ownership of the tile set because it will no longer be valid after this call
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn remove_dynamic_tile_set(&mut self, tiles: DynamicTileSet) {
+        self.remove_tiles(tiles.first_tile, tiles.tile_count, tiles.format);
+    }
+
+    /// Allocates a contiguous block of tiles, registering each one as a tile
+    /// in the tile set covering all of video RAM with a reference count of 1.
+    /// Each tile is reference counted separately, so they can be freed one at
+    /// a time by the usual garbage collection using [`BlockAllocator::dealloc_part`].
+    fn try_allocate_tiles(
+        &mut self,
+        tile_count: usize,
+        tile_format: TileFormat,
+    ) -> Result<TileIndex, VRamError> {
+        let layout = Layout::from_size_align(
+            tile_count * tile_format.tile_size(),
+            tile_format.tile_size(),
+        )
+        .map_err(|_| VRamError::TilesFull)?;
+
+        let block: NonNull<u32> = unsafe { TILE_ALLOCATOR.alloc(layout) }
+            .ok_or(VRamError::TilesFull)?
+            .cast();
+
+        let first_tile = Self::index_from_reference(TileReference(block), tile_format);
+        let tile_set = vram_tile_set(tile_format);
+
+        for tile in 0..tile_count {
+            let index = TileIndex::new(first_tile.raw_index() as usize + tile, tile_format);
+            let key = index.refcount_key();
+            let reference = TileInTileSetReference::new(&tile_set, index.raw_index());
+
+            self.tile_set_to_vram
+                .insert(reference.clone(), Self::reference_from_index(index));
+
+            self.reference_counts
+                .resize(self.reference_counts.len().max(key + 1), Default::default());
+            self.reference_counts[key] = TileReferenceCount::new(reference);
         }
+
+        Ok(first_tile)
+    }
+
+    fn remove_tiles(&mut self, first_tile: u16, tile_count: u16, tile_format: TileFormat) {
+        for tile in 0..tile_count {
+            self.remove_tile(TileIndex::new((first_tile + tile) as usize, tile_format));
+        }
+    }
+
+    /// Decompresses a compressed tile set straight into video RAM, without
+    /// needing to decompress into work RAM first.
+    ///
+    /// ```rust,no_run
+    /// # #![no_std]
+    /// # #![no_main]
+    /// use agb::display::{
+    ///     tiled::{RegularBackgroundSize, TileFormat, TiledMap},
+    ///     Priority,
+    /// };
+    ///
+    /// agb::include_background_gfx!(water_tiles, tiles => compress "examples/water_tiles.png");
+    ///
+    /// # fn foo(mut gba: agb::Gba) {
+    /// let (gfx, mut vram) = gba.display.video.tiled0();
+    /// vram.set_background_palettes(water_tiles::PALETTES);
+    ///
+    /// let tiles = vram.load_compressed_tiles(&water_tiles::tiles.tiles);
+    ///
+    /// let mut bg = gfx.background(Priority::P0, RegularBackgroundSize::Background32x32, TileFormat::FourBpp);
+    /// bg.set_tile(
+    ///     &mut vram,
+    ///     (0, 0),
+    ///     &tiles.tile_set(),
+    ///     tiles.tile_setting(water_tiles::tiles.tile_settings[0]),
+    /// );
+    /// bg.commit(&mut vram);
+    /// bg.set_visible(true);
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if there isn't enough contiguous video RAM for the tiles. Use
+    /// [`try_load_compressed_tiles`](VRamManager::try_load_compressed_tiles) to handle this.
+    pub fn load_compressed_tiles(&mut self, tiles: &CompressedTileSet<'_>) -> VRamTileSet {
+        self.try_load_compressed_tiles(tiles)
+            .expect("Ran out of video RAM for tiles")
+    }
+
+    /// Decompresses a compressed tile set straight into video RAM, or returns an
+    /// error if there isn't a large enough contiguous area free.
+    pub fn try_load_compressed_tiles(
+        &mut self,
+        tiles: &CompressedTileSet<'_>,
+    ) -> Result<VRamTileSet, VRamError> {
+        let tile_format = tiles.format;
+        let tile_count = tiles.tile_count();
+
+        let first_tile = self.try_allocate_tiles(tile_count, tile_format)?;
+
+        let destination = unsafe {
+            slice::from_raw_parts_mut(
+                Self::reference_from_index(first_tile).0.as_ptr(),
+                tile_count * tile_format.tile_size() / core::mem::size_of::<u32>(),
+            )
+        };
+        syscall::decompress_vram(tiles.data, destination);
+
+        Ok(VRamTileSet {
+            first_tile: first_tile.raw_index(),
+            tile_count: tile_count as u16,
+            format: tile_format,
+        })
+    }
+
+    /// Releases tiles loaded by [`load_compressed_tiles`](VRamManager::load_compressed_tiles).
+    /// Tiles which are still used by a background will be freed once they are
+    /// no longer in use.
+    // This needs to take ownership of the tile set because it will no longer be valid after this call
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn unload_tiles(&mut self, tiles: VRamTileSet) {
+        self.remove_tiles(tiles.first_tile, tiles.tile_count, tiles.format);
     }
 
     // This needs to take ownership of the dynamic tile because it will no longer be valid after this call
@@ -305,12 +764,15 @@ impl VRamManager {
         let pointer = NonNull::new(dynamic_tile.tile_data.as_mut_ptr() as *mut _).unwrap();
         let tile_reference = TileReference(pointer);
 
-        // TODO: dynamic_tile.format?
-        let tile_index = Self::index_from_reference(tile_reference, TileFormat::FourBpp);
+        let tile_index = Self::index_from_reference(tile_reference, dynamic_tile.format);
         self.remove_tile(tile_index);
     }
 
-    pub(crate) fn add_tile(&mut self, tile_set: &TileSet<'_>, tile: u16) -> TileIndex {
+    pub(crate) fn try_add_tile(
+        &mut self,
+        tile_set: &TileSet<'_>,
+        tile: u16,
+    ) -> Result<TileIndex, VRamError> {
         let reference = self
             .tile_set_to_vram
             .entry(TileInTileSetReference::new(tile_set, tile));
@@ -319,12 +781,12 @@ impl VRamManager {
             let tile_index = Self::index_from_reference(*reference.get(), tile_set.format);
             let key = tile_index.refcount_key();
             self.reference_counts[key].increment_reference_count();
-            return tile_index;
+            return Ok(tile_index);
         }
 
         let new_reference: NonNull<u32> =
             unsafe { TILE_ALLOCATOR.alloc(layout_of(tile_set.format)) }
-                .expect("Ran out of video RAM for tiles")
+                .ok_or(VRamError::TilesFull)?
                 .cast();
         let tile_reference = TileReference(new_reference);
         reference.or_insert(tile_reference);
@@ -340,7 +802,7 @@ impl VRamManager {
         self.reference_counts[key] =
             TileReferenceCount::new(TileInTileSetReference::new(tile_set, tile));
 
-        index
+        Ok(index)
     }
 
     pub(crate) fn remove_tile(&mut self, tile_index: TileIndex) {
@@ -363,8 +825,12 @@ impl VRamManager {
             }
 
             let tile_reference = Self::reference_from_index(tile_index);
+            // SAFETY: the tile is either a whole allocation from `try_add_tile` or
+            // one tile of a block from `try_allocate_tiles`. Tiles are 32 or 64 bytes,
+            // so they are aligned to and a multiple of 8 bytes, and each tile is only
+            // freed once its reference count drops to 0.
             unsafe {
-                TILE_ALLOCATOR.dealloc(
+                TILE_ALLOCATOR.dealloc_part(
                     tile_reference.0.cast().as_ptr(),
                     layout_of(tile_index.format()),
                 );
@@ -400,6 +866,67 @@ impl VRamManager {
         }
     }
 
+    /// Plays `animator`'s animations whenever [`update_tile_animators`](Self::update_tile_animators)
+    /// is called, until it is removed with [`remove_tile_animator`](Self::remove_tile_animator).
+    pub fn add_tile_animator(&mut self, animator: TileAnimator<'static>) -> TileAnimatorId {
+        let id = TileAnimatorId(self.next_tile_animator_id);
+        self.next_tile_animator_id += 1;
+
+        self.tile_animators.push((id, animator));
+        id
+    }
+
+    /// Stops playing the animations of a tile animator, returning it. The tiles
+    /// are left on whichever frame they were showing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tile animator has been removed already.
+    pub fn remove_tile_animator(&mut self, id: TileAnimatorId) -> TileAnimator<'static> {
+        let index = self.tile_animator_index(id);
+        self.tile_animators.swap_remove(index).1
+    }
+
+    /// Advances every tile animator by one frame, see [`TileAnimator::update`].
+    /// Call this once per frame, ideally just after waiting for vblank.
+    pub fn update_tile_animators(&mut self) {
+        let mut animators = core::mem::take(&mut self.tile_animators);
+        for (_, animator) in &mut animators {
+            animator.update(self);
+        }
+        self.tile_animators = animators;
+    }
+
+    /// Copies the current frame of every tile animator into video RAM, see
+    /// [`TileAnimator::refresh`]. Call this after placing new animated tiles.
+    pub fn refresh_tile_animators(&mut self) {
+        let animators = core::mem::take(&mut self.tile_animators);
+        for (_, animator) in &animators {
+            animator.refresh(self);
+        }
+        self.tile_animators = animators;
+    }
+
+    /// Puts every animation of a tile animator back on its first frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tile animator has been removed.
+    pub fn reset_tile_animator(&mut self, id: TileAnimatorId) {
+        let index = self.tile_animator_index(id);
+
+        let mut animators = core::mem::take(&mut self.tile_animators);
+        animators[index].1.reset(self);
+        self.tile_animators = animators;
+    }
+
+    fn tile_animator_index(&self, id: TileAnimatorId) -> usize {
+        self.tile_animators
+            .iter()
+            .position(|(animator_id, _)| *animator_id == id)
+            .expect("tile animator has been removed")
+    }
+
     fn copy_tile_to_location(
         &self,
         tile_set: &TileSet<'_>,
@@ -509,3 +1036,124 @@ impl VRamManager {
         (0..256).find(|&i| PALETTE_BACKGROUND.get(i) == colour)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::display::{
+        tiled::{RegularBackgroundSize, TiledMap},
+        Priority,
+    };
+
+    use super::*;
+
+    #[test_case]
+    fn dynamic_tiles_in_both_formats(gba: &mut crate::Gba) {
+        let (_, mut vram) = gba.display.video.tiled0();
+
+        for format in [TileFormat::FourBpp, TileFormat::EightBpp] {
+            let tile = vram.new_dynamic_tile_with_format(format).fill_with(3);
+
+            assert_eq!(tile.format(), format);
+            assert_eq!(tile.tile_data.len(), format.tile_size() / 4);
+            assert_eq!(
+                tile.tile_data[0],
+                match format {
+                    TileFormat::FourBpp => 0x3333_3333,
+                    TileFormat::EightBpp => 0x0303_0303,
+                }
+            );
+
+            let offset = tile.tile_data.as_ptr() as usize - TILE_RAM_START;
+            assert_eq!(offset % format.tile_size(), 0);
+            assert_eq!(
+                tile.tile_setting().index() as usize,
+                offset / format.tile_size()
+            );
+
+            vram.remove_dynamic_tile(tile);
+            vram.gc();
+        }
+    }
+
+    #[test_case]
+    fn dynamic_tile_set_uploads_contiguous_tiles(gba: &mut crate::Gba) {
+        let (_, mut vram) = gba.display.video.tiled0();
+
+        let mut tiles = vram.new_dynamic_tile_set(4, TileFormat::EightBpp);
+        let data: Vec<u32> = (0..64).collect();
+
+        tiles.upload(&data);
+        assert_eq!(tiles.data(), &data[..]);
+        assert_eq!(tiles.tile_data(2), &data[32..48]);
+
+        tiles.upload_from(3, &[7; 16]);
+        assert_eq!(tiles.tile_data(3), &[7; 16]);
+
+        assert_eq!(
+            tiles.tile_setting(1).index(),
+            tiles.tile_setting(0).index() + 1
+        );
+
+        vram.remove_dynamic_tile_set(tiles);
+        vram.gc();
+    }
+
+    #[test_case]
+    fn reports_usage_and_fails_gracefully_when_full(gba: &mut crate::Gba) {
+        let (_, mut vram) = gba.display.video.tiled0();
+
+        let before = vram.usage();
+        let tiles = vram.new_dynamic_tile_set(8, TileFormat::FourBpp);
+        let during = vram.usage();
+
+        assert!(during.used >= before.used + 8 * 32);
+        assert_eq!(during.capacity, before.capacity);
+
+        assert_eq!(
+            vram.try_new_dynamic_tile_set(2048, TileFormat::EightBpp)
+                .err(),
+            Some(VRamError::TilesFull)
+        );
+
+        vram.remove_dynamic_tile_set(tiles);
+        vram.gc();
+
+        assert_eq!(vram.usage().used, before.used);
+    }
+
+    #[test_case]
+    fn counts_references_per_tile_set(gba: &mut crate::Gba) {
+        static TILES: [u8; 64] = [1; 64];
+        let tile_set = TileSet::new(&TILES, TileFormat::FourBpp);
+
+        let (gfx, mut vram) = gba.display.video.tiled0();
+        let mut bg = gfx.background(
+            Priority::P0,
+            RegularBackgroundSize::Background32x32,
+            TileFormat::FourBpp,
+        );
+
+        for x in 0..3u16 {
+            bg.set_tile(
+                &mut vram,
+                (x, 0),
+                &tile_set,
+                TileSetting::new(x % 2, false, false, 0),
+            );
+        }
+
+        assert_eq!(
+            vram.tile_set_usage(&tile_set),
+            TileSetUsage {
+                tiles: 2,
+                references: 3
+            }
+        );
+        assert_eq!(vram.tile_reference_count(&tile_set, 0), 2);
+
+        bg.clear(&mut vram);
+        vram.gc();
+
+        assert_eq!(vram.tile_reference_count(&tile_set, 0), 0);
+    }
+}
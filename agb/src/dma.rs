@@ -186,6 +186,15 @@ pub(crate) unsafe fn dma_copy16(src: *const u16, dest: *mut u16, count: usize) {
     DMA3_CONTROL.set(count as u32 | (1 << 31));
 }
 
+pub(crate) unsafe fn dma_copy32(src: *const u32, dest: *mut u32, count: usize) {
+    assert!(count < u16::MAX as usize);
+
+    DMA3_SOURCE_ADDR.set(src as u32);
+    DMA3_DEST_ADDR.set(dest as u32);
+
+    DMA3_CONTROL.set(count as u32 | (1 << 26) | (1 << 31));
+}
+
 pub(crate) fn dma3_exclusive<R>(f: impl FnOnce() -> R) -> R {
     const DMA0_CTRL_HI: MemoryMapped<u16> = unsafe { MemoryMapped::new(dma_control_addr(0) + 2) };
     const DMA1_CTRL_HI: MemoryMapped<u16> = unsafe { MemoryMapped::new(dma_control_addr(1) + 2) };
@@ -0,0 +1,49 @@
+// A compact text encoding for lists of addresses, decoded by `agb-debug`.
+//
+// Addresses in ROM are encoded using 3 characters (the upper 16 bits are assumed
+// to be `0x0800`), and everything else is encoded using 6 characters. The list is
+// terminated with the version marker `v1`.
+
+static ALPHABET: &[u8] = b"0123456789=ABCDEFGHIJKLMNOPQRSTUVWXYZ_abcdefghijklmnopqrstuvwxyz";
+
+pub fn encode_16(input: u16) -> [u8; 3] {
+    let input = input as usize;
+    [
+        ALPHABET[input >> (16 - 5)],
+        ALPHABET[(input >> (16 - 10)) & 0b11111],
+        ALPHABET[input & 0b111111],
+    ]
+}
+
+pub fn encode_32(input: u32) -> [u8; 6] {
+    let input = input as usize;
+    let output_lower_16 = encode_16(input as u16);
+    let input_upper_16 = input >> 16;
+    [
+        ALPHABET[(input_upper_16 >> (16 - 5)) | (1 << 5)],
+        ALPHABET[(input_upper_16 >> (16 - 10)) & 0b11111],
+        ALPHABET[input_upper_16 & 0b111111],
+        output_lower_16[0],
+        output_lower_16[1],
+        output_lower_16[2],
+    ]
+}
+
+pub fn write_frames(f: &mut impl core::fmt::Write, frames: &[u32]) -> core::fmt::Result {
+    for frame in frames {
+        if frame & 0xFFFF_0000 == 0x0800_0000 {
+            let frame = *frame as u16; // intentionally truncate
+            let frame_encoded = encode_16(frame);
+            let frame_str = unsafe { core::str::from_utf8_unchecked(&frame_encoded) };
+
+            f.write_str(frame_str)?;
+        } else {
+            let frame_encoded = encode_32(*frame);
+            let frame_str = unsafe { core::str::from_utf8_unchecked(&frame_encoded) };
+
+            f.write_str(frame_str)?;
+        }
+    }
+
+    f.write_str("v1")
+}
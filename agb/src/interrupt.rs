@@ -67,7 +67,7 @@ impl Interrupt {
 }
 
 const ENABLED_INTERRUPTS: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x04000200) };
-const INTERRUPTS_ENABLED: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x04000208) };
+pub(crate) const INTERRUPTS_ENABLED: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x04000208) };
 
 struct Disable {
     pre: u16,
@@ -140,15 +140,27 @@ static INTERRUPT_TABLE: SyncUnsafeCell<[InterruptRoot; 14]> = SyncUnsafeCell::ne
     InterruptRoot::new(Interrupt::Gamepak),
 ]);
 
+static IN_INTERRUPT: AtomicBool = AtomicBool::new(false);
+
+/// Whether this is being called from within an interrupt handler.
+pub(crate) fn is_in_interrupt() -> bool {
+    IN_INTERRUPT.load(Ordering::Relaxed)
+}
+
 #[no_mangle]
 #[export_name = "__RUST_INTERRUPT_HANDLER"]
 extern "C" fn interrupt_handler(interrupt: u16) -> u16 {
+    // Interrupts are disabled while the handler runs, so they never nest
+    IN_INTERRUPT.store(true, Ordering::Relaxed);
+
     for (i, root) in unsafe { &mut *INTERRUPT_TABLE.get() }.iter().enumerate() {
         if (1 << i) & interrupt != 0 {
             root.trigger_interrupts();
         }
     }
 
+    IN_INTERRUPT.store(false, Ordering::Relaxed);
+
     interrupt
 }
 
@@ -353,6 +365,9 @@ impl VBlank {
 /// With that out of the way, the current version will, in mgba, output the
 /// program counter at regular intervals. This can be used to see hot functions
 /// using, for example, addr2line.
+///
+/// For a profiler which also records call stacks and can be turned into a
+/// report by `agb-debug`, see [`Profiler`](crate::profiler::Profiler).
 pub fn profiler(timer: &mut crate::timer::Timer, period: u16) -> InterruptHandler {
     timer.set_interrupt(true);
     timer.set_overflow_amount(period);
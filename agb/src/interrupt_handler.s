@@ -19,6 +19,10 @@ InterruptHandler:
     ldr r3, =agb_rs__program_counter
     str r1, [r3]
 
+    @ r7 is untouched by the bios, so is still the interrupted code's frame pointer
+    ldr r3, =agb_rs__frame_pointer
+    str r7, [r3]
+
     @ change to system mode
     mrs r1, cpsr
     orr r1, r1, #0xD
@@ -55,3 +59,9 @@ InterruptHandler:
     .balign 4
 agb_rs__program_counter:
     .word 0
+
+.section .iwram.frame_pointer
+    .global agb_rs__frame_pointer
+    .balign 4
+agb_rs__frame_pointer:
+    .word 0
@@ -116,6 +116,42 @@
 /// ##![no_main]
 /// agb::include_background_gfx!(pub water_tiles, tiles => "examples/water_tiles.png");
 /// ```
+///
+/// Adding `compress` before the file name compresses the tiles with whichever of the formats supported by the
+/// BIOS gives the smallest result. The tiles will then be a
+/// [`CompressedTileData`][crate::display::tile_data::CompressedTileData], which you can load straight into
+/// video RAM using [`VRamManager::load_compressed_tiles`][crate::display::tiled::VRamManager::load_compressed_tiles].
+///
+/// ```rust,no_run
+/// ##![no_std]
+/// ##![no_main]
+/// agb::include_background_gfx!(water_tiles, tiles => 256 deduplicate compress "examples/water_tiles.png");
+/// ```
+///
+/// Adding `animated` before the name of an aseprite file includes every frame of the file. The tile settings are for
+/// the first frame, and the tiles which change are listed in an
+/// [`AnimatedTileData`][crate::display::tile_data::AnimatedTileData]. Give its
+/// [`TileAnimator`][crate::display::tiled::TileAnimator] to
+/// [`VRamManager::add_tile_animator`][crate::display::tiled::VRamManager::add_tile_animator] to play the animation
+/// by changing the tiles in video RAM. Animated tiles cannot be compressed, and are never deduplicated using flips.
+///
+/// ```rust,no_run
+/// ##![no_std]
+/// ##![no_main]
+/// agb::include_background_gfx!(water, water_tiles => deduplicate animated "examples/gfx/water_tiles.aseprite");
+/// ```
+///
+/// Adding `affine` makes tiles for an [`AffineMap`][crate::display::tiled::AffineMap]. These are always 256 colours and
+/// are deduplicated without flipping, and the result is an
+/// [`AffineTileData`][crate::display::tile_data::AffineTileData] with the tile index to use for each tile of the image.
+/// Asking for 16 colours, compression or animation along with `affine` is a compile error, as is an image with more than
+/// 256 different tiles since that is all an affine map can refer to.
+///
+/// ```rust,no_run
+/// ##![no_std]
+/// ##![no_main]
+/// agb::include_background_gfx!(affine_tiles, tiles => affine deduplicate "examples/water_tiles.png");
+/// ```
 pub use agb_image_converter::include_background_gfx;
 
 #[doc(hidden)]
@@ -127,6 +163,45 @@ pub use agb_image_converter::include_font as include_font_inner;
 #[doc(hidden)]
 pub use agb_image_converter::include_colours_inner;
 
+#[doc(hidden)]
+pub use agb_image_converter::include_bitmap_inner;
+
+/// Includes a full screen image for use in the bitmap modes, converted at
+/// compile time so it can be copied straight into a page using DMA.
+///
+/// By default this creates a [`BitmapData`](crate::display::tile_data::BitmapData)
+/// of 15-bit colours, which can be drawn in mode 3 with
+/// [`Bitmap3::draw_bitmap`](crate::display::bitmap3::Bitmap3::draw_bitmap) or
+/// mode 5 with [`Bitmap5::draw_bitmap`](crate::display::bitmap5::Bitmap5::draw_bitmap).
+///
+/// Passing `256` before the file name creates a
+/// [`PalettedBitmapData`](crate::display::tile_data::PalettedBitmapData)
+/// for mode 4 instead, along with a palette of the (at most 256) colours the
+/// image uses. Paletted images must have an even width.
+///
+/// ```rust,no_run
+/// ##![no_std]
+/// ##![no_main]
+/// # fn foo(mut gba: agb::Gba) {
+/// static TITLE: agb::display::tile_data::BitmapData =
+///     agb::include_bitmap!("examples/water_tiles.png");
+/// static TITLE_256: agb::display::tile_data::PalettedBitmapData =
+///     agb::include_bitmap!(256 "examples/water_tiles.png");
+///
+/// let mut bitmap = gba.display.video.bitmap5();
+/// bitmap.draw_bitmap(&TITLE);
+/// bitmap.flip_page();
+/// # }
+/// ```
+#[macro_export]
+macro_rules! include_bitmap {
+    ($($args: tt)*) => {{
+        #[allow(unused_imports)]
+        use $crate::display::tile_data::{BitmapData, PalettedBitmapData};
+        $crate::include_bitmap_inner!($($args)*)
+    }};
+}
+
 #[macro_export]
 macro_rules! include_font {
     ($font_path: literal, $font_size: literal) => {{
@@ -163,14 +238,17 @@ mod agbabi;
 #[cfg(feature = "backtrace")]
 mod backtrace;
 mod bitarray;
+pub mod debug_output;
 /// Implements everything relating to things that are displayed on screen.
 pub mod display;
 /// Provides access to the GBA's direct memory access (DMA) which is used for advanced effects
 pub mod dma;
+mod gwilym_encoding;
 /// Button inputs to the system.
 pub mod input;
 /// Interacting with the GBA interrupts
 pub mod interrupt;
+pub mod logger;
 mod memory_mapped;
 /// Implements logging to the mgba emulator.
 pub mod mgba;
@@ -180,6 +258,8 @@ pub use agb_fixnum as fixnum;
 pub use agb_hashmap as hash_map;
 #[cfg(feature = "backtrace")]
 mod panics_render;
+/// A sampling profiler which reports where your game spends its time.
+pub mod profiler;
 /// Simple random number generator
 pub mod rng;
 pub mod save;
@@ -194,6 +274,7 @@ mod sync;
 pub mod syscall;
 /// Interactions with the internal timers
 pub mod timer;
+pub mod timing;
 pub(crate) mod util;
 
 mod no_game;
@@ -218,9 +299,7 @@ pub use {agb_alloc::ExternalAllocator, agb_alloc::InternalAllocator};
 fn panic_implementation(info: &core::panic::PanicInfo) -> ! {
     avoid_double_panic(info);
 
-    if let Some(mut mgba) = mgba::Mgba::new() {
-        let _ = mgba.print(format_args!("{info}"), mgba::DebugLevel::Fatal);
-    }
+    let _ = debug_output::print(format_args!("{info}"), mgba::DebugLevel::Fatal);
 
     #[allow(clippy::empty_loop)]
     loop {}
@@ -232,12 +311,10 @@ fn avoid_double_panic(info: &core::panic::PanicInfo) {
     static IS_PANICKING: portable_atomic::AtomicBool = portable_atomic::AtomicBool::new(false);
 
     if IS_PANICKING.load(portable_atomic::Ordering::SeqCst) {
-        if let Some(mut mgba) = mgba::Mgba::new() {
-            let _ = mgba.print(
-                format_args!("Double panic: {info}"),
-                mgba::DebugLevel::Fatal,
-            );
-        }
+        let _ = debug_output::print(
+            format_args!("Double panic: {info}"),
+            mgba::DebugLevel::Fatal,
+        );
         loop {
             syscall::halt();
         }
@@ -345,8 +422,7 @@ pub mod test_runner {
         T: Fn(&mut Gba),
     {
         fn run(&self, gba: &mut Gba) {
-            let mut mgba = mgba::Mgba::new().unwrap();
-            mgba.print(
+            debug_output::print(
                 format_args!("{}...", core::any::type_name::<T>()),
                 mgba::DebugLevel::Info,
             )
@@ -355,8 +431,7 @@ pub mod test_runner {
             self(gba);
             mgba::test_runner_measure_cycles();
 
-            mgba.print(format_args!("[ok]"), mgba::DebugLevel::Info)
-                .unwrap();
+            debug_output::print(format_args!("[ok]"), mgba::DebugLevel::Info).unwrap();
         }
     }
 
@@ -367,9 +442,7 @@ pub mod test_runner {
         #[cfg(feature = "backtrace")]
         let frames = backtrace::unwind_exception();
 
-        if let Some(mut mgba) = mgba::Mgba::new() {
-            let _ = mgba.print(format_args!("[failed]"), mgba::DebugLevel::Error);
-        }
+        let _ = debug_output::print(format_args!("[failed]"), mgba::DebugLevel::Error);
 
         #[cfg(feature = "backtrace")]
         crate::panics_render::render_backtrace(&frames, info);
@@ -384,8 +457,13 @@ pub mod test_runner {
 
     #[doc(hidden)]
     pub fn test_runner(tests: &[&dyn Testable]) {
-        let mut mgba = mgba::Mgba::new().unwrap();
-        mgba.print(
+        // without any debug output, every test would silently appear to pass
+        assert!(
+            debug_output::backend() != debug_output::Backend::Null,
+            "Tests must be run in an emulator with debug output, such as mGBA or No$GBA"
+        );
+
+        debug_output::print(
             format_args!("Running {} tests", tests.len()),
             mgba::DebugLevel::Info,
         )
@@ -397,7 +475,7 @@ pub mod test_runner {
             test.run(gba);
         }
 
-        mgba.print(
+        debug_output::print(
             format_args!("Tests finished successfully"),
             mgba::DebugLevel::Info,
         )
@@ -445,6 +523,14 @@ pub(crate) fn program_counter_before_interrupt() -> u32 {
     unsafe { agb_rs__program_counter }
 }
 
+#[inline(never)]
+pub(crate) fn frame_pointer_before_interrupt() -> u32 {
+    extern "C" {
+        static mut agb_rs__frame_pointer: u32;
+    }
+    unsafe { agb_rs__frame_pointer }
+}
+
 #[cfg(test)]
 mod test {
     use core::ptr::addr_of_mut;
@@ -0,0 +1,574 @@
+//! Buffered logging which is safe to use from interrupt handlers.
+//!
+//! Log records are written into fixed size lock-free ring buffers rather than
+//! being sent straight to the emulator. This means logging is cheap, works from
+//! within interrupt handlers without disabling interrupts and works on real
+//! hardware. When the buffer is full, new records are dropped until there is
+//! room again, and [`take_dropped_count`] says how many were lost.
+//!
+//! The buffered records can then be:
+//! * written to the emulator's debug output with [`flush`],
+//! * sent over the link cable using [`flush_to_serial`], or
+//! * read back in game using [`drain`], for example to show in a debug overlay.
+//!
+//! Messages longer than [`MAX_MESSAGE_LENGTH`] bytes are truncated.
+//!
+//! With the `log` feature enabled, `Logger` provides a backend for the
+//! [`log`](https://docs.rs/log) crate with per-module level filtering.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() {
+//! use agb::{logger, mgba::DebugLevel};
+//!
+//! logger::write(DebugLevel::Info, format_args!("Hello from the game"));
+//!
+//! // once per frame, outside of any interrupt handler
+//! logger::flush();
+//! # }
+//! ```
+
+use core::{cell::UnsafeCell, fmt::Write};
+
+use portable_atomic::{AtomicU16, AtomicUsize, Ordering};
+
+use crate::{
+    debug_output::{self, Backend},
+    interrupt,
+    mgba::DebugLevel,
+};
+
+/// The number of bytes of log records which can be buffered at once from
+/// outside of interrupt handlers.
+pub const BUFFER_SIZE: usize = 2048;
+
+/// The number of bytes of log records which can be buffered at once from
+/// interrupt handlers.
+pub const INTERRUPT_BUFFER_SIZE: usize = 512;
+
+/// The longest message which can be stored. Longer messages get truncated.
+pub const MAX_MESSAGE_LENGTH: usize = 200;
+
+// Each record is stored as the level, the length of the message and a 2 byte
+// sequence number, followed by the message itself.
+const RECORD_HEADER_SIZE: usize = 4;
+
+// Main code and interrupt handlers each get their own ring, so that every ring
+// only ever has one writer. Interrupts don't nest, so an interrupt handler runs
+// to completion before the main code can carry on with whatever it was writing.
+static LOG_RING: LogRing<BUFFER_SIZE> = LogRing::new();
+static INTERRUPT_LOG_RING: LogRing<INTERRUPT_BUFFER_SIZE> = LogRing::new();
+
+// Used to put the records from both rings back in order. Incrementing this
+// isn't atomic, so an interrupt can cause two records to share a number, but
+// those two were written at the same time anyway.
+static NEXT_SEQUENCE: AtomicU16 = AtomicU16::new(0);
+
+/// A lock-free ring buffer with a single writer and a single reader.
+///
+/// `write` and `read` count the total bytes written and read, so the number of
+/// bytes in the ring is the difference between them. Only the writer changes
+/// `write` and `dropped`, and only the reader changes `read` and `reported`.
+struct LogRing<const N: usize> {
+    data: UnsafeCell<[u8; N]>,
+    write: AtomicUsize,
+    read: AtomicUsize,
+    dropped: AtomicUsize,
+    reported: AtomicUsize,
+}
+
+// SAFETY: the writer only touches bytes which aren't in use, and the reader only
+// touches bytes which are. Bytes change hands through `write` and `read`.
+unsafe impl<const N: usize> Sync for LogRing<N> {}
+
+struct RecordHeader {
+    level: DebugLevel,
+    length: usize,
+    sequence: u16,
+}
+
+impl<const N: usize> LogRing<N> {
+    const fn new() -> Self {
+        // so `write` and `read` can wrap around
+        assert!(N.is_power_of_two());
+
+        Self {
+            data: UnsafeCell::new([0; N]),
+            write: AtomicUsize::new(0),
+            read: AtomicUsize::new(0),
+            dropped: AtomicUsize::new(0),
+            reported: AtomicUsize::new(0),
+        }
+    }
+
+    fn byte_at(&self, position: usize) -> u8 {
+        unsafe {
+            self.data
+                .get()
+                .cast::<u8>()
+                .add(position % N)
+                .read_volatile()
+        }
+    }
+
+    fn set_byte_at(&self, position: usize, byte: u8) {
+        unsafe {
+            self.data
+                .get()
+                .cast::<u8>()
+                .add(position % N)
+                .write_volatile(byte);
+        }
+    }
+
+    /// Adds a record, or drops it if there isn't room. Must only be called by
+    /// the writer.
+    fn push(&self, level: DebugLevel, sequence: u16, message: &[u8]) {
+        let write = self.write.load(Ordering::Relaxed);
+        let read = self.read.load(Ordering::Acquire);
+
+        let needed = message.len() + RECORD_HEADER_SIZE;
+        if N - write.wrapping_sub(read) < needed {
+            let dropped = self.dropped.load(Ordering::Relaxed);
+            self.dropped
+                .store(dropped.wrapping_add(1), Ordering::Relaxed);
+            return;
+        }
+
+        let [sequence_low, sequence_high] = sequence.to_le_bytes();
+        let header = [
+            level as u8,
+            message.len() as u8,
+            sequence_low,
+            sequence_high,
+        ];
+
+        for (offset, &byte) in header.iter().chain(message).enumerate() {
+            self.set_byte_at(write.wrapping_add(offset), byte);
+        }
+
+        self.write
+            .store(write.wrapping_add(needed), Ordering::Release);
+    }
+
+    /// The header of the oldest record, if there is one. Must only be called by
+    /// the reader.
+    fn peek(&self) -> Option<RecordHeader> {
+        let read = self.read.load(Ordering::Relaxed);
+        if self.write.load(Ordering::Acquire) == read {
+            return None;
+        }
+
+        Some(RecordHeader {
+            level: level_from_u8(self.byte_at(read)),
+            length: usize::from(self.byte_at(read.wrapping_add(1))),
+            sequence: u16::from_le_bytes([
+                self.byte_at(read.wrapping_add(2)),
+                self.byte_at(read.wrapping_add(3)),
+            ]),
+        })
+    }
+
+    /// Removes the oldest record, copying its message into `message`. Must
+    /// only be called by the reader.
+    fn pop(&self, message: &mut [u8; MAX_MESSAGE_LENGTH]) -> Option<RecordHeader> {
+        let header = self.peek()?;
+        let read = self.read.load(Ordering::Relaxed);
+
+        for (offset, byte) in message[..header.length].iter_mut().enumerate() {
+            *byte = self.byte_at(read.wrapping_add(RECORD_HEADER_SIZE + offset));
+        }
+
+        self.read.store(
+            read.wrapping_add(RECORD_HEADER_SIZE + header.length),
+            Ordering::Release,
+        );
+
+        Some(header)
+    }
+
+    /// The number of records dropped since this was last called. Must only be
+    /// called by the reader.
+    fn take_dropped(&self) -> usize {
+        let dropped = self.dropped.load(Ordering::Relaxed);
+        let reported = self.reported.load(Ordering::Relaxed);
+        self.reported.store(dropped, Ordering::Relaxed);
+
+        dropped.wrapping_sub(reported)
+    }
+}
+
+fn level_from_u8(level: u8) -> DebugLevel {
+    match level {
+        0 => DebugLevel::Fatal,
+        1 => DebugLevel::Error,
+        2 => DebugLevel::Warning,
+        3 => DebugLevel::Info,
+        _ => DebugLevel::Debug,
+    }
+}
+
+/// Formats into a fixed size buffer, silently truncating anything which doesn't fit.
+struct MessageBuffer {
+    data: [u8; MAX_MESSAGE_LENGTH],
+    length: usize,
+}
+
+impl Write for MessageBuffer {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let remaining = MAX_MESSAGE_LENGTH - self.length;
+        let to_copy = s.len().min(remaining);
+
+        self.data[self.length..self.length + to_copy].copy_from_slice(&s.as_bytes()[..to_copy]);
+        self.length += to_copy;
+
+        Ok(())
+    }
+}
+
+/// Adds a record to the log buffer. Safe to call from interrupt handlers.
+///
+/// This never disables interrupts. Records written from interrupt handlers go
+/// into a separate buffer, and the two are put back in order when they are read.
+pub fn write(level: DebugLevel, message: core::fmt::Arguments) {
+    let mut buffer = MessageBuffer {
+        data: [0; MAX_MESSAGE_LENGTH],
+        length: 0,
+    };
+    let _ = buffer.write_fmt(message);
+
+    // Don't split a multi-byte character when truncating
+    let length = match core::str::from_utf8(&buffer.data[..buffer.length]) {
+        Ok(s) => s.len(),
+        Err(e) => e.valid_up_to(),
+    };
+
+    let sequence = NEXT_SEQUENCE.load(Ordering::Relaxed);
+    NEXT_SEQUENCE.store(sequence.wrapping_add(1), Ordering::Relaxed);
+
+    if interrupt::is_in_interrupt() {
+        INTERRUPT_LOG_RING.push(level, sequence, &buffer.data[..length]);
+    } else {
+        LOG_RING.push(level, sequence, &buffer.data[..length]);
+    }
+}
+
+/// Removes every buffered record in the order they were written, and passes
+/// each of them to `f`.
+///
+/// Records written while `f` is running (for example by interrupt handlers)
+/// will also be passed to `f`. This does nothing if called from an interrupt
+/// handler, since the records can only be read from one place at a time.
+pub fn drain(mut f: impl FnMut(DebugLevel, &str)) {
+    if interrupt::is_in_interrupt() {
+        return;
+    }
+
+    let mut message = [0; MAX_MESSAGE_LENGTH];
+
+    loop {
+        let header = match (LOG_RING.peek(), INTERRUPT_LOG_RING.peek()) {
+            (None, None) => return,
+            (Some(_), None) => LOG_RING.pop(&mut message),
+            (None, Some(_)) => INTERRUPT_LOG_RING.pop(&mut message),
+            (Some(main), Some(from_interrupt)) => {
+                // the sequence numbers wrap, so compare them by their difference
+                if (from_interrupt.sequence.wrapping_sub(main.sequence) as i16) < 0 {
+                    INTERRUPT_LOG_RING.pop(&mut message)
+                } else {
+                    LOG_RING.pop(&mut message)
+                }
+            }
+        };
+
+        let Some(header) = header else {
+            return;
+        };
+
+        // SAFETY: only valid utf-8 is ever written to the rings
+        let message = unsafe { core::str::from_utf8_unchecked(&message[..header.length]) };
+        f(header.level, message);
+    }
+}
+
+/// Returns the number of records which have been discarded because the buffer
+/// was full, and resets the count. Always returns 0 when called from an
+/// interrupt handler.
+pub fn take_dropped_count() -> usize {
+    if interrupt::is_in_interrupt() {
+        return 0;
+    }
+
+    LOG_RING.take_dropped() + INTERRUPT_LOG_RING.take_dropped()
+}
+
+/// Writes all the buffered records to the emulator's [debug output](crate::debug_output).
+/// Does nothing and returns `false` if there is no debug output available,
+/// leaving the records in the buffer.
+///
+/// This should not be called from an interrupt handler, since it would interfere
+/// with anything the main code is writing to the debug output.
+// most callers don't care whether there was anywhere to write the records to
+#[allow(clippy::must_use_candidate)]
+pub fn flush() -> bool {
+    if debug_output::backend() == Backend::Null {
+        return false;
+    }
+
+    let dropped = take_dropped_count();
+    if dropped > 0 {
+        let _ = debug_output::print(
+            format_args!("{dropped} log records were dropped"),
+            DebugLevel::Warning,
+        );
+    }
+
+    drain(|level, message| {
+        let _ = debug_output::print(format_args!("{message}"), level);
+    });
+
+    true
+}
+
+/// Writes all the buffered records to the given serial port (for example
+/// [`LinkPortUart`](crate::serial_link::LinkPortUart)), one record per line.
+/// Blocks until all the records have been sent.
+pub fn flush_to_serial<S>(serial: &mut S) -> Result<(), S::Error>
+where
+    S: embedded_hal_nb::serial::Write<u8>,
+{
+    let mut result = Ok(());
+
+    drain(|level, message| {
+        if result.is_err() {
+            return;
+        }
+
+        let level_name = level_name(level).as_bytes();
+        result = level_name
+            .iter()
+            .chain(b" ")
+            .chain(message.as_bytes())
+            .chain(b"\r\n")
+            .try_for_each(|&byte| nb::block!(serial.write(byte)));
+    });
+
+    result?;
+    nb::block!(serial.flush())
+}
+
+fn level_name(level: DebugLevel) -> &'static str {
+    match level {
+        DebugLevel::Fatal => "[FATAL]",
+        DebugLevel::Error => "[ERROR]",
+        DebugLevel::Warning => "[WARN]",
+        DebugLevel::Info => "[INFO]",
+        DebugLevel::Debug => "[DEBUG]",
+    }
+}
+
+#[cfg(feature = "log")]
+pub use log_backend::Logger;
+
+#[cfg(feature = "log")]
+mod log_backend {
+    use alloc::{boxed::Box, vec::Vec};
+    use log::{LevelFilter, Metadata, Record};
+
+    use crate::mgba::DebugLevel;
+
+    /// A backend for the [`log`](https://docs.rs/log) crate which writes to
+    /// agb's log buffer. See the [module level documentation](super) for how
+    /// to read the records back out.
+    ///
+    /// ```rust,no_run
+    /// # #![no_std]
+    /// # #![no_main]
+    /// # fn foo() {
+    /// use agb::logger::Logger;
+    /// use log::LevelFilter;
+    ///
+    /// Logger::new(LevelFilter::Info)
+    ///     .with_module_level("my_game::physics", LevelFilter::Trace)
+    ///     .init();
+    ///
+    /// log::info!("Hello from the log crate");
+    /// # }
+    /// ```
+    pub struct Logger {
+        default_level: LevelFilter,
+        module_levels: Vec<(&'static str, LevelFilter)>,
+    }
+
+    impl Logger {
+        /// Creates a logger which logs everything at `default_level` or more
+        /// severe.
+        #[must_use]
+        pub fn new(default_level: LevelFilter) -> Self {
+            Self {
+                default_level,
+                module_levels: Vec::new(),
+            }
+        }
+
+        /// Overrides the level for a module and all of its submodules. If more
+        /// than one override matches, the most specific module wins.
+        #[must_use]
+        pub fn with_module_level(mut self, module: &'static str, level: LevelFilter) -> Self {
+            self.module_levels.push((module, level));
+            self
+        }
+
+        fn level_for(&self, target: &str) -> LevelFilter {
+            self.module_levels
+                .iter()
+                .filter(|(module, _)| {
+                    target
+                        .strip_prefix(module)
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+                })
+                .max_by_key(|(module, _)| module.len())
+                .map_or(self.default_level, |&(_, level)| level)
+        }
+
+        /// Installs this as the global logger. Does nothing if a logger has
+        /// already been installed.
+        pub fn init(self) {
+            let max_level = self
+                .module_levels
+                .iter()
+                .map(|&(_, level)| level)
+                .fold(self.default_level, core::cmp::max);
+
+            let logger: &'static Logger = Box::leak(Box::new(self));
+
+            critical_section::with(|_| {
+                // SAFETY: interrupts are disabled so nothing else can be calling these
+                if unsafe { log::set_logger_racy(logger) }.is_ok() {
+                    unsafe { log::set_max_level_racy(max_level) };
+                }
+            });
+        }
+    }
+
+    impl log::Log for Logger {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.level() <= self.level_for(metadata.target())
+        }
+
+        fn log(&self, record: &Record) {
+            if !self.enabled(record.metadata()) {
+                return;
+            }
+
+            let level = match record.level() {
+                log::Level::Error => DebugLevel::Error,
+                log::Level::Warn => DebugLevel::Warning,
+                log::Level::Info => DebugLevel::Info,
+                log::Level::Debug | log::Level::Trace => DebugLevel::Debug,
+            };
+
+            super::write(
+                level,
+                format_args!("{}: {}", record.target(), record.args()),
+            );
+        }
+
+        fn flush(&self) {
+            super::flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::{string::String, vec::Vec};
+
+    use super::*;
+
+    #[test_case]
+    fn records_come_out_in_order(_gba: &mut crate::Gba) {
+        drain(|_, _| {});
+
+        write(DebugLevel::Info, format_args!("first {}", 1));
+        write(DebugLevel::Error, format_args!("second"));
+
+        let mut records = Vec::new();
+        drain(|level, message| records.push((level, String::from(message))));
+
+        assert_eq!(
+            records,
+            [
+                (DebugLevel::Info, String::from("first 1")),
+                (DebugLevel::Error, String::from("second"))
+            ]
+        );
+    }
+
+    #[test_case]
+    fn full_buffer_drops_new_records(_gba: &mut crate::Gba) {
+        let ring = LogRing::<BUFFER_SIZE>::new();
+        let message = [b'a'; 100];
+
+        for sequence in 0..30 {
+            ring.push(DebugLevel::Info, sequence, &message);
+        }
+
+        let mut count = 0;
+        let mut buffer = [0; MAX_MESSAGE_LENGTH];
+        while let Some(header) = ring.pop(&mut buffer) {
+            assert_eq!(&buffer[..header.length], &message);
+            assert_eq!(header.sequence, count as u16);
+            count += 1;
+        }
+
+        assert_eq!(count, BUFFER_SIZE / (message.len() + RECORD_HEADER_SIZE));
+        assert_eq!(ring.take_dropped(), 30 - count);
+        assert_eq!(ring.take_dropped(), 0);
+
+        ring.push(DebugLevel::Info, 30, &message);
+        assert_eq!(
+            ring.pop(&mut buffer).map(|header| header.sequence),
+            Some(30)
+        );
+    }
+
+    #[test_case]
+    fn records_from_interrupts_are_put_back_in_order(_gba: &mut crate::Gba) {
+        drain(|_, _| {});
+
+        write(DebugLevel::Info, format_args!("first"));
+        INTERRUPT_LOG_RING.push(
+            DebugLevel::Info,
+            NEXT_SEQUENCE.load(Ordering::Relaxed),
+            b"second",
+        );
+        NEXT_SEQUENCE.store(NEXT_SEQUENCE.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
+        write(DebugLevel::Info, format_args!("third"));
+
+        let mut records = Vec::new();
+        drain(|_, message| records.push(String::from(message)));
+
+        assert_eq!(records, ["first", "second", "third"]);
+    }
+
+    #[test_case]
+    fn long_messages_are_truncated(_gba: &mut crate::Gba) {
+        drain(|_, _| {});
+
+        write(DebugLevel::Info, format_args!("{:é<300}", ""));
+
+        let mut records = Vec::new();
+        drain(|_, message| records.push(String::from(message)));
+
+        assert_eq!(records.len(), 1);
+
+        // truncated to a character boundary, and 'é' is 2 bytes long
+        let expected_length = MAX_MESSAGE_LENGTH / 'é'.len_utf8() * 'é'.len_utf8();
+        assert_eq!(records[0].len(), expected_length);
+        assert!(records[0].chars().all(|c| c == 'é'));
+    }
+}
@@ -1,7 +1,7 @@
 use crate::memory_mapped::{MemoryMapped, MemoryMapped1DArray};
 use core::fmt::Write;
 
-#[derive(Eq, PartialEq, Clone, Copy)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
 #[allow(dead_code)]
 pub enum DebugLevel {
     Fatal = 0,
@@ -80,13 +80,13 @@ impl core::fmt::Write for MgbaWriter {
     }
 }
 
+/// Prints a line to the emulator's debug output, see [`debug_output`](crate::debug_output)
+/// for which emulators are supported.
 #[macro_export]
 macro_rules! println {
     ($( $x:expr ),*) => {
         {
-            if let Some(mut mgba) = $crate::mgba::Mgba::new() {
-                let _ = mgba.print(format_args!($($x,)*), $crate::mgba::DebugLevel::Info);
-            }
+            let _ = $crate::debug_output::print(format_args!($($x,)*), $crate::mgba::DebugLevel::Info);
         }
     };
 }
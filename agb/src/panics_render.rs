@@ -4,7 +4,7 @@ use alloc::{collections::TryReserveError, format, vec::Vec};
 use qrcodegen_no_heap::DataTooLong;
 
 use crate::{
-    backtrace,
+    backtrace, debug_output,
     display::{bitmap3::Bitmap3, busy_wait_for_vblank, HEIGHT, WIDTH},
     dma::dma3_exclusive,
     mgba, syscall, ExternalAllocator,
@@ -59,9 +59,7 @@ pub fn render_backtrace(trace: &backtrace::Frames, info: &PanicInfo) -> ! {
             busy_wait_for_vblank();
             busy_wait_for_vblank();
 
-            if let Some(mut mgba) = mgba::Mgba::new() {
-                let _ = mgba.print(format_args!("Error: {info}"), mgba::DebugLevel::Fatal);
-            }
+            let _ = debug_output::print(format_args!("Error: {info}"), mgba::DebugLevel::Fatal);
 
             loop {
                 syscall::halt();
@@ -0,0 +1,381 @@
+//! A sampling profiler which records where your game is spending its time.
+//!
+//! The profiler uses a timer interrupt to periodically record the program
+//! counter of the code which was interrupted along with the return addresses
+//! of the functions which called it. After a given number of frames, the
+//! samples are collected into a histogram and written to the emulator's
+//! [debug output](crate::debug_output) in a compact encoding.
+//!
+//! To turn the output into a readable profile, save the debug log and pass it
+//! along with the elf file of your game to `agb-debug`:
+//!
+//! ```sh
+//! agb-debug profile path/to/game.elf path/to/mgba.log
+//! ```
+//!
+//! This will print a flat profile (where the samples landed) and an inclusive
+//! profile (which functions were on the call stack when the sample was taken).
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(mut gba: agb::Gba) {
+//! use agb::profiler::Profiler;
+//!
+//! let mut timers = gba.timers.timers();
+//! let vblank = agb::interrupt::VBlank::get();
+//!
+//! // Profile the next 600 frames (10 seconds)
+//! let mut profiler = Profiler::new(&mut timers.timer2, 0x800, 600);
+//!
+//! loop {
+//!     // game logic goes here
+//!
+//!     profiler.frame();
+//!     vblank.wait_for_vblank();
+//! }
+//! # }
+//! ```
+
+use core::cell::RefCell;
+
+use alloc::vec::Vec;
+use critical_section::{CriticalSection, Mutex};
+
+use crate::{
+    debug_output::{self, Backend},
+    gwilym_encoding,
+    hash_map::HashMap,
+    interrupt::{add_interrupt_handler, InterruptHandler},
+    mgba::DebugLevel,
+    timer::Timer,
+    ExternalAllocator,
+};
+
+/// The maximum number of frames recorded for each sample, including the
+/// sampled program counter.
+pub const MAX_STACK_DEPTH: usize = 12;
+
+/// The default size (in words) of the buffer used to store samples. This is
+/// enough for at least 150 samples at the maximum stack depth. Use
+/// [`Profiler::new_with_buffer_size`] if you need to record more samples.
+pub const DEFAULT_BUFFER_WORDS: usize = 2 * 1024;
+
+/// The prefix of every line the profiler writes to the debug output.
+const LOG_PREFIX: &str = "agb-profile:";
+
+static PROFILE_STATE: Mutex<RefCell<Option<ProfileState>>> = Mutex::new(RefCell::new(None));
+
+struct ProfileState {
+    // Each sample is stored as its length followed by that many addresses.
+    samples: Vec<u32, ExternalAllocator>,
+    sample_count: usize,
+    dropped: usize,
+    is_sampling: bool,
+}
+
+impl ProfileState {
+    fn record(&mut self, stack: &[u32]) {
+        if !self.is_sampling {
+            return;
+        }
+
+        // never grow the buffer since we're in an interrupt
+        if self.samples.len() + stack.len() + 1 > self.samples.capacity() {
+            self.dropped += 1;
+            return;
+        }
+
+        self.samples.push(stack.len() as u32);
+        self.samples.extend_from_slice(stack);
+        self.sample_count += 1;
+    }
+
+    fn stacks(&self) -> impl Iterator<Item = &[u32]> {
+        let mut remaining = &self.samples[..];
+
+        core::iter::from_fn(move || {
+            let (&len, rest) = remaining.split_first()?;
+            let (stack, rest) = rest.split_at(len as usize);
+            remaining = rest;
+            Some(stack)
+        })
+    }
+}
+
+/// A sampling profiler which collects samples for a given number of frames
+/// and then writes a report to the debug output.
+///
+/// See the [module level documentation](self) for how to use the output.
+pub struct Profiler<'a> {
+    timer: &'a mut Timer,
+    // SAFETY: Has to be dropped before the state is cleared
+    interrupt_handler: Option<InterruptHandler>,
+
+    period: u16,
+    frames_remaining: usize,
+}
+
+impl<'a> Profiler<'a> {
+    /// Starts a profiler which samples every `period` cycles of the given
+    /// `timer` for `frames` frames, using [`DEFAULT_BUFFER_WORDS`] of
+    /// storage for the samples. If you are profiling over many frames or with
+    /// a small period, use [`Profiler::new_with_buffer_size`] to make room for
+    /// more samples.
+    ///
+    /// Smaller periods give more accurate results, but affect the
+    /// performance of your game more.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another [`Profiler`] is already running.
+    #[must_use]
+    pub fn new(timer: &'a mut Timer, period: u16, frames: usize) -> Self {
+        Self::new_with_buffer_size(timer, period, frames, DEFAULT_BUFFER_WORDS)
+    }
+
+    /// Starts a profiler like [`Profiler::new`] but with `buffer_words` words
+    /// of storage for the samples. The buffer is allocated in ewram. Samples
+    /// which don't fit in the buffer are counted but otherwise discarded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another [`Profiler`] is already running.
+    #[must_use]
+    pub fn new_with_buffer_size(
+        timer: &'a mut Timer,
+        period: u16,
+        frames: usize,
+        buffer_words: usize,
+    ) -> Self {
+        let state = ProfileState {
+            samples: Vec::with_capacity_in(buffer_words, ExternalAllocator),
+            sample_count: 0,
+            dropped: 0,
+            is_sampling: true,
+        };
+
+        critical_section::with(|cs| {
+            let mut current_state = PROFILE_STATE.borrow_ref_mut(cs);
+            assert!(
+                current_state.is_none(),
+                "Cannot run more than one profiler at once"
+            );
+            *current_state = Some(state);
+        });
+
+        timer.set_interrupt(true);
+        timer.set_overflow_amount(period);
+        timer.set_enabled(true);
+
+        // SAFETY: the interrupt handler never allocates since the buffer never grows
+        let interrupt_handler = unsafe {
+            add_interrupt_handler(timer.interrupt(), |cs: CriticalSection| {
+                let mut stack = [0; MAX_STACK_DEPTH];
+                let depth = capture_interrupted_stack(&mut stack);
+
+                if let Some(state) = PROFILE_STATE.borrow_ref_mut(cs).as_mut() {
+                    state.record(&stack[..depth]);
+                }
+            })
+        };
+
+        Self {
+            timer,
+            interrupt_handler: Some(interrupt_handler),
+            period,
+            frames_remaining: frames,
+        }
+    }
+
+    /// Call this once per frame. Once the requested number of frames has
+    /// passed, sampling stops and the report is written to the debug output.
+    ///
+    /// Returns `true` on the frame that the report is written.
+    pub fn frame(&mut self) -> bool {
+        match self.frames_remaining {
+            0 => false,
+            1 => {
+                self.frames_remaining = 0;
+                self.report();
+                true
+            }
+            _ => {
+                self.frames_remaining -= 1;
+                false
+            }
+        }
+    }
+
+    /// Returns whether the profiler is still collecting samples.
+    #[must_use]
+    pub fn is_sampling(&self) -> bool {
+        self.interrupt_handler.is_some()
+    }
+
+    /// Stops sampling and writes the report to the debug output immediately. Does
+    /// nothing if the report has already been written.
+    pub fn report(&mut self) {
+        if !self.is_sampling() {
+            return;
+        }
+
+        self.stop();
+
+        if debug_output::backend() == Backend::Null {
+            return;
+        }
+
+        // Printing is slow, so don't keep interrupts disabled while writing the report.
+        // Sampling has stopped, so nothing else needs the state any more.
+        let state = critical_section::with(|cs| PROFILE_STATE.borrow_ref_mut(cs).take());
+        if let Some(state) = state {
+            let _ = write_report(&state, self.period);
+        }
+    }
+
+    fn stop(&mut self) {
+        self.timer.set_enabled(false);
+        self.timer.set_interrupt(false);
+        self.interrupt_handler = None;
+
+        critical_section::with(|cs| {
+            if let Some(state) = PROFILE_STATE.borrow_ref_mut(cs).as_mut() {
+                state.is_sampling = false;
+            }
+        });
+    }
+}
+
+impl Drop for Profiler<'_> {
+    fn drop(&mut self) {
+        self.stop();
+
+        critical_section::with(|cs| {
+            PROFILE_STATE.borrow_ref_mut(cs).take();
+        });
+    }
+}
+
+fn write_report(state: &ProfileState, period: u16) -> Result<(), core::fmt::Error> {
+    let mut histogram: HashMap<&[u32], usize> = HashMap::new();
+    for stack in state.stacks() {
+        *histogram.entry(stack).or_default() += 1;
+    }
+
+    debug_output::print(
+        format_args!(
+            "{LOG_PREFIX}start period={period} samples={} dropped={}",
+            state.sample_count, state.dropped
+        ),
+        DebugLevel::Info,
+    )?;
+
+    for (stack, count) in histogram.iter() {
+        debug_output::print(
+            format_args!("{LOG_PREFIX}{count} {}", EncodedStack(stack)),
+            DebugLevel::Info,
+        )?;
+    }
+
+    debug_output::print(format_args!("{LOG_PREFIX}end"), DebugLevel::Info)
+}
+
+struct EncodedStack<'a>(&'a [u32]);
+
+impl core::fmt::Display for EncodedStack<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        gwilym_encoding::write_frames(f, self.0)
+    }
+}
+
+/// Fills `stack` with the program counter of the interrupted code followed by
+/// the return addresses found by walking the frame pointer chain. Returns the
+/// number of entries written.
+///
+/// Only valid to call from within an interrupt handler.
+fn capture_interrupted_stack(stack: &mut [u32; MAX_STACK_DEPTH]) -> usize {
+    // The bios saves the address of the next instruction + 4
+    stack[0] = crate::program_counter_before_interrupt().wrapping_sub(4);
+
+    let mut frame_pointer = crate::frame_pointer_before_interrupt();
+    let mut depth = 1;
+
+    while depth < MAX_STACK_DEPTH && is_valid_frame_pointer(frame_pointer) {
+        let previous_frame_pointer = unsafe { *(frame_pointer as *const u32) };
+        let lr = unsafe { *((frame_pointer as *const u32).add(1)) };
+
+        if previous_frame_pointer == 0 || lr < 2 {
+            break;
+        }
+
+        // the link register points to the next instruction after the call, and we want the call itself
+        stack[depth] = lr - 2;
+        depth += 1;
+
+        // the stack grows downwards, so anything else means we've gone wrong somewhere
+        if previous_frame_pointer <= frame_pointer {
+            break;
+        }
+
+        frame_pointer = previous_frame_pointer;
+    }
+
+    depth
+}
+
+/// The interrupted code may not be maintaining a frame pointer (for example
+/// hand written assembly), so only follow ones which point into the stack.
+fn is_valid_frame_pointer(frame_pointer: u32) -> bool {
+    const IWRAM: core::ops::Range<u32> = 0x0300_0000..0x0300_8000;
+
+    frame_pointer.is_multiple_of(4)
+        && IWRAM.contains(&frame_pointer)
+        && IWRAM.contains(&(frame_pointer + 4))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test_case]
+    fn samples_are_stored_with_their_length(_gba: &mut crate::Gba) {
+        let mut state = ProfileState {
+            samples: Vec::with_capacity_in(8, ExternalAllocator),
+            sample_count: 0,
+            dropped: 0,
+            is_sampling: true,
+        };
+
+        state.record(&[0x0800_1234, 0x0800_4567]);
+        state.record(&[0x0300_0010]);
+        state.record(&[0x0800_1234, 0x0800_4567, 0x0800_89ab]);
+
+        assert_eq!(state.sample_count, 2);
+        assert_eq!(state.dropped, 1);
+        assert_eq!(
+            state.stacks().collect::<Vec<_>>(),
+            &[&[0x0800_1234, 0x0800_4567][..], &[0x0300_0010][..]]
+        );
+    }
+
+    #[test_case]
+    fn stacks_are_encoded_for_agb_debug(_gba: &mut crate::Gba) {
+        use alloc::format;
+
+        assert_eq!(
+            format!("{}", EncodedStack(&[0x0800_16d3, 0x0800_315b])),
+            "2QI65Qv1"
+        );
+    }
+
+    #[test_case]
+    fn frame_pointers_outside_iwram_are_rejected(_gba: &mut crate::Gba) {
+        assert!(is_valid_frame_pointer(0x0300_7f00));
+        assert!(!is_valid_frame_pointer(0x0300_7f02));
+        assert!(!is_valid_frame_pointer(0x0800_0000));
+        assert!(!is_valid_frame_pointer(0));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::memory_mapped::MemoryMapped;
+use crate::memory_mapped::{MemoryMapped, MemoryMapped1DArray};
 
 const CHANNEL_1_SWEEP: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0060) };
 const CHANNEL_1_LENGTH_DUTY_ENVELOPE: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0062) };
@@ -7,6 +7,12 @@ const CHANNEL_1_FREQUENCY_CONTROL: MemoryMapped<u16> = unsafe { MemoryMapped::ne
 const CHANNEL_2_LENGTH_DUTY_ENVELOPE: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0068) };
 const CHANNEL_2_FREQUENCY_CONTROL: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_006c) };
 
+const CHANNEL_3_STOP_WAVE_RAM_SELECT: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0070) };
+const CHANNEL_3_LENGTH_VOLUME: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0072) };
+const CHANNEL_3_FREQUENCY_CONTROL: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0074) };
+
+const WAVE_RAM: MemoryMapped1DArray<u16, 8> = unsafe { MemoryMapped1DArray::new(0x0400_0090) };
+
 const CHANNEL_4_LENGTH_ENVELOPE: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0078) };
 const CHANNEL_4_FREQUENCY_CONTROL: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_007c) };
 
@@ -32,20 +38,116 @@ impl Sound {
         Channel2 {}
     }
 
+    #[must_use]
+    pub fn channel3(&self) -> Channel3 {
+        Channel3 {}
+    }
+
     #[must_use]
     pub fn noise(&self) -> Noise {
         Noise {}
     }
 
+    /// Turns on the sound hardware, with every channel playing through both
+    /// speakers at full volume.
+    ///
+    /// This leaves the direct sound settings alone, so it can be used
+    /// alongside the [`mixer`](super::mixer).
     pub fn enable(&self) {
         MASTER_SOUND_STATUS.set_bits(1, 1, 7);
 
         #[allow(clippy::unusual_byte_groupings)] // I've split these like this for a reason
         MASTER_SOUND_VOLUME_ENABLE.set(0b1111_1111_0_111_0_111);
-        MASTER_SOUND_VOLUME_MIXING.set(0b10);
+        self.set_psg_volume(PsgVolume::Full);
+    }
+
+    /// Sets the volume of the left and right speakers for all the DMG
+    /// channels, from 0 (quietest, but not silent) to 7.
+    pub fn set_master_volume(&self, left: u8, right: u8) {
+        assert!(left < 8, "Left volume must be less than 8");
+        assert!(right < 8, "Right volume must be less than 8");
+
+        MASTER_SOUND_VOLUME_ENABLE.set_bits(u16::from(right), 3, 0);
+        MASTER_SOUND_VOLUME_ENABLE.set_bits(u16::from(left), 3, 4);
+    }
+
+    /// Sets which speakers `channel` plays through. A channel which plays
+    /// through neither is silent.
+    pub fn set_channel_output(&self, channel: DmgChannel, left: bool, right: bool) {
+        let index = channel.index();
+
+        MASTER_SOUND_VOLUME_ENABLE.set_bits(u16::from(right), 1, 8 + index);
+        MASTER_SOUND_VOLUME_ENABLE.set_bits(u16::from(left), 1, 12 + index);
+    }
+
+    /// Sets the volume of all the DMG channels relative to the direct sound
+    /// channels used by the [`mixer`](super::mixer).
+    pub fn set_psg_volume(&self, volume: PsgVolume) {
+        MASTER_SOUND_VOLUME_MIXING.set_bits(volume.as_bits(), 2, 0);
+    }
+
+    /// Whether `channel` is still playing. Sounds played without a length keep
+    /// playing until they are stopped.
+    #[must_use]
+    pub fn is_playing(&self, channel: DmgChannel) -> bool {
+        MASTER_SOUND_STATUS.get() & (1 << channel.index()) != 0
+    }
+}
+
+/// One of the 4 DMG channels, used to control their volume and routing
+/// through [`Sound`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DmgChannel {
+    /// The square wave channel with a frequency sweep, see [`Channel1`]
+    Channel1,
+    /// The square wave channel, see [`Channel2`]
+    Channel2,
+    /// The wave channel, see [`Channel3`]
+    Channel3,
+    /// The noise channel, see [`Noise`]
+    Noise,
+}
+
+impl DmgChannel {
+    fn index(self) -> u16 {
+        match self {
+            DmgChannel::Channel1 => 0,
+            DmgChannel::Channel2 => 1,
+            DmgChannel::Channel3 => 2,
+            DmgChannel::Noise => 3,
+        }
+    }
+}
+
+/// The volume of the DMG channels compared to the direct sound channels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PsgVolume {
+    Quarter,
+    Half,
+    Full,
+}
+
+impl PsgVolume {
+    fn as_bits(self) -> u16 {
+        match self {
+            PsgVolume::Quarter => 0,
+            PsgVolume::Half => 1,
+            PsgVolume::Full => 2,
+        }
     }
 }
 
+// An envelope starting at volume 0 is silent straight away, and the shortest
+// length lets the hardware mark the channel as finished soon after
+fn stop_channel(length_envelope: &MemoryMapped<u16>, frequency_control: &MemoryMapped<u16>) {
+    let shortest_length: u16 = 63;
+    let length_flag: u16 = 1 << 14;
+    let initial: u16 = 1 << 15;
+
+    length_envelope.set(shortest_length);
+    frequency_control.set(length_flag | initial);
+}
+
 #[non_exhaustive]
 pub struct Channel1 {}
 
@@ -71,6 +173,20 @@ impl Channel1 {
             .set(envelope_settings.as_bits() | duty_cycle.as_bits() | length_bits);
         CHANNEL_1_FREQUENCY_CONTROL.set(frequency | length_flag | initial);
     }
+
+    /// Changes the frequency of the sound currently playing without restarting it.
+    pub fn set_frequency(&self, frequency: u16) {
+        assert!(frequency < 2048, "Frequency must be less than 2048");
+        CHANNEL_1_FREQUENCY_CONTROL.set_bits(frequency, 11, 0);
+    }
+
+    /// Stops the sound currently playing.
+    pub fn stop(&self) {
+        stop_channel(
+            &CHANNEL_1_LENGTH_DUTY_ENVELOPE,
+            &CHANNEL_1_FREQUENCY_CONTROL,
+        );
+    }
 }
 
 #[non_exhaustive]
@@ -96,6 +212,179 @@ impl Channel2 {
             .set(envelope_settings.as_bits() | duty_cycle.as_bits() | length_bits);
         CHANNEL_2_FREQUENCY_CONTROL.set(frequency | length_flag | initial);
     }
+
+    /// Changes the frequency of the sound currently playing without restarting it.
+    pub fn set_frequency(&self, frequency: u16) {
+        assert!(frequency < 2048, "Frequency must be less than 2048");
+        CHANNEL_2_FREQUENCY_CONTROL.set_bits(frequency, 11, 0);
+    }
+
+    /// Stops the sound currently playing.
+    pub fn stop(&self) {
+        stop_channel(
+            &CHANNEL_2_LENGTH_DUTY_ENVELOPE,
+            &CHANNEL_2_FREQUENCY_CONTROL,
+        );
+    }
+}
+
+/// A wave made of 32 4-bit samples, packed 2 to a byte with the first sample
+/// in the high nibble.
+pub type Wave = [u8; 16];
+
+/// The channel which plays a [`Wave`] stored in the sound hardware's wave
+/// RAM, for sounds which can't be made from square waves.
+///
+/// Wave RAM has 2 banks. While one bank is playing, the other can be
+/// changed with [`load_wave`](Channel3::load_wave) and swapped in with
+/// [`set_playing_bank`](Channel3::set_playing_bank) without stopping the
+/// sound.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # fn foo(gba: &mut agb::Gba) {
+/// use agb::sound::dmg::{WaveBank, WaveBankMode, WaveVolume};
+///
+/// const TRIANGLE: agb::sound::dmg::Wave = [
+///     0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef,
+///     0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54, 0x32, 0x10,
+/// ];
+///
+/// gba.sound.enable();
+///
+/// let channel3 = gba.sound.channel3();
+/// channel3.load_wave(WaveBank::Bank0, &TRIANGLE);
+/// channel3.play_sound(1750, None, WaveVolume::Full, WaveBankMode::Single(WaveBank::Bank0));
+/// # }
+/// ```
+#[non_exhaustive]
+pub struct Channel3 {}
+
+impl Channel3 {
+    /// Plays the wave RAM at `frequency`, where each sample plays for
+    /// `(2048 - frequency) / 2097152` seconds. A [`Wave`] from a single bank
+    /// plays at `65536 / (2048 - frequency)` Hz.
+    ///
+    /// If `length` is given, the sound stops after `(256 - length) / 256`
+    /// seconds.
+    pub fn play_sound(
+        &self,
+        frequency: u16,
+        length: Option<u8>,
+        volume: WaveVolume,
+        bank_mode: WaveBankMode,
+    ) {
+        assert!(frequency < 2048, "Frequency must be less than 2048");
+
+        let length_bits = u16::from(length.unwrap_or(0));
+        let length_flag: u16 = length.map_or(0, |_| 1 << 14);
+        let initial: u16 = 1 << 15;
+        let playback_enable: u16 = 1 << 7;
+
+        CHANNEL_3_STOP_WAVE_RAM_SELECT.set(bank_mode.as_bits() | playback_enable);
+        CHANNEL_3_LENGTH_VOLUME.set(length_bits | volume.as_bits());
+        CHANNEL_3_FREQUENCY_CONTROL.set(frequency | length_flag | initial);
+    }
+
+    /// Changes the frequency of the sound currently playing without restarting it.
+    pub fn set_frequency(&self, frequency: u16) {
+        assert!(frequency < 2048, "Frequency must be less than 2048");
+        CHANNEL_3_FREQUENCY_CONTROL.set_bits(frequency, 11, 0);
+    }
+
+    /// Changes the volume of the sound currently playing without restarting it.
+    pub fn set_volume(&self, volume: WaveVolume) {
+        CHANNEL_3_LENGTH_VOLUME.set_bits(volume.as_bits() >> 13, 3, 13);
+    }
+
+    /// Stops the sound currently playing.
+    pub fn stop(&self) {
+        CHANNEL_3_STOP_WAVE_RAM_SELECT.set_bits(0, 1, 7);
+    }
+
+    /// Copies `wave` into `bank` of the wave RAM.
+    ///
+    /// Only the bank which isn't playing can be written to, so if `bank` is
+    /// currently playing the channel plays the other bank while the wave is
+    /// copied. Load into the bank that isn't playing to avoid glitches.
+    pub fn load_wave(&self, bank: WaveBank, wave: &Wave) {
+        let previous = CHANNEL_3_STOP_WAVE_RAM_SELECT.get();
+
+        // the cpu sees whichever bank isn't selected for playback
+        CHANNEL_3_STOP_WAVE_RAM_SELECT.set_bits(1 - bank.as_bit(), 1, 6);
+
+        for (i, samples) in wave.chunks_exact(2).enumerate() {
+            WAVE_RAM.set(i, u16::from_le_bytes([samples[0], samples[1]]));
+        }
+
+        CHANNEL_3_STOP_WAVE_RAM_SELECT.set(previous);
+    }
+
+    /// Switches which bank is playing without restarting the sound. In
+    /// [`WaveBankMode::Both`] this is the bank that plays first.
+    pub fn set_playing_bank(&self, bank: WaveBank) {
+        CHANNEL_3_STOP_WAVE_RAM_SELECT.set_bits(bank.as_bit(), 1, 6);
+    }
+}
+
+/// One of the 2 banks of wave RAM used by [`Channel3`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WaveBank {
+    Bank0,
+    Bank1,
+}
+
+impl WaveBank {
+    fn as_bit(self) -> u16 {
+        match self {
+            WaveBank::Bank0 => 0,
+            WaveBank::Bank1 => 1,
+        }
+    }
+}
+
+/// Which banks of wave RAM [`Channel3`] plays.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WaveBankMode {
+    /// Plays the 32 samples in the given bank over and over.
+    Single(WaveBank),
+    /// Plays all 64 samples, starting with the given bank and then the other
+    /// one.
+    Both(WaveBank),
+}
+
+impl WaveBankMode {
+    fn as_bits(self) -> u16 {
+        match self {
+            WaveBankMode::Single(bank) => bank.as_bit() << 6,
+            WaveBankMode::Both(bank) => (1 << 5) | (bank.as_bit() << 6),
+        }
+    }
+}
+
+/// How loud [`Channel3`] plays its wave.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WaveVolume {
+    Mute,
+    Quarter,
+    Half,
+    ThreeQuarters,
+    Full,
+}
+
+impl WaveVolume {
+    fn as_bits(self) -> u16 {
+        match self {
+            WaveVolume::Mute => 0,
+            WaveVolume::Quarter => 3 << 13,
+            WaveVolume::Half => 2 << 13,
+            WaveVolume::ThreeQuarters => 1 << 15,
+            WaveVolume::Full => 1 << 13,
+        }
+    }
 }
 
 #[non_exhaustive]
@@ -136,6 +425,11 @@ impl Noise {
                 | initial,
         );
     }
+
+    /// Stops the sound currently playing.
+    pub fn stop(&self) {
+        stop_channel(&CHANNEL_4_LENGTH_ENVELOPE, &CHANNEL_4_FREQUENCY_CONTROL);
+    }
 }
 
 pub enum SoundDirection {
@@ -242,3 +536,25 @@ impl DutyCycle {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test_case]
+    fn volume_and_routing_leave_other_settings_alone(gba: &mut crate::Gba) {
+        gba.sound.enable();
+
+        gba.sound.set_master_volume(3, 5);
+        gba.sound
+            .set_channel_output(DmgChannel::Channel3, false, true);
+        gba.sound.set_psg_volume(PsgVolume::Half);
+
+        #[allow(clippy::unusual_byte_groupings)]
+        let expected = 0b1011_1111_0_011_0_101;
+        assert_eq!(MASTER_SOUND_VOLUME_ENABLE.get(), expected);
+        assert_eq!(MASTER_SOUND_VOLUME_MIXING.get() & 0b11, 1);
+
+        gba.sound.enable();
+    }
+}
@@ -0,0 +1,279 @@
+use super::SoundSource;
+
+const BLOCK_FRAMES: usize = 1024;
+const HEADER_SIZE: usize = 8;
+
+const INDEX_TABLE: [i8; 16] = [-1, -1, -1, -1, 2, 4, 6, 8, -1, -1, -1, -1, 2, 4, 6, 8];
+
+const STEP_TABLE: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+    73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449,
+    494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272,
+    2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493,
+    10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+
+#[derive(Clone, Copy, Default)]
+struct ChannelState {
+    predictor: i32,
+    index: usize,
+}
+
+impl ChannelState {
+    #[inline]
+    fn decode(&mut self, nibble: u8) -> i8 {
+        let step = STEP_TABLE[self.index];
+
+        let mut diff = step >> 3;
+        if nibble & 4 != 0 {
+            diff += step;
+        }
+        if nibble & 2 != 0 {
+            diff += step >> 1;
+        }
+        if nibble & 1 != 0 {
+            diff += step >> 2;
+        }
+
+        if nibble & 8 != 0 {
+            self.predictor = (self.predictor - diff).max(i16::MIN.into());
+        } else {
+            self.predictor = (self.predictor + diff).min(i16::MAX.into());
+        }
+
+        self.index =
+            (self.index as i32 + i32::from(INDEX_TABLE[nibble as usize])).clamp(0, 88) as usize;
+
+        (self.predictor >> 8) as i8
+    }
+}
+
+/// Plays a sound encoded as 4-bit ADPCM by [`include_wav!`](crate::include_wav)
+/// with the `adpcm` option, decoding it as it plays. ADPCM sounds take half
+/// the space of the usual 8-bit samples, at the cost of some quality and the
+/// CPU time to decode them.
+///
+/// Decoding happens in Rust rather than alongside the hand written mixing
+/// routines in `mixer.s`. Those mix from a slice of samples which is already
+/// there, whereas ADPCM has to keep the predictor and step size of each channel
+/// from one frame to the next, and decode into the same buffer any other
+/// [`SoundSource`] would fill. Each call decodes as long a run as it can from
+/// one block, so the loop is just the decoding, and the `adpcm_cost` example
+/// compares the time `Mixer::frame` takes with this against playing 8-bit
+/// samples directly.
+///
+/// Play it with [`SoundChannel::from_adpcm`](super::SoundChannel::from_adpcm).
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # use agb::sound::mixer::*;
+/// # use agb::*;
+/// # fn foo(gba: &mut Gba) {
+/// # let mut mixer = gba.mixer.mixer(Frequency::Hz10512);
+/// static MY_BGM: &[u8] = include_wav!("examples/sfx/my_bgm.wav", adpcm);
+///
+/// let mut bgm = SoundChannel::from_adpcm(AdpcmSource::new(MY_BGM).looping(0));
+/// bgm.priority(u8::MAX);
+/// let _ = mixer.play_sound(bgm);
+/// # }
+/// ```
+pub struct AdpcmSource {
+    data: &'static [u8],
+    channels: usize,
+    frames: usize,
+    frame: usize,
+    states: [ChannelState; 2],
+    restart_frame: Option<usize>,
+}
+
+impl AdpcmSource {
+    /// Creates a source which plays `data` once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` wasn't created by [`include_wav!`](crate::include_wav)
+    /// with the `adpcm` option.
+    #[must_use]
+    pub fn new(data: &'static [u8]) -> Self {
+        assert!(
+            data.len() >= HEADER_SIZE && (data[0] == 1 || data[0] == 2),
+            "Not ADPCM data, include it with include_wav!(..., adpcm)"
+        );
+
+        let channels = data[0] as usize;
+        let frames = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+
+        Self {
+            data,
+            channels,
+            frames,
+            frame: 0,
+            states: [ChannelState::default(); 2],
+            restart_frame: None,
+        }
+    }
+
+    /// Makes the sound loop forever, jumping back to `restart_frame` (counted
+    /// in samples per channel) each time it reaches the end.
+    ///
+    /// Jumping is quickest when `restart_frame` is a multiple of 1024.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `restart_frame` is past the end of the sound.
+    #[must_use]
+    pub fn looping(mut self, restart_frame: usize) -> Self {
+        assert!(
+            restart_frame < self.frames,
+            "restart frame must be before the end of the sound"
+        );
+
+        self.restart_frame = Some(restart_frame);
+        self
+    }
+
+    /// Whether the sound has 2 channels. [`SoundChannel::from_adpcm`](super::SoundChannel::from_adpcm)
+    /// uses this to play the sound in stereo.
+    #[must_use]
+    pub fn is_stereo(&self) -> bool {
+        self.channels == 2
+    }
+
+    fn block_size(&self) -> usize {
+        self.channels * 4 + BLOCK_FRAMES * self.channels / 2
+    }
+
+    /// Decodes the next `output.len() / channels` frames, which must all be in
+    /// the same block. The block's position is only worked out once, so the
+    /// loops over the samples are just the decoding.
+    fn decode_run(&mut self, output: &mut [i8]) {
+        let block_start = HEADER_SIZE + (self.frame / BLOCK_FRAMES) * self.block_size();
+        let offset = self.frame % BLOCK_FRAMES;
+
+        if offset == 0 {
+            self.load_block_header(block_start);
+        }
+
+        let samples = &self.data[block_start + self.channels * 4..];
+        let frames = output.len() / self.channels;
+
+        if self.channels == 1 {
+            // 2 frames per byte, low nibble first
+            let mut state = self.states[0];
+
+            // a run starting half way through a byte finishes that byte first
+            let (first, rest) =
+                output.split_at_mut(usize::from(!offset.is_multiple_of(2)).min(frames));
+            if let [first] = first {
+                *first = state.decode(samples[offset / 2] >> 4);
+            }
+
+            let bytes = &samples[offset.div_ceil(2)..];
+            let whole_bytes = rest.len() / 2;
+            let mut pairs = rest.chunks_exact_mut(2);
+            for (pair, &byte) in (&mut pairs).zip(bytes) {
+                pair[0] = state.decode(byte & 0xf);
+                pair[1] = state.decode(byte >> 4);
+            }
+
+            if let [last] = pairs.into_remainder() {
+                *last = state.decode(bytes[whole_bytes] & 0xf);
+            }
+
+            self.states[0] = state;
+        } else {
+            // 1 frame per byte, left channel in the low nibble
+            let [mut left, mut right] = self.states;
+
+            for (frame, &byte) in output.chunks_exact_mut(2).zip(&samples[offset..]) {
+                frame[0] = left.decode(byte & 0xf);
+                frame[1] = right.decode(byte >> 4);
+            }
+
+            self.states = [left, right];
+        }
+
+        self.frame += frames;
+    }
+
+    fn load_block_header(&mut self, block_start: usize) {
+        for (channel, state) in self.states[..self.channels].iter_mut().enumerate() {
+            let header = &self.data[block_start + channel * 4..];
+
+            state.predictor = i16::from_le_bytes([header[0], header[1]]).into();
+            state.index = usize::from(header[2]).min(88);
+        }
+    }
+
+    /// How many frames can be decoded in one go, stopping at the end of the
+    /// current block or the end of the sound.
+    fn frames_in_run(&self, wanted: usize) -> usize {
+        wanted
+            .min(self.frames - self.frame)
+            .min(BLOCK_FRAMES - self.frame % BLOCK_FRAMES)
+    }
+
+    fn seek(&mut self, frame: usize) {
+        self.frame = frame - frame % BLOCK_FRAMES;
+
+        let mut discard = [0; 64];
+        while self.frame < frame {
+            let run = self.frames_in_run((frame - self.frame).min(discard.len() / self.channels));
+            self.decode_run(&mut discard[..run * self.channels]);
+        }
+    }
+}
+
+impl SoundSource for AdpcmSource {
+    fn fill(&mut self, buffer: &mut [i8]) -> usize {
+        let frames_wanted = buffer.len() / self.channels;
+        let mut frames_written = 0;
+
+        while frames_written < frames_wanted {
+            if self.frame >= self.frames {
+                match self.restart_frame {
+                    Some(restart_frame) => self.seek(restart_frame),
+                    None => break,
+                }
+            }
+
+            let run = self.frames_in_run(frames_wanted - frames_written);
+            let start = frames_written * self.channels;
+            self.decode_run(&mut buffer[start..start + run * self.channels]);
+
+            frames_written += run;
+        }
+
+        frames_written * self.channels
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // A mono sound with 2 frames, starting at the largest step size
+    static SOUND: &[u8] = &[1, 0, 0, 0, 2, 0, 0, 0, 0x00, 0x10, 88, 0, 0xf7];
+
+    #[test_case]
+    fn adpcm_source_decodes_until_the_end(_: &mut crate::Gba) {
+        let mut source = AdpcmSource::new(SOUND);
+        let mut buffer = [0; 4];
+
+        assert!(!source.is_stereo());
+        assert_eq!(source.fill(&mut buffer), 2);
+        assert_eq!(&buffer[..2], &[127, -112]);
+    }
+
+    #[test_case]
+    fn adpcm_source_can_loop(_: &mut crate::Gba) {
+        let mut source = AdpcmSource::new(SOUND).looping(0);
+        let mut buffer = [0; 5];
+
+        assert_eq!(source.fill(&mut buffer), 5);
+        assert_eq!(buffer, [127, -112, 127, -112, 127]);
+    }
+}
@@ -0,0 +1,160 @@
+use alloc::{vec, vec::Vec};
+
+use crate::fixnum::Num;
+
+/// A one-pole low-pass filter, applied to a single channel's samples after
+/// they have been mixed on their own.
+pub(super) struct LowPass {
+    amount: i32,
+    // the previous output for the two halves of the buffer, with 4 extra bits
+    // of precision so quiet sounds don't get stuck
+    state: [i32; 2],
+}
+
+impl LowPass {
+    pub(super) fn new(amount: Num<i16, 8>) -> Self {
+        Self {
+            amount: amount.to_raw().into(),
+            state: [0; 2],
+        }
+    }
+
+    pub(super) fn set_amount(&mut self, amount: Num<i16, 8>) {
+        self.amount = amount.to_raw().into();
+    }
+
+    pub(super) fn apply(&mut self, buffer: &mut [Num<i16, 4>]) {
+        for frame in buffer.chunks_exact_mut(2) {
+            for (sample, state) in frame.iter_mut().zip(&mut self.state) {
+                let input = i32::from(sample.to_raw()) << 4;
+                *state += ((input - *state) * self.amount) >> 8;
+                *sample = Num::from_raw((*state >> 4) as i16);
+            }
+        }
+    }
+}
+
+/// Settings for the echo which the [`Mixer`](super::Mixer) can apply to
+/// everything it plays. Turn it on with
+/// [`Mixer::set_echo`](super::Mixer::set_echo).
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # use agb::sound::mixer::*;
+/// # fn foo(gba: &mut agb::Gba) {
+/// # let mut mixer = gba.mixer.mixer(Frequency::Hz10512);
+/// use agb::fixnum::num;
+///
+/// // A quarter of a second delay, which fades out over a few repeats
+/// mixer.set_echo(Some(Echo::new(250, num!(0.5), num!(0.5))));
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Echo {
+    delay_ms: u32,
+    feedback: Num<i16, 8>,
+    volume: Num<i16, 8>,
+}
+
+impl Echo {
+    /// Creates echo settings which repeat the sound after `delay_ms`
+    /// milliseconds at `volume`. Each repeat is fed back in at `feedback`
+    /// times the volume of the previous one, so `feedback` should be less than
+    /// 1 or the echo will never die away.
+    ///
+    /// The delay takes 4 bytes of memory per sample at the mixer's frequency,
+    /// so long delays at high frequencies use a lot of memory.
+    #[must_use]
+    pub fn new(delay_ms: u32, feedback: Num<i16, 8>, volume: Num<i16, 8>) -> Self {
+        assert!(delay_ms > 0, "echo delay must be greater than 0");
+
+        Self {
+            delay_ms,
+            feedback,
+            volume,
+        }
+    }
+}
+
+pub(super) struct EchoState {
+    delay: Vec<i16>,
+    pos: usize,
+    feedback: i32,
+    volume: i32,
+}
+
+impl EchoState {
+    pub(super) fn new(echo: Echo, frequency: i32) -> Self {
+        let delay_samples = (echo.delay_ms as usize * frequency as usize / 1000).max(1);
+
+        Self {
+            // left and right are interleaved
+            delay: vec![0; delay_samples * 2],
+            pos: 0,
+            feedback: echo.feedback.to_raw().into(),
+            volume: echo.volume.to_raw().into(),
+        }
+    }
+
+    pub(super) fn apply(&mut self, buffer: &mut [Num<i16, 4>]) {
+        for sample in buffer {
+            let dry = i32::from(sample.to_raw());
+            let delayed = i32::from(self.delay[self.pos]);
+
+            let wet = dry + ((delayed * self.volume) >> 8);
+            *sample = Num::from_raw(wet.clamp(i16::MIN.into(), i16::MAX.into()) as i16);
+
+            let fed_back = dry + ((delayed * self.feedback) >> 8);
+            self.delay[self.pos] = fed_back.clamp(i16::MIN.into(), i16::MAX.into()) as i16;
+
+            self.pos += 1;
+            if self.pos == self.delay.len() {
+                self.pos = 0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fixnum::num;
+
+    use super::*;
+
+    fn raw(buffer: &[Num<i16, 4>]) -> Vec<i16> {
+        buffer.iter().map(|sample| sample.to_raw()).collect()
+    }
+
+    #[test_case]
+    fn low_pass_smooths_a_step(_: &mut crate::Gba) {
+        let mut low_pass = LowPass::new(num!(0.5));
+        let mut buffer = [Num::from_raw(256); 8];
+
+        low_pass.apply(&mut buffer);
+
+        assert_eq!(raw(&buffer), &[128, 128, 192, 192, 224, 224, 240, 240]);
+    }
+
+    #[test_case]
+    fn echo_repeats_after_the_delay(_: &mut crate::Gba) {
+        let mut echo = EchoState::new(Echo::new(1, num!(0.5), num!(0.5)), 2000);
+        let mut buffer = vec![Num::from_raw(0); 12];
+        buffer[0] = Num::from_raw(400);
+        buffer[1] = Num::from_raw(-400);
+
+        echo.apply(&mut buffer);
+
+        // 1ms at 2000Hz is 2 samples for each of left and right
+        assert_eq!(
+            raw(&buffer),
+            &[400, -400, 0, 0, 200, -200, 0, 0, 100, -100, 0, 0]
+        );
+
+        let mut buffer = vec![Num::from_raw(0); 4];
+        echo.apply(&mut buffer);
+        assert_eq!(raw(&buffer), &[50, -50, 0, 0]);
+    }
+}
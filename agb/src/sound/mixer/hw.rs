@@ -78,8 +78,12 @@ pub(super) fn set_sound_control_register_for_mixer() {
     let sound_b_lout: u16 = 0 << 13;
     let sound_b_fifo_reset: u16 = 1 << 15;
 
+    // keep the dmg volume, in case it is being used at the same time
+    let dmg_volume = SOUND_CONTROL.get() & 0b11;
+
     SOUND_CONTROL.set(
-        sound_a_volume_100
+        dmg_volume
+            | sound_a_volume_100
             | sound_a_rout
             | sound_a_lout
             | sound_a_fifo_reset
@@ -10,7 +10,9 @@
 //! have to use a software mixer.
 //!
 //! agb's software mixer allows for up to 8 simultaneous sounds played at once at
-//! various speeds and volumes.
+//! various speeds and volumes by default. If you need more (for example, tracker music
+//! and sound effects at the same time), create the mixer with
+//! [`mixer_with_channels`](MixerController::mixer_with_channels) instead.
 //!
 //! # Concepts
 //!
@@ -68,11 +70,12 @@
 //!
 //! ## Loading a sample
 //!
-//! To load a sample, you must have it in `wav` format (both stereo and mono work) at exactly the
-//! selected frequency based on the features enabled in the agb crate.
+//! To load a sample, you must have it in `wav`, `ogg`, `flac` or `mp3` format (both stereo and mono
+//! work) and at exactly the frequency of your mixer.
 //!
-//! Use the [`include_wav!`](crate::include_wav) macro in order to load the sound. This will produce
-//! an error if your wav file is of the wrong frequency.
+//! Use the [`include_wav!`](crate::include_wav) macro in order to load the sound. If your file is at
+//! a different frequency, pass the `resample` option to convert it, for example
+//! `include_wav!("music.ogg", resample = 10512)`.
 //!
 //! ```rust,no_run
 //! # #![no_std]
@@ -93,15 +96,65 @@
 //!
 //! See the [`SoundChannel`] struct for more details on how you can configure the sounds to play.
 //!
+//! Sounds take a lot of ROM space, so you can pass `compress` to [`include_wav!`](crate::include_wav)
+//! to compress them. The mixer can't play compressed sound directly, so you will need to decompress
+//! them into RAM first with [`syscall::decompress`](crate::syscall::decompress). This works best for
+//! short sound effects which are played often.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # extern crate alloc;
+//! # fn foo(gba: &mut agb::Gba) {
+//! # let mut mixer = gba.mixer.mixer(agb::sound::mixer::Frequency::Hz10512);
+//! # use agb::{*, sound::mixer::*};
+//! static MY_COMPRESSED_SOUND: &[u8] = include_wav!("examples/sfx/jump.wav", compress);
+//!
+//! // Decompress once, and keep it around for the rest of the game
+//! let my_sound: &'static [u8] = agb::syscall::decompress(MY_COMPRESSED_SOUND).leak();
+//!
+//! let _ = mixer.play_sound(SoundChannel::new(my_sound));
+//! # }
+//! ```
+//!
+//! Background music is usually the biggest thing in a ROM. Passing `adpcm` to
+//! [`include_wav!`](crate::include_wav) encodes the sound as 4-bit ADPCM, which is half the size
+//! and is decoded as it plays by [`AdpcmSource`].
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(gba: &mut agb::Gba) {
+//! # let mut mixer = gba.mixer.mixer(agb::sound::mixer::Frequency::Hz10512);
+//! # use agb::{*, sound::mixer::*};
+//! static MY_BGM: &[u8] = include_wav!("examples/sfx/my_bgm.wav", adpcm);
+//!
+//! let _ = mixer.play_sound(SoundChannel::from_adpcm(AdpcmSource::new(MY_BGM).looping(0)));
+//! # }
+//! ```
+//!
+//! Channels can be muffled with [`SoundChannel::low_pass`], and [`Mixer::set_echo`] adds an
+//! [`Echo`] to everything the mixer plays. Neither costs anything unless it is turned on.
+//!
 //! Once you have run [`play_sound`](Mixer::play_sound), the mixer will play that sound until
 //! it has finished.
+mod adpcm;
+mod effects;
 mod hw;
+mod source;
 mod sw_mixer;
 
+pub use adpcm::AdpcmSource;
+pub use effects::Echo;
+pub use source::SoundSource;
 pub use sw_mixer::ChannelId;
 pub use sw_mixer::Mixer;
 
+use alloc::boxed::Box;
+
 use crate::fixnum::Num;
+use effects::LowPass;
+use source::SourceStream;
 
 /// Controls access to the mixer and the underlying hardware it uses. A zero sized type that
 /// ensures that mixer access is exclusive.
@@ -113,16 +166,41 @@ impl MixerController {
         MixerController {}
     }
 
-    /// Get a [`Mixer`] in order to start producing sounds.
+    /// Get a [`Mixer`] in order to start producing sounds. The mixer can
+    /// play up to 8 sounds at once.
     pub fn mixer(&mut self, frequency: Frequency) -> Mixer<'_> {
-        Mixer::new(frequency)
+        Mixer::new(frequency, 8)
+    }
+
+    /// Get a [`Mixer`] which can play up to `channels` sounds at once. Each
+    /// playing channel costs CPU time every frame, so only ask for as many as
+    /// you need.
+    ///
+    /// Panics if `channels` is 0.
+    pub fn mixer_with_channels(&mut self, frequency: Frequency, channels: usize) -> Mixer<'_> {
+        assert!(channels > 0, "The mixer needs at least one channel");
+        Mixer::new(frequency, channels)
     }
 }
 
-#[derive(PartialEq, Eq)]
-enum SoundPriority {
-    High,
-    Low,
+/// Decides which playing sound gets replaced when a new sound is played and
+/// every channel of the [`Mixer`] is in use.
+///
+/// Only sounds with a strictly lower [`priority`](SoundChannel::priority)
+/// than the new sound are ever replaced, so the policy just picks between
+/// those. Set it with [`Mixer::set_voice_stealing`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum VoiceStealing {
+    /// Replace the sound with the lowest priority, picking the one which
+    /// started playing first if there is a tie.
+    #[default]
+    LowestPriority,
+    /// Replace the sound which started playing first.
+    Oldest,
+    /// Replace the sound with the lowest volume, counting paused sounds as
+    /// silent. Picks the one which started playing first if there is a tie.
+    Quietest,
 }
 
 /// The supported frequencies within AGB.
@@ -173,14 +251,16 @@ impl Frequency {
 /// SoundChannels are very cheap to create, so don't worry about creating a brand new
 /// one for every single sound you want to play.
 ///
-/// SoundChannels can be either 'low priority' or 'high priority'. A high priority
-/// sound channel will override 'low priority' sound channels which are already playing
-/// to ensure that it is always running. A 'low priority' sound channel will not override
-/// any other channel.
+/// SoundChannels have a numeric priority from 0 to 255. When every channel of the mixer
+/// is in use, a new sound will replace a playing sound with a strictly lower priority
+/// (chosen by the mixer's [`VoiceStealing`] policy) or not play at all if there isn't one.
+/// [`new`](SoundChannel::new) creates channels with priority 0, which never replace
+/// anything, and [`new_high_priority`](SoundChannel::new_high_priority) creates channels
+/// with priority 255, which can never be replaced.
 ///
-/// This is because you can only play up to 8 channels at once, and so high priority channels
-/// are prioritised over low priority channels to ensure that sounds that you always want
-/// playing will always play.
+/// This is because the mixer can only play a limited number of channels at once, and so
+/// high priority channels are prioritised over low priority channels to ensure that sounds
+/// that you always want playing will always play.
 ///
 /// # Example
 ///
@@ -239,13 +319,17 @@ pub struct SoundChannel {
 
     is_stereo: bool,
 
-    priority: SoundPriority,
+    priority: u8,
+
+    source: Option<SourceStream>,
+
+    low_pass: Option<LowPass>,
 }
 
 impl SoundChannel {
-    /// Creates a new low priority [`SoundChannel`].
+    /// Creates a new low priority [`SoundChannel`], with a priority of 0.
     ///
-    /// A low priority sound channel will be overridden by a high priority one if
+    /// A low priority sound channel will be overridden by a higher priority one if
     /// the mixer runs out of channels.
     ///
     /// Low priority sound channels are intended for sound effects.
@@ -278,16 +362,18 @@ impl SoundChannel {
             is_playing: true,
             panning: 0.into(),
             is_done: false,
-            priority: SoundPriority::Low,
+            priority: 0,
             volume: 1.into(),
             is_stereo: false,
             restart_point: 0.into(),
+            source: None,
+            low_pass: None,
         }
     }
 
-    /// Creates a new high priority [`SoundChannel`].
+    /// Creates a new high priority [`SoundChannel`], with a priority of 255.
     ///
-    /// A high priority sound channel will override low priority ones if
+    /// A high priority sound channel will override lower priority ones if
     /// the mixer runs out of channels. They will also never be overridden
     /// by other high priority channels.
     ///
@@ -323,13 +409,68 @@ impl SoundChannel {
             is_playing: true,
             panning: 0.into(),
             is_done: false,
-            priority: SoundPriority::High,
+            priority: u8::MAX,
             volume: 1.into(),
             is_stereo: false,
             restart_point: 0.into(),
+            source: None,
+            low_pass: None,
         }
     }
 
+    /// Creates a new low priority [`SoundChannel`] which plays samples pulled
+    /// from `source` each frame, rather than from static data. See
+    /// [`SoundSource`] for an example.
+    ///
+    /// The source decides when the sound ends, so
+    /// [`should_loop`](SoundChannel::should_loop) and
+    /// [`restart_point`](SoundChannel::restart_point) have no effect, and
+    /// [`pos`](SoundChannel::pos) is only the position within the samples
+    /// the mixer has pulled but not yet played.
+    #[must_use]
+    pub fn from_source(source: impl SoundSource + 'static) -> Self {
+        let mut channel = Self::new(&[]);
+        channel.source = Some(SourceStream::new(Box::new(source)));
+        channel
+    }
+
+    /// Creates a new low priority [`SoundChannel`] which decodes and plays an
+    /// ADPCM sound. Stereo sounds are played in stereo without needing to
+    /// call [`stereo`](SoundChannel::stereo).
+    ///
+    /// ```rust,no_run
+    /// # #![no_std]
+    /// # #![no_main]
+    /// # use agb::sound::mixer::*;
+    /// # use agb::*;
+    /// # fn foo(gba: &mut Gba) {
+    /// # let mut mixer = gba.mixer.mixer(Frequency::Hz10512);
+    /// static JUMP_SOUND: &[u8] = include_wav!("examples/sfx/jump.wav", adpcm);
+    ///
+    /// let _ = mixer.play_sound(SoundChannel::from_adpcm(AdpcmSource::new(JUMP_SOUND)));
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn from_adpcm(source: AdpcmSource) -> Self {
+        let is_stereo = source.is_stereo();
+
+        let mut channel = Self::from_source(source);
+        if is_stereo {
+            channel.stereo();
+        }
+
+        channel
+    }
+
+    /// Sets the priority of this channel, which decides whether it can replace
+    /// (or be replaced by) other sounds when the mixer runs out of channels. A
+    /// sound can only replace sounds with a strictly lower priority.
+    #[inline(always)]
+    pub fn priority(&mut self, priority: u8) -> &mut Self {
+        self.priority = priority;
+        self
+    }
+
     /// Sets that a sound channel should loop back to the start once it has
     /// finished playing rather than stopping.
     #[inline(always)]
@@ -395,6 +536,33 @@ impl SoundChannel {
         self
     }
 
+    /// Muffles the sound with a low-pass filter, for example to make it sound
+    /// like it is underwater or behind a wall. `amount` should be between 0
+    /// and 1, with smaller values cutting out more of the high frequencies.
+    /// An `amount` of 1 turns the filter off.
+    ///
+    /// Filtered channels take a bit more CPU time to mix, but channels without
+    /// a filter cost nothing extra.
+    #[inline]
+    pub fn low_pass(&mut self, amount: impl Into<Num<i16, 8>>) -> &mut Self {
+        let amount = amount.into();
+
+        assert!(
+            amount >= Num::new(0) && amount <= Num::new(1),
+            "low pass amount must be between 0 and 1"
+        );
+
+        if amount == Num::new(1) {
+            self.low_pass = None;
+        } else if let Some(low_pass) = &mut self.low_pass {
+            low_pass.set_amount(amount);
+        } else {
+            self.low_pass = Some(LowPass::new(amount));
+        }
+
+        self
+    }
+
     /// Sets that the sound effect should be played in stereo. Not setting this
     /// will result in the sound playing at half speed and mono. Setting this on
     /// a mono sound will cause some interesting results (and play it at double speed).
@@ -0,0 +1,198 @@
+use alloc::{boxed::Box, vec::Vec};
+
+/// Something which produces samples for the mixer as it plays, rather than
+/// them all being available up front. Play one with
+/// [`SoundChannel::from_source`](super::SoundChannel::from_source).
+///
+/// This is useful for sounds generated at runtime (synthesisers, noise),
+/// sounds decompressed a bit at a time, or samples which live in RAM rather
+/// than ROM.
+///
+/// Samples are signed 8-bit values at the mixer's frequency. For stereo
+/// channels they are interleaved, left then right.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # use agb::sound::mixer::*;
+/// # fn foo(gba: &mut agb::Gba) {
+/// # let mut mixer = gba.mixer.mixer(Frequency::Hz10512);
+/// struct Noise {
+///     rng: agb::rng::RandomNumberGenerator,
+///     samples_left: usize,
+/// }
+///
+/// impl SoundSource for Noise {
+///     fn fill(&mut self, buffer: &mut [i8]) -> usize {
+///         let count = buffer.len().min(self.samples_left);
+///         for sample in &mut buffer[..count] {
+///             *sample = (self.rng.gen() >> 24) as i8;
+///         }
+///
+///         self.samples_left -= count;
+///         count
+///     }
+/// }
+///
+/// let noise = Noise {
+///     rng: agb::rng::RandomNumberGenerator::new(),
+///     samples_left: 10512 / 2,
+/// };
+///
+/// let mut channel = SoundChannel::from_source(noise);
+/// channel.volume(agb::fixnum::num!(0.5));
+/// let _ = mixer.play_sound(channel);
+/// # }
+/// ```
+pub trait SoundSource {
+    /// Writes the next samples into `buffer`, returning how many were written.
+    /// This is called from [`Mixer::frame`](super::Mixer::frame), so should be
+    /// quick.
+    ///
+    /// Writing fewer than `buffer.len()` samples means the source has finished,
+    /// and it won't be asked for any more. The channel stops once the samples
+    /// it did write have played.
+    fn fill(&mut self, buffer: &mut [i8]) -> usize;
+}
+
+/// Holds the samples pulled from a [`SoundSource`] which haven't been played
+/// yet, because the mixer needs a few samples ahead of where it is playing.
+///
+/// The samples live in a ring buffer, so playing them just moves the start
+/// along. The mixer needs each frame's samples to be contiguous, so rather
+/// than wrapping around part way through a frame, the few samples which
+/// haven't been played jump back to the beginning once there isn't room after
+/// them. The buffer has plenty of space so this only happens every few frames.
+pub(super) struct SourceStream {
+    source: Box<dyn SoundSource>,
+    // stored as u16s so the stereo mixer can read 2 samples at a time
+    buffer: Vec<u16>,
+    start: usize,
+    end: usize,
+    source_finished: bool,
+}
+
+/// How many frames' worth of samples fit in the buffer
+const BUFFERED_FRAMES: usize = 4;
+
+impl SourceStream {
+    pub(super) fn new(source: Box<dyn SoundSource>) -> Self {
+        Self {
+            source,
+            buffer: Vec::new(),
+            start: 0,
+            end: 0,
+            source_finished: false,
+        }
+    }
+
+    /// Returns the next `count` samples, pulling more from the source if
+    /// needed. Anything past the end of the source is silent. The returned
+    /// slice is aligned to 2 bytes as long as only whole pairs of samples have
+    /// been consumed, which is always the case for stereo channels.
+    pub(super) fn fill_to(&mut self, count: usize) -> &[u8] {
+        if self.buffer.len() * 2 < count * BUFFERED_FRAMES {
+            self.buffer.resize((count * BUFFERED_FRAMES).div_ceil(2), 0);
+        }
+
+        if self.start + count > self.buffer.len() * 2 {
+            as_samples(&mut self.buffer).copy_within(self.start..self.end, 0);
+            self.end -= self.start;
+            self.start = 0;
+        }
+
+        let (start, end) = (self.start, self.start + count);
+        let samples = as_samples(&mut self.buffer);
+
+        if !self.source_finished && self.end < end {
+            let unfilled = &mut samples[self.end..end];
+            let written = self.source.fill(unfilled).min(unfilled.len());
+
+            self.source_finished = written < unfilled.len();
+            self.end += written;
+        }
+
+        samples[self.end.min(end)..end].fill(0);
+
+        // SAFETY: u8 and i8 have the same layout
+        unsafe { core::slice::from_raw_parts(samples[start..].as_ptr().cast(), count) }
+    }
+
+    /// Drops the first `count` samples, which have now been played.
+    pub(super) fn consume(&mut self, count: usize) {
+        self.start += count.min(self.end - self.start);
+
+        if self.start == self.end {
+            self.start = 0;
+            self.end = 0;
+        }
+    }
+
+    /// Whether the source has finished and every sample it wrote has played.
+    pub(super) fn is_finished(&self) -> bool {
+        self.source_finished && self.start == self.end
+    }
+}
+
+fn as_samples(buffer: &mut [u16]) -> &mut [i8] {
+    // SAFETY: i8 has a weaker alignment than u16, and the length is in bytes
+    unsafe { core::slice::from_raw_parts_mut(buffer.as_mut_ptr().cast(), buffer.len() * 2) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Counter {
+        next: i8,
+        end: i8,
+    }
+
+    impl SoundSource for Counter {
+        fn fill(&mut self, buffer: &mut [i8]) -> usize {
+            let mut written = 0;
+            for sample in buffer {
+                if self.next == self.end {
+                    break;
+                }
+
+                *sample = self.next;
+                self.next += 1;
+                written += 1;
+            }
+
+            written
+        }
+    }
+
+    #[test_case]
+    fn source_stream_keeps_unplayed_samples(_: &mut crate::Gba) {
+        let mut stream = SourceStream::new(Box::new(Counter { next: 0, end: 10 }));
+
+        assert_eq!(stream.fill_to(4), &[0, 1, 2, 3]);
+        stream.consume(3);
+        assert_eq!(stream.fill_to(5), &[3, 4, 5, 6, 7]);
+        stream.consume(5);
+        assert!(!stream.is_finished());
+
+        assert_eq!(stream.fill_to(4), &[8, 9, 0, 0]);
+        assert!(!stream.is_finished());
+        stream.consume(4);
+        assert!(stream.is_finished());
+    }
+
+    #[test_case]
+    fn source_stream_keeps_unplayed_samples_when_wrapping(_: &mut crate::Gba) {
+        let mut stream = SourceStream::new(Box::new(Counter { next: 0, end: 100 }));
+
+        // consuming fewer samples than are filled leaves some behind each
+        // frame, which have to move back to the start of the buffer
+        for frame in 0..20 {
+            let first = frame * 3;
+            assert_eq!(stream.fill_to(4), &[first, first + 1, first + 2, first + 3]);
+            stream.consume(3);
+        }
+    }
+}
@@ -1,13 +1,16 @@
 use core::cell::RefCell;
+use core::cmp::Reverse;
 use core::marker::PhantomData;
 
 use alloc::boxed::Box;
+use alloc::vec;
 use alloc::vec::Vec;
 use critical_section::{CriticalSection, Mutex};
 
+use super::effects::{Echo, EchoState};
 use super::hw::LeftOrRight;
 use super::{hw, Frequency};
-use super::{SoundChannel, SoundPriority};
+use super::{SoundChannel, VoiceStealing};
 
 use crate::InternalAllocator;
 use crate::{
@@ -107,11 +110,17 @@ pub struct Mixer<'gba> {
     _interrupt_handler: InterruptHandler,
 
     buffer: raw_box::RawBoxDrop<MixerBuffer, InternalAllocator>,
-    channels: [Option<SoundChannel>; 8],
-    indices: [i32; 8],
+    channels: Vec<Option<SoundChannel>>,
+    indices: Vec<i32>,
+    start_times: Vec<u32>,
+    sounds_started: u32,
+    voice_stealing: VoiceStealing,
     frequency: Frequency,
 
     working_buffer: Box<[Num<i16, 4>], InternalAllocator>,
+    // only allocated once a channel with a low pass filter plays
+    filter_buffer: Vec<Num<i16, 4>, InternalAllocator>,
+    echo: Option<EchoState>,
 
     fifo_timer: Timer,
 
@@ -133,7 +142,7 @@ pub struct Mixer<'gba> {
 /// # let mut mixer = gba.mixer.mixer(agb::sound::mixer::Frequency::Hz10512);
 /// # static MY_BGM: &[u8] = include_wav!("examples/sfx/my_bgm.wav");
 /// let mut channel = SoundChannel::new_high_priority(MY_BGM);
-/// let bgm_channel_id = mixer.play_sound(channel).unwrap(); // will always be Some if high priority
+/// let bgm_channel_id = mixer.play_sound(channel).unwrap();
 ///
 /// // Later, stop that particular channel
 /// mixer.channel(&bgm_channel_id).expect("Expected to still be playing").stop();
@@ -142,7 +151,7 @@ pub struct Mixer<'gba> {
 pub struct ChannelId(usize, i32);
 
 impl Mixer<'_> {
-    pub(super) fn new(frequency: Frequency) -> Self {
+    pub(super) fn new(frequency: Frequency, num_channels: usize) -> Self {
         let buffer =
             raw_box::RawBoxDrop::new(Box::new_in(MixerBuffer::new(frequency), InternalAllocator));
 
@@ -182,13 +191,18 @@ impl Mixer<'_> {
         Self {
             frequency,
             buffer,
-            channels: Default::default(),
-            indices: Default::default(),
+            channels: (0..num_channels).map(|_| None).collect(),
+            indices: vec![0; num_channels],
+            start_times: vec![0; num_channels],
+            sounds_started: 0,
+            voice_stealing: VoiceStealing::default(),
 
             interrupt_timer,
             _interrupt_handler: interrupt_handler,
 
             working_buffer: working_buffer.into_boxed_slice(),
+            filter_buffer: Vec::new_in(InternalAllocator),
+            echo: None,
             fifo_timer,
 
             phantom: PhantomData,
@@ -237,22 +251,33 @@ impl Mixer<'_> {
             return;
         }
 
-        self.buffer
-            .write_channels(&mut self.working_buffer, self.channels.iter_mut().flatten());
+        self.buffer.write_channels(
+            &mut self.working_buffer,
+            &mut self.filter_buffer,
+            self.echo.as_mut(),
+            self.channels.iter_mut().flatten(),
+        );
+    }
+
+    /// Turns on an [`Echo`] of everything the mixer plays, or turns it off if
+    /// `echo` is `None`. Changing the echo clears any echoes still playing.
+    ///
+    /// The echo costs CPU time every frame while it is on, but nothing when
+    /// it is off.
+    pub fn set_echo(&mut self, echo: Option<Echo>) {
+        self.echo = echo.map(|echo| EchoState::new(echo, self.frequency.frequency()));
     }
 
     /// Start playing a given [`SoundChannel`].
     ///
     /// Returns a [`ChannelId`] which you can later use to modify the playing sound.
     ///
-    /// Will first try to play the sound in an unused channel (of the 8 possible channels)
-    /// followed by overriding a low priority sound (if the sound channel being passed in
-    /// is high priority).
+    /// Will first try to play the sound in an unused channel, followed by replacing a
+    /// playing sound with a lower [`priority`](SoundChannel::priority). If there is more
+    /// than one it could replace, the mixer's [`VoiceStealing`] policy decides which.
     ///
-    /// Returns Some if the channel is now playing (which is guaranteed if the channel is
-    /// high priority) or None if it failed to find a slot.
-    ///
-    /// Panics if you try to play a high priority sound and there are no free channels.
+    /// Returns Some if the channel is now playing or None if every channel is playing
+    /// a sound with the same or higher priority.
     ///
     /// # Example
     ///
@@ -265,37 +290,71 @@ impl Mixer<'_> {
     /// # let mut mixer = gba.mixer.mixer(agb::sound::mixer::Frequency::Hz10512);
     /// # static MY_BGM: &[u8] = include_wav!("examples/sfx/my_bgm.wav");
     /// let mut channel = SoundChannel::new_high_priority(MY_BGM);
-    /// let bgm_channel_id = mixer.play_sound(channel).unwrap(); // will always be Some if high priority
+    /// let bgm_channel_id = mixer.play_sound(channel).unwrap();
     /// # }
     /// ```
     pub fn play_sound(&mut self, new_channel: SoundChannel) -> Option<ChannelId> {
-        for (i, channel) in self.channels.iter_mut().enumerate() {
-            if let Some(some_channel) = channel {
-                if !some_channel.is_done {
-                    continue;
-                }
-            }
+        let slot = self
+            .free_slot()
+            .or_else(|| self.slot_to_steal(new_channel.priority))?;
 
-            channel.replace(new_channel);
-            self.indices[i] += 1;
-            return Some(ChannelId(i, self.indices[i]));
-        }
+        self.channels[slot] = Some(new_channel);
+        self.indices[slot] += 1;
 
-        if new_channel.priority == SoundPriority::Low {
-            return None; // don't bother even playing it
-        }
+        self.sounds_started = self.sounds_started.wrapping_add(1);
+        self.start_times[slot] = self.sounds_started;
 
-        for (i, channel) in self.channels.iter_mut().enumerate() {
-            if channel.as_ref().unwrap().priority == SoundPriority::High {
-                continue;
-            }
+        Some(ChannelId(slot, self.indices[slot]))
+    }
 
-            channel.replace(new_channel);
-            self.indices[i] += 1;
-            return Some(ChannelId(i, self.indices[i]));
-        }
+    /// Sets how the mixer picks which sound to replace when every channel is in
+    /// use. Defaults to [`VoiceStealing::LowestPriority`].
+    pub fn set_voice_stealing(&mut self, voice_stealing: VoiceStealing) {
+        self.voice_stealing = voice_stealing;
+    }
+
+    /// The maximum number of sounds this mixer can play at once.
+    #[must_use]
+    pub fn num_channels(&self) -> usize {
+        self.channels.len()
+    }
 
-        panic!("Cannot play more than 8 sounds at once");
+    fn free_slot(&self) -> Option<usize> {
+        self.channels
+            .iter()
+            .position(|channel| !matches!(channel, Some(channel) if !channel.is_done))
+    }
+
+    fn slot_to_steal(&self, priority: u8) -> Option<usize> {
+        let age = |start_time: u32| self.sounds_started.wrapping_sub(start_time);
+
+        let candidates = self
+            .channels
+            .iter()
+            .zip(&self.start_times)
+            .enumerate()
+            .filter_map(|(i, (channel, &start_time))| {
+                let channel = channel.as_ref()?;
+                (channel.priority < priority).then_some((i, channel, age(start_time)))
+            });
+
+        let victim = match self.voice_stealing {
+            VoiceStealing::LowestPriority => {
+                candidates.min_by_key(|&(_, channel, age)| (channel.priority, Reverse(age)))
+            }
+            VoiceStealing::Oldest => candidates.max_by_key(|&(_, _, age)| age),
+            VoiceStealing::Quietest => candidates.min_by_key(|&(_, channel, age)| {
+                let volume = if channel.is_playing {
+                    channel.volume
+                } else {
+                    0.into()
+                };
+
+                (volume, Reverse(age))
+            }),
+        };
+
+        victim.map(|(i, _, _)| i)
     }
 
     /// Lets you modify an already playing channel.
@@ -314,7 +373,7 @@ impl Mixer<'_> {
     /// # let mut mixer = gba.mixer.mixer(agb::sound::mixer::Frequency::Hz10512);
     /// # static MY_BGM: &[u8] = include_wav!("examples/sfx/my_bgm.wav");
     /// let mut channel = SoundChannel::new_high_priority(MY_BGM);
-    /// let bgm_channel_id = mixer.play_sound(channel).unwrap(); // will always be Some if high priority
+    /// let bgm_channel_id = mixer.play_sound(channel).unwrap();
     ///
     /// // Later, stop that particular channel
     /// mixer.channel(&bgm_channel_id).expect("Expected still to be playing").stop();
@@ -417,27 +476,25 @@ impl MixerBuffer {
     fn write_channels<'a>(
         &self,
         working_buffer: &mut [Num<i16, 4>],
+        filter_buffer: &mut Vec<Num<i16, 4>, InternalAllocator>,
+        echo: Option<&mut EchoState>,
         channels: impl Iterator<Item = &'a mut SoundChannel>,
     ) {
         let mut channels = channels
             .filter(|channel| !channel.is_done && channel.volume != 0.into() && channel.is_playing);
 
         if let Some(channel) = channels.next() {
-            if channel.is_stereo {
-                self.write_stereo(channel, working_buffer, true);
-            } else {
-                self.write_mono(channel, working_buffer, true);
-            }
+            self.write_channel(channel, working_buffer, filter_buffer, true);
         } else {
             working_buffer.fill(0.into());
         }
 
         for channel in channels {
-            if channel.is_stereo {
-                self.write_stereo(channel, working_buffer, false);
-            } else {
-                self.write_mono(channel, working_buffer, false);
-            }
+            self.write_channel(channel, working_buffer, filter_buffer, false);
+        }
+
+        if let Some(echo) = echo {
+            echo.apply(working_buffer);
         }
 
         let write_buffer =
@@ -452,6 +509,50 @@ impl MixerBuffer {
         }
     }
 
+    fn write_channel(
+        &self,
+        channel: &mut SoundChannel,
+        working_buffer: &mut [Num<i16, 4>],
+        filter_buffer: &mut Vec<Num<i16, 4>, InternalAllocator>,
+        is_first: bool,
+    ) {
+        if channel.low_pass.is_none() {
+            self.write_unfiltered_channel(channel, working_buffer, is_first);
+            return;
+        }
+
+        // The filter needs the channel on its own, so mix it separately and add it in afterwards
+        filter_buffer.resize(working_buffer.len(), 0.into());
+        self.write_unfiltered_channel(channel, filter_buffer, true);
+
+        if let Some(low_pass) = &mut channel.low_pass {
+            low_pass.apply(filter_buffer);
+        }
+
+        if is_first {
+            working_buffer.copy_from_slice(filter_buffer);
+        } else {
+            for (output, &sample) in working_buffer.iter_mut().zip(filter_buffer.iter()) {
+                *output = Num::from_raw(output.to_raw().wrapping_add(sample.to_raw()));
+            }
+        }
+    }
+
+    fn write_unfiltered_channel(
+        &self,
+        channel: &mut SoundChannel,
+        working_buffer: &mut [Num<i16, 4>],
+        is_first: bool,
+    ) {
+        if channel.source.is_some() {
+            self.write_source(channel, working_buffer, is_first);
+        } else if channel.is_stereo {
+            self.write_stereo(channel, working_buffer, is_first);
+        } else {
+            self.write_mono(channel, working_buffer, is_first);
+        }
+    }
+
     fn write_stereo(
         &self,
         channel: &mut SoundChannel,
@@ -498,11 +599,7 @@ impl MixerBuffer {
         working_buffer: &mut [Num<i16, 4>],
         is_first: bool,
     ) {
-        let right_amount = ((channel.panning + 1) / 2) * channel.volume;
-        let left_amount = ((-channel.panning + 1) / 2) * channel.volume;
-
-        let right_amount: Num<i16, 4> = right_amount.change_base();
-        let left_amount: Num<i16, 4> = left_amount.change_base();
+        let mul_amount = mono_mul_amount(channel);
 
         let channel_len = Num::<u32, 8>::new(channel.data.len() as u32);
         let mut playback_speed = channel.playback_speed;
@@ -511,16 +608,7 @@ impl MixerBuffer {
             playback_speed -= channel_len;
         }
 
-        // SAFETY: always aligned correctly by construction
-        let working_buffer_i32: &mut [i32] = unsafe {
-            core::slice::from_raw_parts_mut(
-                working_buffer.as_mut_ptr().cast(),
-                working_buffer.len() / 2,
-            )
-        };
-
-        let mul_amount =
-            ((left_amount.to_raw() as i32) << 16) | (right_amount.to_raw() as i32 & 0x0000ffff);
+        let working_buffer_i32 = mono_working_buffer(working_buffer);
 
         macro_rules! call_mono_fn {
             ($fn_name:ident) => {
@@ -552,6 +640,103 @@ impl MixerBuffer {
             }
         }
     }
+
+    fn write_source(
+        &self,
+        channel: &mut SoundChannel,
+        working_buffer: &mut [Num<i16, 4>],
+        is_first: bool,
+    ) {
+        let buffer_size = self.frequency.buffer_size();
+        let mul_amount = mono_mul_amount(channel);
+
+        let Some(stream) = &mut channel.source else {
+            return;
+        };
+
+        if channel.is_stereo {
+            let data = stream.fill_to(2 * buffer_size);
+
+            unsafe {
+                if is_first {
+                    agb_rs__mixer_add_stereo_first(
+                        data.as_ptr(),
+                        working_buffer.as_mut_ptr(),
+                        channel.volume.change_base(),
+                        buffer_size,
+                    );
+                } else {
+                    agb_rs__mixer_add_stereo(
+                        data.as_ptr(),
+                        working_buffer.as_mut_ptr(),
+                        channel.volume.change_base(),
+                        buffer_size,
+                    );
+                }
+            }
+
+            stream.consume(2 * buffer_size);
+        } else {
+            // Pull enough that the next frame's position is still in the buffer
+            let needed =
+                (channel.pos + channel.playback_speed * buffer_size as u32).floor() as usize + 1;
+            let data = stream.fill_to(needed);
+
+            let working_buffer_i32 = mono_working_buffer(working_buffer);
+
+            let new_pos = unsafe {
+                if is_first {
+                    agb_rs__mixer_add_mono_first(
+                        data.as_ptr(),
+                        working_buffer_i32.as_mut_ptr(),
+                        working_buffer_i32.len(),
+                        0.into(),
+                        data.len(),
+                        channel.pos,
+                        channel.playback_speed,
+                        mul_amount,
+                    )
+                } else {
+                    agb_rs__mixer_add_mono(
+                        data.as_ptr(),
+                        working_buffer_i32.as_mut_ptr(),
+                        working_buffer_i32.len(),
+                        0.into(),
+                        data.len(),
+                        channel.pos,
+                        channel.playback_speed,
+                        mul_amount,
+                    )
+                }
+            };
+
+            let played = new_pos.floor();
+            stream.consume(played as usize);
+            channel.pos = new_pos - played;
+        }
+
+        channel.is_done = stream.is_finished();
+    }
+}
+
+fn mono_mul_amount(channel: &SoundChannel) -> i32 {
+    let right_amount = ((channel.panning + 1) / 2) * channel.volume;
+    let left_amount = ((-channel.panning + 1) / 2) * channel.volume;
+
+    let right_amount: Num<i16, 4> = right_amount.change_base();
+    let left_amount: Num<i16, 4> = left_amount.change_base();
+
+    ((left_amount.to_raw() as i32) << 16) | (right_amount.to_raw() as i32 & 0x0000ffff)
+}
+
+fn mono_working_buffer(working_buffer: &mut [Num<i16, 4>]) -> &mut [i32] {
+    // SAFETY: always aligned correctly by construction
+    unsafe {
+        core::slice::from_raw_parts_mut(
+            working_buffer.as_mut_ptr().cast(),
+            working_buffer.len() / 2,
+        )
+    }
 }
 
 mod raw_box {
@@ -586,10 +771,64 @@ mod raw_box {
 #[cfg(test)]
 mod test {
     use crate::fixnum::num;
-    use alloc::vec;
 
     use super::*;
 
+    static SAMPLE: &[u8] = &[0; 64];
+
+    #[test_case]
+    fn play_sound_returns_none_when_saturated(gba: &mut crate::Gba) {
+        let mut mixer = gba.mixer.mixer_with_channels(Frequency::Hz10512, 2);
+
+        assert_eq!(mixer.num_channels(), 2);
+        assert!(mixer
+            .play_sound(SoundChannel::new_high_priority(SAMPLE))
+            .is_some());
+        assert!(mixer
+            .play_sound(SoundChannel::new_high_priority(SAMPLE))
+            .is_some());
+        assert!(mixer
+            .play_sound(SoundChannel::new_high_priority(SAMPLE))
+            .is_none());
+        assert!(mixer.play_sound(SoundChannel::new(SAMPLE)).is_none());
+    }
+
+    #[test_case]
+    fn voice_stealing_picks_the_right_channel(gba: &mut crate::Gba) {
+        let mut mixer = gba.mixer.mixer_with_channels(Frequency::Hz10512, 3);
+
+        let mut quiet = SoundChannel::new(SAMPLE);
+        quiet.priority(20).volume(num!(0.25));
+        let mut oldest = SoundChannel::new(SAMPLE);
+        oldest.priority(10);
+        let mut lowest = SoundChannel::new(SAMPLE);
+        lowest.priority(5);
+
+        let oldest = mixer.play_sound(oldest).unwrap();
+        let quiet = mixer.play_sound(quiet).unwrap();
+        let lowest = mixer.play_sound(lowest).unwrap();
+
+        let new_channel = || {
+            let mut channel = SoundChannel::new(SAMPLE);
+            channel.priority(30);
+            channel
+        };
+
+        let stolen = mixer.play_sound(new_channel()).unwrap();
+        assert_eq!(stolen.0, lowest.0);
+
+        mixer.set_voice_stealing(VoiceStealing::Quietest);
+        let stolen = mixer.play_sound(new_channel()).unwrap();
+        assert_eq!(stolen.0, quiet.0);
+
+        mixer.set_voice_stealing(VoiceStealing::Oldest);
+        let stolen = mixer.play_sound(new_channel()).unwrap();
+        assert_eq!(stolen.0, oldest.0);
+
+        assert!(mixer.channel(&oldest).is_none());
+        assert!(mixer.play_sound(new_channel()).is_none());
+    }
+
     #[test_case]
     fn collapse_should_correctly_reduce_size_of_input(_: &mut crate::Gba) {
         #[repr(align(4))]
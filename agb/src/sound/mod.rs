@@ -1,13 +1,15 @@
 //! # Game Boy Advance audio
 //!
 //! The GBA has 2 different ways of producing sound, which agb has support for.
-//! You currently cannot use both at the same time, and currently there is no
-//! compile time prevention of using both, but you should either use the DMG
-//! which allows for Game Boy and Game Boy Color style sound effects, or the mixer
-//! which allows for more advanced sounds.
+//! The DMG allows for Game Boy and Game Boy Color style sound effects, and the
+//! mixer allows for more advanced sounds. They can be used at the same time,
+//! which lets chiptune style sound effects play without costing any CPU time
+//! for mixing.
 //!
-//! The [`dmg`](crate::sound::dmg) module is very rudimentary and doesn't support most of the possible
-//! sounds possible. However, it may be expanded in the future.
+//! The [`dmg`](crate::sound::dmg) module gives access to the 2 square wave channels, the wave
+//! channel and the noise channel, along with their volume and which speakers they play through.
+//! Use [`Sound::set_psg_volume`](crate::sound::dmg::Sound::set_psg_volume) to balance them against
+//! the mixer.
 //!
 //! The [`mixer`](crate::sound::mixer) module is high performance, and allows for playing wav files at
 //! various levels of quality. Check out the module documentation for more.
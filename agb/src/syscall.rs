@@ -1,9 +1,17 @@
+//! Safe wrappers around the functions provided by the Game Boy Advance BIOS.
+//!
+//! The functions for the BIOS's sound driver and for multiboot aren't wrapped,
+//! since agb provides its own mixer and multiboot support.
+
 use agb_fixnum::Vector2D;
+use alloc::{vec, vec::Vec};
+use bitflags::bitflags;
 use core::arch::asm;
 use core::mem::MaybeUninit;
 
-use crate::display::affine::AffineMatrixBackground;
+use crate::display::affine::{AffineMatrixBackground, AffineMatrixObject};
 use crate::fixnum::Num;
+use crate::memory_mapped::MemoryMapped;
 
 #[allow(non_snake_case)]
 const fn swi_map(thumb_id: u32) -> u32 {
@@ -14,6 +22,103 @@ const fn swi_map(thumb_id: u32) -> u32 {
     }
 }
 
+// Where SoftReset will jump to, 0 for ROM and anything else for EWRAM
+const SOFT_RESET_RETURN_ADDRESS_FLAG: MemoryMapped<u8> = unsafe { MemoryMapped::new(0x0300_7FFA) };
+
+fn prepare_for_soft_reset() {
+    // SoftReset clears the interrupt handler address, so an interrupt before the game has
+    // started up again would crash.
+    crate::interrupt::INTERRUPTS_ENABLED.set(0);
+
+    SOFT_RESET_RETURN_ADDRESS_FLAG.set(u8::from(cfg!(feature = "multiboot")));
+}
+
+/// Restarts the game from the beginning, as if the console had just been
+/// turned on but without showing the boot logo. Commonly triggered by holding
+/// A + B + START + SELECT.
+///
+/// Nothing is dropped, and memory is not cleared other than by the game
+/// starting up again. If you want to clear memory and reset the hardware
+/// registers, use [`soft_reset_with_ram_reset`].
+pub fn soft_reset() -> ! {
+    prepare_for_soft_reset();
+
+    unsafe {
+        asm!(
+            "swi {SWI}",
+            SWI = const { swi_map(0x00) },
+            options(noreturn)
+        );
+    }
+}
+
+/// Clears the given areas of memory and registers with [`register_ram_reset`],
+/// and then restarts the game with [`soft_reset`].
+///
+/// Unlike [`register_ram_reset`], this can clear work RAM since the game is
+/// never returned to.
+pub fn soft_reset_with_ram_reset(flags: RamResetFlags) -> ! {
+    prepare_for_soft_reset();
+
+    unsafe {
+        asm!(
+            "swi {RESET}",
+            "swi {SOFT_RESET}",
+            RESET = const { swi_map(0x01) },
+            SOFT_RESET = const { swi_map(0x00) },
+            in("r0") flags.bits(),
+            options(noreturn)
+        );
+    }
+}
+
+bitflags! {
+    /// The areas of memory and registers which can be cleared by [`register_ram_reset`].
+    #[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+    pub struct RamResetFlags: u32 {
+        /// The 256kb of external work RAM
+        const EWRAM = 1 << 0;
+        /// The internal work RAM, other than the last 512 bytes used by the BIOS
+        const IWRAM = 1 << 1;
+        /// The background and object palettes
+        const PALETTE = 1 << 2;
+        /// Video RAM
+        const VRAM = 1 << 3;
+        /// Object attribute memory. Note that this is cleared to zero, which
+        /// leaves every object visible.
+        const OAM = 1 << 4;
+        /// The serial communication registers
+        const SIO_REGISTERS = 1 << 5;
+        /// The sound registers
+        const SOUND_REGISTERS = 1 << 6;
+        /// Every other register
+        const OTHER_REGISTERS = 1 << 7;
+    }
+}
+
+/// Clears the given areas of memory and resets the given registers.
+///
+/// # Panics
+///
+/// Panics if asked to clear either [`EWRAM`](RamResetFlags::EWRAM) or
+/// [`IWRAM`](RamResetFlags::IWRAM), since those contain the heap, the stack and
+/// static variables. Use [`soft_reset_with_ram_reset`] to clear those.
+pub fn register_ram_reset(flags: RamResetFlags) {
+    assert!(
+        !flags.intersects(RamResetFlags::EWRAM | RamResetFlags::IWRAM),
+        "Cannot clear work RAM while the game is running"
+    );
+
+    unsafe {
+        asm!(
+            "swi {SWI}",
+            SWI = const { swi_map(0x01) },
+            in("r0") flags.bits(),
+            clobber_abi("C")
+        );
+    }
+}
+
 pub fn halt() {
     unsafe {
         asm!(
@@ -127,7 +232,7 @@ pub fn arc_tan2(x: i16, y: i32) -> i16 {
     unsafe {
         asm!(
             "swi {SWI}",
-            SWI = const { swi_map(0x09) },
+            SWI = const { swi_map(0x0A) },
             in("r0") x,
             in("r1") y,
             lateout("r0") result,
@@ -138,6 +243,327 @@ pub fn arc_tan2(x: i16, y: i32) -> i16 {
     result
 }
 
+fn cpu_set(source: *const u8, dest: *mut u8, control: u32) {
+    unsafe {
+        asm!(
+            "swi {SWI}",
+            SWI = const { swi_map(0x0B) },
+            in("r0") source,
+            in("r1") dest,
+            in("r2") control,
+            clobber_abi("C")
+        );
+    }
+}
+
+fn cpu_fast_set(source: *const u8, dest: *mut u8, control: u32) {
+    unsafe {
+        asm!(
+            "swi {SWI}",
+            SWI = const { swi_map(0x0C) },
+            in("r0") source,
+            in("r1") dest,
+            in("r2") control,
+            clobber_abi("C")
+        );
+    }
+}
+
+const CPU_SET_MAX_LEN: usize = (1 << 21) - 1;
+const CPU_SET_FILL: u32 = 1 << 24;
+const CPU_SET_32BIT: u32 = 1 << 26;
+
+/// Copies `source` into `dest` 16 bits at a time, so works with video RAM.
+///
+/// # Panics
+///
+/// Panics if the slices have different lengths, or have more than 2^21 - 1 elements.
+pub fn cpu_set_copy16(source: &[u16], dest: &mut [u16]) {
+    assert_eq!(source.len(), dest.len(), "Slices must be the same length");
+    assert!(source.len() <= CPU_SET_MAX_LEN, "Too many elements to copy");
+
+    cpu_set(
+        source.as_ptr().cast(),
+        dest.as_mut_ptr().cast(),
+        source.len() as u32,
+    );
+}
+
+/// Copies `source` into `dest` 32 bits at a time.
+///
+/// # Panics
+///
+/// Panics if the slices have different lengths, or have more than 2^21 - 1 elements.
+pub fn cpu_set_copy32(source: &[u32], dest: &mut [u32]) {
+    assert_eq!(source.len(), dest.len(), "Slices must be the same length");
+    assert!(source.len() <= CPU_SET_MAX_LEN, "Too many elements to copy");
+
+    cpu_set(
+        source.as_ptr().cast(),
+        dest.as_mut_ptr().cast(),
+        source.len() as u32 | CPU_SET_32BIT,
+    );
+}
+
+/// Fills `dest` with `value` 16 bits at a time, so works with video RAM.
+///
+/// # Panics
+///
+/// Panics if `dest` has more than 2^21 - 1 elements.
+pub fn cpu_set_fill16(value: u16, dest: &mut [u16]) {
+    assert!(dest.len() <= CPU_SET_MAX_LEN, "Too many elements to fill");
+
+    cpu_set(
+        (&value as *const u16).cast(),
+        dest.as_mut_ptr().cast(),
+        dest.len() as u32 | CPU_SET_FILL,
+    );
+}
+
+/// Fills `dest` with `value` 32 bits at a time.
+///
+/// # Panics
+///
+/// Panics if `dest` has more than 2^21 - 1 elements.
+pub fn cpu_set_fill32(value: u32, dest: &mut [u32]) {
+    assert!(dest.len() <= CPU_SET_MAX_LEN, "Too many elements to fill");
+
+    cpu_set(
+        (&value as *const u32).cast(),
+        dest.as_mut_ptr().cast(),
+        dest.len() as u32 | CPU_SET_FILL | CPU_SET_32BIT,
+    );
+}
+
+/// Copies `source` into `dest` 8 words at a time, which is faster than
+/// [`cpu_set_copy32`] but requires the length to be a multiple of 8.
+///
+/// # Panics
+///
+/// Panics if the slices have different lengths, if the length isn't a multiple
+/// of 8 or is more than 2^21 - 8.
+pub fn cpu_fast_set_copy(source: &[u32], dest: &mut [u32]) {
+    assert_eq!(source.len(), dest.len(), "Slices must be the same length");
+    assert_eq!(source.len() % 8, 0, "Length must be a multiple of 8");
+    assert!(source.len() <= CPU_SET_MAX_LEN, "Too many elements to copy");
+
+    cpu_fast_set(
+        source.as_ptr().cast(),
+        dest.as_mut_ptr().cast(),
+        source.len() as u32,
+    );
+}
+
+/// Fills `dest` with `value` 8 words at a time, which is faster than
+/// [`cpu_set_fill32`] but requires the length to be a multiple of 8.
+///
+/// # Panics
+///
+/// Panics if the length of `dest` isn't a multiple of 8 or is more than 2^21 - 8.
+pub fn cpu_fast_set_fill(value: u32, dest: &mut [u32]) {
+    assert_eq!(dest.len() % 8, 0, "Length must be a multiple of 8");
+    assert!(dest.len() <= CPU_SET_MAX_LEN, "Too many elements to fill");
+
+    cpu_fast_set(
+        (&value as *const u32).cast(),
+        dest.as_mut_ptr().cast(),
+        dest.len() as u32 | CPU_SET_FILL,
+    );
+}
+
+/// Returns the checksum of the BIOS, which is `0xBAAE187F` on the Game Boy
+/// Advance and `0xBAAE1880` on the Nintendo DS.
+#[must_use]
+pub fn bios_checksum() -> u32 {
+    let checksum: u32;
+    unsafe {
+        asm!(
+            "swi {SWI}",
+            SWI = const { swi_map(0x0D) },
+            lateout("r0") checksum,
+            clobber_abi("C")
+        );
+    }
+    checksum
+}
+
+const COMPRESSION_LZ77: u8 = 1;
+const COMPRESSION_HUFFMAN: u8 = 2;
+const COMPRESSION_RUN_LENGTH: u8 = 3;
+
+/// Returns the size in bytes of the data once decompressed, as stored in the
+/// header of data compressed for the BIOS, such as that produced by the
+/// `compress` option of the include macros.
+///
+/// # Panics
+///
+/// Panics if `compressed` is too short to contain a header.
+#[must_use]
+pub fn decompressed_len(compressed: &[u8]) -> usize {
+    assert!(compressed.len() >= 4, "Compressed data must have a header");
+    u32::from_le_bytes([compressed[1], compressed[2], compressed[3], 0]) as usize
+}
+
+fn compression_type(compressed: &[u8]) -> u8 {
+    assert!(compressed.len() >= 4, "Compressed data must have a header");
+    compressed[0] >> 4
+}
+
+fn check_compressed(compressed: &[u8], expected_type: u8, destination_len: usize) {
+    assert_eq!(
+        compressed.as_ptr() as usize % 4,
+        0,
+        "Compressed data must be word aligned"
+    );
+    assert_eq!(
+        compression_type(compressed),
+        expected_type,
+        "Data was compressed with a different compression type"
+    );
+    assert!(
+        decompressed_len(compressed) <= destination_len,
+        "Destination is too small to decompress into"
+    );
+}
+
+macro_rules! decompress_syscall {
+    ($id: literal, $src: expr, $dest: expr) => {
+        unsafe {
+            asm!(
+                "swi {SWI}",
+                SWI = const { swi_map($id) },
+                in("r0") $src.as_ptr(),
+                in("r1") $dest.as_mut_ptr(),
+                clobber_abi("C")
+            );
+        }
+    };
+}
+
+/// Decompresses LZ77 compressed data, writing a byte at a time. Use this for
+/// destinations in work RAM, and [`lz77_uncomp_vram`] for video RAM.
+///
+/// # Panics
+///
+/// Panics if the data isn't word aligned LZ77 compressed data, or if `dest` is
+/// too small to hold the decompressed data.
+pub fn lz77_uncomp_wram(compressed: &[u8], dest: &mut [u8]) {
+    check_compressed(compressed, COMPRESSION_LZ77, dest.len());
+    decompress_syscall!(0x11, compressed, dest);
+}
+
+/// Decompresses LZ77 compressed data, writing 16 bits at a time so it is safe
+/// to use with video RAM.
+///
+/// The compressed data must not copy from the byte immediately before the one
+/// being written, which is true of data compressed by the include macros.
+///
+/// # Panics
+///
+/// Panics if the data isn't word aligned LZ77 compressed data, or if `dest` is
+/// too small to hold the decompressed data.
+pub fn lz77_uncomp_vram(compressed: &[u8], dest: &mut [u16]) {
+    check_compressed(compressed, COMPRESSION_LZ77, dest.len() * 2);
+    decompress_syscall!(0x12, compressed, dest);
+}
+
+/// Decompresses run length encoded data, writing a byte at a time. Use this for
+/// destinations in work RAM, and [`rl_uncomp_vram`] for video RAM.
+///
+/// # Panics
+///
+/// Panics if the data isn't word aligned run length encoded data, or if `dest`
+/// is too small to hold the decompressed data.
+pub fn rl_uncomp_wram(compressed: &[u8], dest: &mut [u8]) {
+    check_compressed(compressed, COMPRESSION_RUN_LENGTH, dest.len());
+    decompress_syscall!(0x14, compressed, dest);
+}
+
+/// Decompresses run length encoded data, writing 16 bits at a time so it is
+/// safe to use with video RAM.
+///
+/// # Panics
+///
+/// Panics if the data isn't word aligned run length encoded data, or if `dest`
+/// is too small to hold the decompressed data.
+pub fn rl_uncomp_vram(compressed: &[u8], dest: &mut [u16]) {
+    check_compressed(compressed, COMPRESSION_RUN_LENGTH, dest.len() * 2);
+    decompress_syscall!(0x15, compressed, dest);
+}
+
+/// Decompresses Huffman coded data. This writes 32 bits at a time, so works
+/// for both work RAM and video RAM.
+///
+/// # Panics
+///
+/// Panics if the data isn't word aligned Huffman coded data, or if `dest` is
+/// too small to hold the decompressed data.
+pub fn huff_uncomp(compressed: &[u8], dest: &mut [u32]) {
+    check_compressed(compressed, COMPRESSION_HUFFMAN, dest.len() * 4);
+    decompress_syscall!(0x13, compressed, dest);
+}
+
+/// Decompresses data compressed with any of the formats supported by the BIOS
+/// into a newly allocated buffer.
+///
+/// # Panics
+///
+/// Panics if the data isn't word aligned compressed data.
+#[must_use]
+pub fn decompress(compressed: &[u8]) -> Vec<u8> {
+    let len = decompressed_len(compressed);
+
+    match compression_type(compressed) {
+        COMPRESSION_LZ77 => {
+            let mut output = vec![0; len];
+            lz77_uncomp_wram(compressed, &mut output);
+            output
+        }
+        COMPRESSION_RUN_LENGTH => {
+            let mut output = vec![0; len];
+            rl_uncomp_wram(compressed, &mut output);
+            output
+        }
+        COMPRESSION_HUFFMAN => {
+            // Huffman decompression needs a word aligned destination
+            let mut words = vec![0u32; len.div_ceil(4)];
+            huff_uncomp(compressed, &mut words);
+
+            words
+                .into_iter()
+                .flat_map(u32::to_le_bytes)
+                .take(len)
+                .collect()
+        }
+        unknown => panic!("Unknown compression type {unknown}"),
+    }
+}
+
+/// Decompresses data compressed with any of the formats supported by the BIOS
+/// into memory which can't be written a byte at a time, such as video RAM.
+///
+/// # Panics
+///
+/// Panics if the data isn't word aligned compressed data, or if `dest` is too
+/// small to hold the decompressed data.
+pub fn decompress_vram(compressed: &[u8], dest: &mut [u32]) {
+    match compression_type(compressed) {
+        COMPRESSION_HUFFMAN => huff_uncomp(compressed, dest),
+        compression => {
+            // SAFETY: u16 has a lower alignment than u32, and the new slice covers the same memory
+            let dest = unsafe {
+                core::slice::from_raw_parts_mut(dest.as_mut_ptr().cast::<u16>(), dest.len() * 2)
+            };
+
+            match compression {
+                COMPRESSION_LZ77 => lz77_uncomp_vram(compressed, dest),
+                COMPRESSION_RUN_LENGTH => rl_uncomp_vram(compressed, dest),
+                unknown => panic!("Unknown compression type {unknown}"),
+            }
+        }
+    }
+}
+
 /// `rotation` is in revolutions. It is hard to create the rotation, usually
 /// you'll go in from a larger sized type.
 #[must_use]
@@ -185,6 +611,216 @@ pub(crate) fn bg_affine_matrix(
     unsafe { output.assume_init() }
 }
 
+/// The scale and rotation of an object, used to calculate its affine matrix
+/// with [`obj_affine_set`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct ObjAffineSource {
+    scale_x: Num<i16, 8>,
+    scale_y: Num<i16, 8>,
+    rotation: Num<u16, 16>,
+    _padding: u16,
+}
+
+impl ObjAffineSource {
+    /// `rotation` is in revolutions, anticlockwise.
+    #[must_use]
+    pub fn new(scale: impl Into<Vector2D<Num<i16, 8>>>, rotation: Num<u16, 16>) -> Self {
+        let scale = scale.into();
+
+        Self {
+            scale_x: scale.x,
+            scale_y: scale.y,
+            rotation,
+            _padding: 0,
+        }
+    }
+}
+
+/// Calculates the affine matrices for many objects at once.
+///
+/// # Panics
+///
+/// Panics if `sources` and `dest` have different lengths.
+pub fn obj_affine_set(sources: &[ObjAffineSource], dest: &mut [AffineMatrixObject]) {
+    assert_eq!(sources.len(), dest.len(), "Slices must be the same length");
+
+    unsafe {
+        asm!(
+            "swi {SWI}",
+            SWI = const { swi_map(0x0F) },
+            in("r0") sources.as_ptr(),
+            in("r1") dest.as_mut_ptr(),
+            in("r2") sources.len(),
+            // the distance in bytes between each entry of the matrix
+            in("r3") 2,
+            clobber_abi("C")
+        );
+    }
+}
+
+/// How to unpack data with [`bit_unpack`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BitUnPackOptions {
+    /// The number of bits in each source value, either 1, 2, 4 or 8.
+    pub source_width: u8,
+    /// The number of bits in each unpacked value, either 1, 2, 4, 8, 16 or 32.
+    /// Must be at least `source_width`.
+    pub destination_width: u8,
+    /// Added to every non zero source value. Must be less than 2^31.
+    pub offset: u32,
+    /// Whether `offset` should be added to source values which are zero too.
+    pub offset_zero: bool,
+}
+
+#[repr(C)]
+struct BitUnPackInfo {
+    source_length: u16,
+    source_width: u8,
+    destination_width: u8,
+    offset: u32,
+}
+
+/// Unpacks values of one bit width to a wider one, such as expanding a 1 bit
+/// per pixel font into 4 bit per pixel tiles. Values are unpacked starting
+/// from the least significant bits.
+///
+/// # Panics
+///
+/// Panics if the options are invalid, if `source` is longer than 65535 bytes,
+/// or if `dest` is too small to hold the unpacked data.
+pub fn bit_unpack(source: &[u8], dest: &mut [u32], options: BitUnPackOptions) {
+    assert!(
+        matches!(options.source_width, 1 | 2 | 4 | 8),
+        "Source width must be 1, 2, 4 or 8"
+    );
+    assert!(
+        matches!(options.destination_width, 1 | 2 | 4 | 8 | 16 | 32),
+        "Destination width must be 1, 2, 4, 8, 16 or 32"
+    );
+    assert!(
+        options.destination_width >= options.source_width,
+        "Destination width must be at least the source width"
+    );
+    assert!(options.offset < 1 << 31, "Offset must be less than 2^31");
+
+    let source_length = u16::try_from(source.len()).expect("Source too long");
+    let unpacked_bits =
+        source.len() * 8 / options.source_width as usize * options.destination_width as usize;
+    assert!(
+        unpacked_bits <= dest.len() * 32,
+        "Destination is too small to unpack into"
+    );
+
+    let info = BitUnPackInfo {
+        source_length,
+        source_width: options.source_width,
+        destination_width: options.destination_width,
+        offset: options.offset | (u32::from(options.offset_zero) << 31),
+    };
+
+    unsafe {
+        asm!(
+            "swi {SWI}",
+            SWI = const { swi_map(0x10) },
+            in("r0") source.as_ptr(),
+            in("r1") dest.as_mut_ptr(),
+            in("r2") &info as *const BitUnPackInfo,
+            clobber_abi("C")
+        );
+    }
+}
+
+const DIFF_FILTER: u8 = 8;
+
+fn check_diff_filtered(filtered: &[u8], unit_size: u8, destination_len: usize) {
+    check_compressed(filtered, DIFF_FILTER, destination_len);
+    assert_eq!(
+        filtered[0] & 0xf,
+        unit_size,
+        "Data was filtered with a different unit size"
+    );
+}
+
+/// Reverses a filter where each byte is stored as the difference from the
+/// previous one, writing a byte at a time. The data should start with the same
+/// header as compressed data.
+///
+/// # Panics
+///
+/// Panics if the data isn't word aligned 8 bit difference filtered data, or if
+/// `dest` is too small.
+pub fn diff_8bit_unfilter_wram(filtered: &[u8], dest: &mut [u8]) {
+    check_diff_filtered(filtered, 1, dest.len());
+    decompress_syscall!(0x16, filtered, dest);
+}
+
+/// Reverses a filter where each byte is stored as the difference from the
+/// previous one, writing 16 bits at a time so it is safe to use with video RAM.
+///
+/// # Panics
+///
+/// Panics if the data isn't word aligned 8 bit difference filtered data, or if
+/// `dest` is too small.
+pub fn diff_8bit_unfilter_vram(filtered: &[u8], dest: &mut [u16]) {
+    check_diff_filtered(filtered, 1, dest.len() * 2);
+    decompress_syscall!(0x17, filtered, dest);
+}
+
+/// Reverses a filter where each 16 bit value is stored as the difference from
+/// the previous one.
+///
+/// # Panics
+///
+/// Panics if the data isn't word aligned 16 bit difference filtered data, or if
+/// `dest` is too small.
+pub fn diff_16bit_unfilter(filtered: &[u8], dest: &mut [u16]) {
+    check_diff_filtered(filtered, 2, dest.len() * 2);
+    decompress_syscall!(0x18, filtered, dest);
+}
+
+/// Calculates the playback frequency of a sample recorded at
+/// `sample_frequency` to play it at the given midi key with a fine adjustment
+/// in 1/256ths of a semitone. The result is in the same units as `sample_frequency`,
+/// and is `sample_frequency * 2^((180 - key - fine_adjust / 256) / 12)`.
+///
+/// So a key of 180 plays the sample at its original frequency, and a key of 168
+/// plays it an octave higher.
+#[must_use]
+pub fn midi_key_to_freq(sample_frequency: u32, key: u8, fine_adjust: u8) -> u32 {
+    // Only the frequency of the sound driver's wave data is read
+    #[repr(C)]
+    struct WaveData {
+        kind: u16,
+        status: u16,
+        frequency: u32,
+        loop_start: u32,
+        size: u32,
+    }
+
+    let wave_data = WaveData {
+        kind: 0,
+        status: 0,
+        frequency: sample_frequency,
+        loop_start: 0,
+        size: 0,
+    };
+
+    let result: u32;
+    unsafe {
+        asm!(
+            "swi {SWI}",
+            SWI = const { swi_map(0x1F) },
+            in("r0") &wave_data as *const WaveData,
+            in("r1") u32::from(key),
+            in("r2") u32::from(fine_adjust),
+            lateout("r0") result,
+            clobber_abi("C")
+        );
+    }
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use crate::display::affine::AffineMatrix;
@@ -204,4 +840,161 @@ mod tests {
         let matrix = aff.to_affine_matrix();
         assert_eq!(matrix, AffineMatrix::identity());
     }
+
+    #[test_case]
+    fn obj_affine_identity(_gba: &mut crate::Gba) {
+        let sources = [ObjAffineSource::new((1i16, 1i16), Num::from_raw(0))];
+        let mut dest =
+            [AffineMatrix::from_rotation(Num::<i32, 8>::new(1) / 8).to_object_wrapping()];
+
+        obj_affine_set(&sources, &mut dest);
+
+        assert_eq!(dest[0], AffineMatrix::identity().to_object_wrapping());
+    }
+
+    #[test_case]
+    fn cpu_set_copies_and_fills(_gba: &mut crate::Gba) {
+        let source: Vec<u32> = (0..16).collect();
+
+        let mut dest = [0u32; 16];
+        cpu_set_copy32(&source, &mut dest);
+        assert_eq!(dest[..], source[..]);
+
+        let mut dest = [0u32; 16];
+        cpu_fast_set_copy(&source, &mut dest);
+        assert_eq!(dest[..], source[..]);
+
+        cpu_fast_set_fill(0x1234_5678, &mut dest);
+        assert_eq!(dest, [0x1234_5678; 16]);
+
+        cpu_set_fill32(7, &mut dest[..3]);
+        assert_eq!(dest[..4], [7, 7, 7, 0x1234_5678]);
+
+        let mut halfwords = [0u16; 5];
+        cpu_set_fill16(0xabcd, &mut halfwords);
+        assert_eq!(halfwords, [0xabcd; 5]);
+
+        cpu_set_copy16(&[1, 2, 3], &mut halfwords[1..4]);
+        assert_eq!(halfwords, [0xabcd, 1, 2, 3, 0xabcd]);
+    }
+
+    #[test_case]
+    fn checksum_is_gba_bios(_gba: &mut crate::Gba) {
+        assert_eq!(bios_checksum(), 0xBAAE_187F);
+    }
+
+    #[test_case]
+    fn arc_tan2_gives_the_angle_of_the_point(_gba: &mut crate::Gba) {
+        // a full turn is 0x10000, so straight up is a quarter of that
+        assert_eq!(arc_tan2(0x4000, 0), 0);
+        assert_eq!(arc_tan2(0, 0x4000), 0x4000);
+    }
+
+    #[test_case]
+    fn bit_unpack_1bpp_to_4bpp(_gba: &mut crate::Gba) {
+        let mut dest = [0u32; 2];
+
+        bit_unpack(
+            &[0b1000_0101, 0xff],
+            &mut dest,
+            BitUnPackOptions {
+                source_width: 1,
+                destination_width: 4,
+                offset: 2,
+                offset_zero: false,
+            },
+        );
+
+        assert_eq!(dest, [0x3000_0303, 0x3333_3333]);
+    }
+
+    #[test_case]
+    fn register_ram_reset_clears_palette(_gba: &mut crate::Gba) {
+        let palette = unsafe { MemoryMapped::<u16>::new(0x0500_0002) };
+        palette.set(0x7fff);
+
+        register_ram_reset(RamResetFlags::PALETTE);
+
+        assert_eq!(palette.get(), 0);
+    }
+
+    #[test_case]
+    fn midi_key_frequencies(_gba: &mut crate::Gba) {
+        assert_eq!(midi_key_to_freq(10_000, 180, 0), 10_000);
+
+        let octave_up = midi_key_to_freq(10_000, 168, 0);
+        assert!((19_800..=20_200).contains(&octave_up), "{octave_up}");
+    }
+
+    #[repr(align(4))]
+    struct Aligned<const N: usize>([u8; N]);
+
+    const UNCOMPRESSED: &[u8] = b"agb agb agb agb, the rusty game boy advance library!!!!!!!!";
+
+    static LZ77: Aligned<56> = Aligned([
+        16, 59, 0, 0, 8, 97, 103, 98, 32, 128, 3, 44, 32, 116, 0, 104, 101, 32, 114, 117, 115, 116,
+        121, 0, 32, 103, 97, 109, 101, 32, 98, 111, 0, 121, 32, 97, 100, 118, 97, 110, 99, 0, 101,
+        32, 108, 105, 98, 114, 97, 114, 16, 121, 33, 33, 48, 1,
+    ]);
+
+    static RUN_LENGTH: Aligned<60> = Aligned([
+        48, 59, 0, 0, 50, 97, 103, 98, 32, 97, 103, 98, 32, 97, 103, 98, 32, 97, 103, 98, 44, 32,
+        116, 104, 101, 32, 114, 117, 115, 116, 121, 32, 103, 97, 109, 101, 32, 98, 111, 121, 32,
+        97, 100, 118, 97, 110, 99, 101, 32, 108, 105, 98, 114, 97, 114, 121, 133, 33, 0, 0,
+    ]);
+
+    static HUFFMAN: Aligned<80> = Aligned([
+        36, 59, 0, 0, 13, 0, 128, 129, 2, 65, 6, 129, 1, 1, 7, 65, 1, 194, 130, 0, 194, 195, 4, 5,
+        9, 194, 8, 13, 14, 15, 3, 12, 104, 199, 139, 118, 199, 139, 118, 188, 86, 60, 187, 104,
+        151, 225, 93, 66, 222, 220, 202, 186, 188, 203, 200, 105, 28, 111, 238, 36, 234, 74, 206,
+        170, 34, 119, 231, 93, 198, 204, 141, 51, 25, 99, 140, 49, 0, 0, 0, 254,
+    ]);
+
+    #[test_case]
+    fn decompress_to_wram(_gba: &mut crate::Gba) {
+        let mut output = [0u8; UNCOMPRESSED.len()];
+        lz77_uncomp_wram(&LZ77.0, &mut output);
+        assert_eq!(output, UNCOMPRESSED);
+
+        let mut output = [0u8; UNCOMPRESSED.len()];
+        rl_uncomp_wram(&RUN_LENGTH.0, &mut output);
+        assert_eq!(output, UNCOMPRESSED);
+    }
+
+    #[test_case]
+    fn decompress_to_vram(_gba: &mut crate::Gba) {
+        for compressed in [&LZ77.0[..], &RUN_LENGTH.0[..], &HUFFMAN.0[..]] {
+            let mut output = [0u32; UNCOMPRESSED.len().div_ceil(4)];
+            decompress_vram(compressed, &mut output);
+
+            let bytes: Vec<u8> = output.into_iter().flat_map(u32::to_le_bytes).collect();
+            assert_eq!(&bytes[..UNCOMPRESSED.len()], UNCOMPRESSED);
+        }
+    }
+
+    #[test_case]
+    fn decompress_any_format(_gba: &mut crate::Gba) {
+        for compressed in [&LZ77.0[..], &RUN_LENGTH.0[..], &HUFFMAN.0[..]] {
+            assert_eq!(decompressed_len(compressed), UNCOMPRESSED.len());
+            assert_eq!(decompress(compressed), UNCOMPRESSED);
+        }
+    }
+
+    static DIFF_8BIT: Aligned<8> = Aligned([0x81, 4, 0, 0, 1, 1, 1, 1]);
+    static DIFF_16BIT: Aligned<12> = Aligned([0x82, 8, 0, 0, 1, 0, 1, 0, 1, 0, 1, 0]);
+
+    #[test_case]
+    fn diff_unfilter(_gba: &mut crate::Gba) {
+        let mut bytes = [0u8; 4];
+        diff_8bit_unfilter_wram(&DIFF_8BIT.0, &mut bytes);
+        assert_eq!(bytes, [1, 2, 3, 4]);
+
+        let mut halfwords = [0u16; 2];
+        diff_8bit_unfilter_vram(&DIFF_8BIT.0, &mut halfwords);
+        assert_eq!(halfwords, [0x0201, 0x0403]);
+
+        let mut halfwords = [0u16; 4];
+        diff_16bit_unfilter(&DIFF_16BIT.0, &mut halfwords);
+        assert_eq!(halfwords, [1, 2, 3, 4]);
+    }
 }
@@ -10,6 +10,23 @@ const fn timer_control(timer: usize) -> MemoryMapped<u16> {
     unsafe { MemoryMapped::new(0x0400_0102 + 4 * timer) }
 }
 
+/// Reads timers 2 and 3 as a single 32 bit counter. Only meaningful if timer 3
+/// is set to cascade from timer 2.
+pub(crate) fn cascaded_timer_value() -> u32 {
+    let low = timer_data(2);
+    let high = timer_data(3);
+
+    loop {
+        let high_before = high.get();
+        let low_value = low.get();
+
+        // if timer 2 overflowed between the reads, then try again
+        if high.get() == high_before {
+            return (u32::from(high_before) << 16) | u32::from(low_value);
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub enum Divider {
     // 16.78MHz or 59.59ns
@@ -0,0 +1,465 @@
+//! Measure how much of each frame is spent in named sections of your code.
+//!
+//! Create a [`CpuMeter`] using timers 2 and 3, and then wrap the code you want
+//! to measure in a [`scope`]. The time spent in each scope is accumulated over
+//! the frame, and calling [`CpuMeter::frame`] once per frame records the
+//! minimum, average and maximum number of cycles each section took.
+//!
+//! A frame on the Game Boy Advance is [`CYCLES_PER_FRAME`] cycles long, and the
+//! vblank period starts after [`CYCLES_PER_VDRAW`] of those.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(mut gba: agb::Gba) {
+//! use agb::timing::{self, CpuMeter};
+//!
+//! let mut timers = gba.timers.timers();
+//! let mut meter = CpuMeter::new(&mut timers);
+//! let vblank = agb::interrupt::VBlank::get();
+//!
+//! loop {
+//!     {
+//!         let _g = timing::scope("physics");
+//!         // physics update goes here
+//!     }
+//!
+//!     {
+//!         let _g = timing::scope("enemies");
+//!         // enemy update goes here
+//!     }
+//!
+//!     meter.frame();
+//!     if meter.frames() == 600 {
+//!         meter.report();
+//!         meter.reset();
+//!     }
+//!
+//!     vblank.wait_for_vblank();
+//! }
+//! # }
+//! ```
+
+use core::cell::RefCell;
+
+use alloc::vec::Vec;
+use critical_section::Mutex;
+
+use crate::{
+    debug_output::{self, Backend},
+    memory_mapped::MemoryMapped,
+    mgba::DebugLevel,
+    timer::{self, Divider, Timer, Timers},
+};
+
+/// The number of cycles in a single frame including vblank.
+pub const CYCLES_PER_FRAME: u32 = 280_896;
+/// The number of cycles between the start of the frame and the start of vblank.
+pub const CYCLES_PER_VDRAW: u32 = 197_120;
+
+const BACKDROP_COLOUR: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0500_0000) };
+
+static TIMING_STATE: Mutex<RefCell<Option<TimingState>>> = Mutex::new(RefCell::new(None));
+
+struct TimingState {
+    sections: Vec<Section>,
+    raster_bars: bool,
+}
+
+struct Section {
+    name: &'static str,
+    this_frame: u32,
+    was_run_this_frame: bool,
+    stats: Stats,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Stats {
+    min: u32,
+    max: u32,
+    last: u32,
+    total: u64,
+    frames: u32,
+}
+
+impl Stats {
+    fn record(&mut self, cycles: u32) {
+        if self.frames == 0 {
+            self.min = cycles;
+            self.max = cycles;
+        } else {
+            self.min = self.min.min(cycles);
+            self.max = self.max.max(cycles);
+        }
+
+        self.last = cycles;
+        self.total += u64::from(cycles);
+        self.frames += 1;
+    }
+
+    fn timing(&self, name: &'static str) -> SectionTiming {
+        SectionTiming {
+            name,
+            min: self.min,
+            max: self.max,
+            average: self.total.checked_div(u64::from(self.frames)).unwrap_or(0) as u32,
+            last: self.last,
+            frames: self.frames,
+        }
+    }
+}
+
+/// The accumulated timing information for a single section.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SectionTiming {
+    /// The name passed to [`scope`], or `"frame"` for the whole frame.
+    pub name: &'static str,
+    /// The fewest cycles spent in this section in a single frame.
+    pub min: u32,
+    /// The most cycles spent in this section in a single frame.
+    pub max: u32,
+    /// The average number of cycles spent in this section per frame.
+    pub average: u32,
+    /// The number of cycles spent in this section in the most recent frame.
+    pub last: u32,
+    /// The number of frames in which this section was run.
+    pub frames: u32,
+}
+
+impl SectionTiming {
+    /// The average time spent in this section as a fraction of the frame, in
+    /// thousandths. So a value of 500 means half of the frame.
+    #[must_use]
+    pub fn average_per_mille(&self) -> u32 {
+        (u64::from(self.average) * 1000 / u64::from(CYCLES_PER_FRAME)) as u32
+    }
+}
+
+impl core::fmt::Display for SectionTiming {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let per_mille = self.average_per_mille();
+
+        write!(
+            f,
+            "{}: min {} avg {} max {} cycles ({}.{}% of frame)",
+            self.name,
+            self.min,
+            self.average,
+            self.max,
+            per_mille / 10,
+            per_mille % 10
+        )
+    }
+}
+
+/// Measures the time spent in [`scope`]s, using the cascaded timers 2 and 3
+/// to count cycles.
+///
+/// Only one `CpuMeter` can be active at once. Scopes entered while there is no
+/// active `CpuMeter` are not recorded.
+pub struct CpuMeter<'a> {
+    low: &'a mut Timer,
+    high: &'a mut Timer,
+
+    frame_start: u32,
+    frame: Stats,
+}
+
+impl<'a> CpuMeter<'a> {
+    /// Starts measuring, taking over timers 2 and 3.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is already an active `CpuMeter`.
+    #[must_use]
+    pub fn new(timers: &'a mut Timers<'_>) -> Self {
+        critical_section::with(|cs| {
+            let mut state = TIMING_STATE.borrow_ref_mut(cs);
+            assert!(state.is_none(), "Cannot have more than one CpuMeter active");
+
+            *state = Some(TimingState {
+                sections: Vec::new(),
+                raster_bars: false,
+            });
+        });
+
+        let low = &mut timers.timer2;
+        let high = &mut timers.timer3;
+
+        high.set_overflow_amount(0)
+            .set_cascade(true)
+            .set_enabled(true);
+        low.set_overflow_amount(0)
+            .set_divider(Divider::Divider1)
+            .set_enabled(true);
+
+        Self {
+            low,
+            high,
+            frame_start: timer::cascaded_timer_value(),
+            frame: Stats::default(),
+        }
+    }
+
+    /// Call this once per frame, usually just before waiting for vblank. Adds
+    /// the time spent in each section since the last call to the statistics.
+    pub fn frame(&mut self) {
+        let now = timer::cascaded_timer_value();
+        self.frame.record(now.wrapping_sub(self.frame_start));
+        self.frame_start = now;
+
+        critical_section::with(|cs| {
+            if let Some(state) = TIMING_STATE.borrow_ref_mut(cs).as_mut() {
+                for section in &mut state.sections {
+                    if section.was_run_this_frame {
+                        section.stats.record(section.this_frame);
+                    }
+
+                    section.this_frame = 0;
+                    section.was_run_this_frame = false;
+                }
+            }
+        });
+    }
+
+    /// The number of frames recorded since the meter was created or last reset.
+    #[must_use]
+    pub fn frames(&self) -> u32 {
+        self.frame.frames
+    }
+
+    /// Timing for the entire frame, measured between calls to [`CpuMeter::frame`].
+    #[must_use]
+    pub fn frame_timing(&self) -> SectionTiming {
+        self.frame.timing("frame")
+    }
+
+    /// Timing for each of the sections which have been entered, in the order
+    /// they were first seen.
+    #[must_use]
+    pub fn sections(&self) -> Vec<SectionTiming> {
+        critical_section::with(|cs| {
+            TIMING_STATE
+                .borrow_ref(cs)
+                .as_ref()
+                .map(|state| {
+                    state
+                        .sections
+                        .iter()
+                        .map(|section| section.stats.timing(section.name))
+                        .collect()
+                })
+                .unwrap_or_default()
+        })
+    }
+
+    /// Timing for a single section, or `None` if it has never been entered.
+    #[must_use]
+    pub fn section(&self, name: &str) -> Option<SectionTiming> {
+        self.sections()
+            .into_iter()
+            .find(|section| section.name == name)
+    }
+
+    /// Clears all the statistics recorded so far.
+    pub fn reset(&mut self) {
+        self.frame = Stats::default();
+        self.frame_start = timer::cascaded_timer_value();
+
+        critical_section::with(|cs| {
+            if let Some(state) = TIMING_STATE.borrow_ref_mut(cs).as_mut() {
+                for section in &mut state.sections {
+                    section.stats = Stats::default();
+                    section.this_frame = 0;
+                    section.was_run_this_frame = false;
+                }
+            }
+        });
+    }
+
+    /// When enabled, the backdrop colour is changed to a colour unique to each
+    /// section while it is running. Any part of the backdrop which is visible
+    /// will then show a bar of that colour on the scanlines which were being
+    /// drawn while that section was running, giving a quick visual indication
+    /// of how much of the frame each section takes.
+    pub fn set_raster_bars(&mut self, enabled: bool) {
+        critical_section::with(|cs| {
+            if let Some(state) = TIMING_STATE.borrow_ref_mut(cs).as_mut() {
+                state.raster_bars = enabled;
+            }
+        });
+    }
+
+    /// Writes the timing for the frame and every section to the emulator's
+    /// [debug output](crate::debug_output).
+    pub fn report(&self) {
+        if debug_output::backend() == Backend::Null {
+            return;
+        }
+
+        let _ = debug_output::print(format_args!("{} frames", self.frames()), DebugLevel::Info);
+        let _ = debug_output::print(format_args!("{}", self.frame_timing()), DebugLevel::Info);
+
+        for section in self.sections() {
+            let _ = debug_output::print(format_args!("{section}"), DebugLevel::Info);
+        }
+    }
+}
+
+impl Drop for CpuMeter<'_> {
+    fn drop(&mut self) {
+        self.low.set_enabled(false);
+        self.high.set_enabled(false).set_cascade(false);
+
+        critical_section::with(|cs| {
+            TIMING_STATE.borrow_ref_mut(cs).take();
+        });
+    }
+}
+
+/// Measures the time until the returned guard is dropped, and adds it to the
+/// section called `name` in the active [`CpuMeter`].
+///
+/// Scopes may be nested, in which case the time for the inner scope is also
+/// included in the outer one. Should not be used from within interrupt handlers.
+pub fn scope(name: &'static str) -> TimingScope {
+    let raster_bar = critical_section::with(|cs| {
+        TIMING_STATE
+            .borrow_ref(cs)
+            .as_ref()
+            .map(|state| state.raster_bars)
+    });
+
+    let Some(raster_bar) = raster_bar else {
+        return TimingScope {
+            name,
+            start: None,
+            previous_backdrop: None,
+        };
+    };
+
+    let previous_backdrop = raster_bar.then(|| {
+        let previous = BACKDROP_COLOUR.get();
+        BACKDROP_COLOUR.set(colour_for_name(name));
+        previous
+    });
+
+    TimingScope {
+        name,
+        start: Some(timer::cascaded_timer_value()),
+        previous_backdrop,
+    }
+}
+
+/// A guard returned by [`scope`] which records the time spent in a section
+/// when it is dropped.
+#[must_use]
+pub struct TimingScope {
+    name: &'static str,
+    start: Option<u32>,
+    previous_backdrop: Option<u16>,
+}
+
+impl Drop for TimingScope {
+    fn drop(&mut self) {
+        let Some(start) = self.start else {
+            return;
+        };
+
+        let elapsed = timer::cascaded_timer_value().wrapping_sub(start);
+
+        if let Some(previous_backdrop) = self.previous_backdrop {
+            BACKDROP_COLOUR.set(previous_backdrop);
+        }
+
+        critical_section::with(|cs| {
+            let mut state = TIMING_STATE.borrow_ref_mut(cs);
+            let Some(state) = state.as_mut() else {
+                return;
+            };
+
+            if let Some(section) = state
+                .sections
+                .iter_mut()
+                .find(|section| section.name == self.name)
+            {
+                section.this_frame += elapsed;
+                section.was_run_this_frame = true;
+            } else {
+                state.sections.push(Section {
+                    name: self.name,
+                    this_frame: elapsed,
+                    was_run_this_frame: true,
+                    stats: Stats::default(),
+                });
+            }
+        });
+    }
+}
+
+/// Picks a bright colour for the raster bar based on the name of the section.
+fn colour_for_name(name: &str) -> u16 {
+    let hash = name.bytes().fold(0x811c_9dc5_u32, |hash, b| {
+        (hash ^ u32::from(b)).wrapping_mul(0x0100_0193)
+    });
+
+    // ensure each component is at least half brightness so it stands out
+    ((hash ^ (hash >> 16)) as u16 & 0x7fff) | 0b0_10000_10000_10000
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test_case]
+    fn scopes_are_recorded_per_frame(gba: &mut crate::Gba) {
+        let mut timers = gba.timers.timers();
+        let mut meter = CpuMeter::new(&mut timers);
+
+        for _ in 0..3 {
+            {
+                let _g = scope("outer");
+                let _g = scope("inner");
+                core::hint::black_box((0..100).sum::<u32>());
+            }
+            meter.frame();
+        }
+
+        let outer = meter
+            .section("outer")
+            .expect("outer should have been recorded");
+        let inner = meter
+            .section("inner")
+            .expect("inner should have been recorded");
+
+        assert_eq!(outer.frames, 3);
+        assert_eq!(meter.frames(), 3);
+        assert!(inner.min > 0);
+        assert!(inner.min <= inner.average && inner.average <= inner.max);
+        assert!(outer.average >= inner.average);
+        assert!(meter.frame_timing().average >= outer.average);
+    }
+
+    #[test_case]
+    fn scopes_without_a_meter_are_ignored(_gba: &mut crate::Gba) {
+        let g = scope("nothing");
+        assert!(g.start.is_none());
+    }
+
+    #[test_case]
+    fn reset_clears_statistics(gba: &mut crate::Gba) {
+        let mut timers = gba.timers.timers();
+        let mut meter = CpuMeter::new(&mut timers);
+
+        {
+            let _g = scope("section");
+        }
+        meter.frame();
+        meter.reset();
+
+        assert_eq!(meter.frames(), 0);
+        assert_eq!(meter.section("section").map(|s| s.frames), Some(0));
+    }
+}
@@ -340,6 +340,7 @@ pub fn parse_midi(midi_info: &MidiInfo) -> Track {
                 .envelope
                 .map(|e| Num::from_f32(envelopes[e].decay))
                 .unwrap_or(0.into()),
+            psg: None,
         })
         .collect();
 
@@ -27,6 +27,22 @@ pub struct Sample {
     pub volume: Num<i16, 8>,
     pub volume_envelope: Option<usize>,
     pub fadeout: Num<i32, 8>,
+    /// Play this sample on one of the GBA's hardware channels rather than the software mixer,
+    /// if the backend supports it
+    pub psg: Option<PsgInstrument>,
+}
+
+/// Which of the hardware sound channels should play a sample. The sample data is still used
+/// to work out the shape and pitch of the sound, so a PSG sample can be played on the software
+/// mixer too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PsgInstrument {
+    /// A square wave with a duty cycle matching the sample
+    Square,
+    /// The sample played on the wave channel, squashed into 32 4-bit samples
+    Wave,
+    /// The noise channel, with the sample's pitch setting the noise frequency
+    Noise,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -104,6 +120,8 @@ pub enum PatternEffect {
     SampleOffset(u16),
     /// Retrigger the note every u8 ticks with the volume change specified
     Retrigger(RetriggerVolumeChange, u8),
+    /// Doesn't change the sound, but is reported to the game so it can sync to the music
+    Marker(u8),
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
@@ -283,12 +301,17 @@ impl quote::ToTokens for Sample {
             volume,
             volume_envelope,
             fadeout,
+            psg,
         } = self;
 
         let volume_envelope = match volume_envelope {
             Some(index) => quote!(Some(#index)),
             None => quote!(None),
         };
+        let psg = match psg {
+            Some(psg) => quote!(Some(#psg)),
+            None => quote!(None),
+        };
         let fadeout = fadeout.to_raw();
 
         let samples = ByteString(data);
@@ -307,6 +330,7 @@ impl quote::ToTokens for Sample {
                     volume: agb_tracker::__private::Num::from_raw(#volume),
                     volume_envelope: #volume_envelope,
                     fadeout: agb_tracker::__private::Num::from_raw(#fadeout),
+                    psg: #psg,
                 }
             }
         });
@@ -432,6 +456,7 @@ impl quote::ToTokens for PatternEffect {
             PatternEffect::Retrigger(retrigger_volume_change, ticks) => {
                 quote! { Retrigger(#retrigger_volume_change, #ticks) }
             }
+            PatternEffect::Marker(value) => quote! { Marker(#value) },
         };
 
         tokens.append_all(quote! {
@@ -456,3 +481,20 @@ impl quote::ToTokens for Waveform {
         });
     }
 }
+
+#[cfg(feature = "quote")]
+impl quote::ToTokens for PsgInstrument {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        use quote::{quote, TokenStreamExt};
+
+        let name = match self {
+            PsgInstrument::Square => quote!(Square),
+            PsgInstrument::Wave => quote!(Wave),
+            PsgInstrument::Noise => quote!(Noise),
+        };
+
+        tokens.append_all(quote! {
+            agb_tracker::__private::agb_tracker_interop::PsgInstrument::#name
+        });
+    }
+}
@@ -1,4 +1,4 @@
-use agb_fixnum::Num;
+use agb_fixnum::{FixedWidthUnsignedInteger, Num};
 
 use std::env;
 use std::fs;
@@ -17,6 +17,9 @@ fn main() {
 
     let saw = (0..64).map(|i| (Num::<i32, 12>::new(i) - 32) / 32);
 
+    // how much faster to play a note to raise it by each number of semitones in an octave
+    let semitones = (0..12).map(|i| Num::<u32, 16>::from_f64(2f64.powf(f64::from(i) / 12.0)));
+
     let out_dir = env::var_os("OUT_DIR").unwrap();
     let dest_path = Path::new(&out_dir).join("lookups.rs");
 
@@ -27,10 +30,12 @@ fn main() {
             pub(crate) static SINE_LOOKUP: [agb_fixnum::Num<i32, 12>; 64] = [{sine_lookup}];
             pub(crate) static SQUARE_LOOKUP: [agb_fixnum::Num<i32, 12>; 64] = [{square_lookup}];
             pub(crate) static SAW_LOOKUP: [agb_fixnum::Num<i32, 12>; 64] = [{saw_lookup}];
+            pub(crate) static SEMITONE_LOOKUP: [agb_fixnum::Num<u32, 16>; 12] = [{semitone_lookup}];
             ",
             sine_lookup = gen_lookup(sine),
             square_lookup = gen_lookup(square),
             saw_lookup = gen_lookup(saw),
+            semitone_lookup = gen_lookup(semitones),
         ),
     )
     .unwrap();
@@ -38,7 +43,9 @@ fn main() {
     println!("cargo::rerun-if-changed=build.rs");
 }
 
-fn gen_lookup(input: impl IntoIterator<Item = Num<i32, 12>>) -> String {
+fn gen_lookup<I: FixedWidthUnsignedInteger + std::fmt::Display, const N: usize>(
+    input: impl IntoIterator<Item = Num<I, N>>,
+) -> String {
     let output: Vec<_> = input
         .into_iter()
         .map(|v| format!("agb_fixnum::Num::from_raw({})", v.to_raw()))
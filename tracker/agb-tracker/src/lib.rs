@@ -62,16 +62,27 @@
 //! In theory, the format the tracker file gets converted into is agnostic to the base format.
 //! Currently, only XM is implemented, however, more formats could be added in future depending
 //! on demand.
+//!
+//! # Hardware channels
+//!
+//! Instruments can be played on the GBA's square, wave and noise channels rather than the
+//! software mixer, which takes almost no CPU time. Start the instrument's name with
+//! `psg:square`, `psg:wave` or `psg:noise` and play the track with a [`PsgTracker`] and
+//! [`PsgMixer`]. Any other instruments in the track still play on the software mixer.
 
 extern crate alloc;
 
 mod lookups;
 mod mixer;
+#[cfg(feature = "agb")]
+mod psg;
 
 use agb_tracker_interop::{Jump, PatternEffect, Sample, Waveform};
-use alloc::vec::Vec;
+use alloc::{boxed::Box, vec::Vec};
 
 pub use mixer::{Mixer, SoundChannel};
+#[cfg(feature = "agb")]
+pub use psg::{PsgChannelId, PsgMixer, PsgSoundChannel};
 
 use agb_fixnum::Num;
 
@@ -120,6 +131,66 @@ pub struct TrackerInner<'track, TChannelId> {
     current_row: usize,
     current_pattern: usize,
     current_jump: Option<Jump>,
+
+    tempo: Num<u32, 8>,
+    volume: Num<i32, 8>,
+    transpose: Num<u32, 16>,
+    solo: Option<usize>,
+
+    should_loop: bool,
+    is_finished: bool,
+    jingle: Option<Jingle<'track, TChannelId>>,
+
+    rows_per_beat: usize,
+    previous_pattern: Option<usize>,
+}
+
+/// Something which happened in the music during [`step_with_events`](TrackerInner::step_with_events),
+/// for keeping the game in time with the music.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TrackerEvent {
+    /// A new pattern started, at `pattern_position` in the track's pattern order.
+    Pattern {
+        /// The position in the track's pattern order
+        pattern_position: usize,
+    },
+    /// A new row started.
+    Row {
+        /// The position in the track's pattern order
+        pattern_position: usize,
+        /// The row within the pattern
+        row: usize,
+    },
+    /// A row at the start of a beat started. See [`set_rows_per_beat`](TrackerInner::set_rows_per_beat).
+    Beat {
+        /// The position in the track's pattern order
+        pattern_position: usize,
+        /// The number of the beat within the pattern, starting at 0
+        beat: usize,
+    },
+    /// A marker effect was reached. In XM files these are `Zxx` effects, where `xx` is `value`.
+    Marker {
+        /// The channel the effect is in
+        channel: usize,
+        /// The effect's parameter
+        value: u8,
+    },
+}
+
+struct Jingle<'track, TChannelId> {
+    tracker: Box<TrackerInner<'track, TChannelId>>,
+    mode: JingleMode,
+}
+
+/// What happens to the main track while a jingle started by
+/// [`play_jingle`](TrackerInner::play_jingle) plays.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JingleMode {
+    /// The main track stops where it is, and carries on from there once the jingle finishes
+    Pause,
+    /// The main track keeps playing underneath the jingle at the given volume
+    Duck(Num<i32, 8>),
 }
 
 #[derive(Default)]
@@ -134,6 +205,7 @@ struct TrackerChannel {
     current_speed: Num<u32, 16>,
     current_panning: Num<i32, 8>,
     is_playing: bool,
+    is_muted: bool,
 
     // if some, should set the current position to this
     current_pos: Option<u16>,
@@ -217,12 +289,210 @@ impl<'track, TChannelId> TrackerInner<'track, TChannelId> {
             current_pattern: 0,
             current_row: 0,
             current_jump: None,
+
+            tempo: 1.into(),
+            volume: 1.into(),
+            transpose: 1.into(),
+            solo: None,
+
+            should_loop: true,
+            is_finished: false,
+            jingle: None,
+
+            rows_per_beat: 4,
+            previous_pattern: None,
+        }
+    }
+
+    /// The position in the track's pattern order which is currently playing.
+    pub fn pattern_position(&self) -> usize {
+        self.current_pattern
+    }
+
+    /// The row of the current pattern which is currently playing.
+    pub fn row(&self) -> usize {
+        self.current_row
+    }
+
+    /// Jumps to `row` of the pattern at `pattern_position` in the track's pattern order. Any notes
+    /// currently playing are stopped, and the new row plays on the next call to [`step`](Self::step).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern_position` or `row` is past the end of the track or pattern.
+    pub fn seek<M: Mixer<ChannelId = TChannelId>>(
+        &mut self,
+        mixer: &mut M,
+        pattern_position: usize,
+        row: usize,
+    ) {
+        assert!(
+            pattern_position < self.track.patterns_to_play.len(),
+            "pattern position must be within the track"
+        );
+        assert!(
+            row < self.track.patterns[self.track.patterns_to_play[pattern_position]].length,
+            "row must be within the pattern"
+        );
+
+        self.stop(mixer);
+
+        for envelope in &mut self.envelopes {
+            *envelope = None;
         }
+
+        self.current_pattern = pattern_position;
+        self.current_row = row;
+        self.current_jump = None;
+
+        self.frame = 0.into();
+        self.tick = 0;
+        self.first = true;
+        self.is_finished = false;
+        self.previous_pattern = None;
+    }
+
+    /// Changes how fast the track plays, where 1 is the track's own tempo and 2 is twice as fast.
+    /// This also affects any tempo changes made by the track itself. The track can't play more
+    /// than one tick per frame, so very high tempos will be capped.
+    pub fn set_tempo(&mut self, tempo: impl Into<Num<u32, 8>>) {
+        self.tempo = tempo.into();
+    }
+
+    /// Sets the volume of the whole track, on top of any volume changes made by the track itself.
+    /// If this makes a channel louder than the mixer can play, it is played as loudly as possible.
+    pub fn set_volume(&mut self, volume: impl Into<Num<i32, 8>>) {
+        self.volume = volume.into();
+    }
+
+    /// Shifts every note in the track up or down by `semitones`, which is clamped to 8 octaves
+    /// (96 semitones) either way.
+    pub fn set_transpose(&mut self, semitones: i32) {
+        // any further and the playback speed no longer fits
+        let semitones = semitones.clamp(-96, 96);
+        let octaves = semitones.div_euclid(12);
+        let ratio = lookups::SEMITONE_LOOKUP[semitones.rem_euclid(12) as usize];
+
+        self.transpose = if octaves >= 0 {
+            Num::from_raw(ratio.to_raw() << octaves)
+        } else {
+            Num::from_raw(ratio.to_raw() >> -octaves)
+        };
+    }
+
+    /// The number of channels in the track, which is the limit for the `channel` passed to
+    /// [`set_channel_muted`](Self::set_channel_muted) and [`set_solo`](Self::set_solo).
+    pub fn num_channels(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Mutes or unmutes one of the track's channels. Muted channels keep playing silently, so
+    /// unmuting them brings them straight back in time with the rest of the track. This is
+    /// useful for adding and removing layers of music as the game changes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `channel` isn't less than [`num_channels`](Self::num_channels).
+    pub fn set_channel_muted(&mut self, channel: usize, muted: bool) {
+        assert!(
+            channel < self.channels.len(),
+            "channel must be within the track"
+        );
+        self.channels[channel].is_muted = muted;
+    }
+
+    /// Mutes every channel apart from `channel`, or turns off the solo if `channel` is `None`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `channel` isn't less than [`num_channels`](Self::num_channels).
+    pub fn set_solo(&mut self, channel: Option<usize>) {
+        assert!(
+            channel.is_none_or(|channel| channel < self.channels.len()),
+            "channel must be within the track"
+        );
+        self.solo = channel;
+    }
+
+    /// Plays `jingle` once, over the top of the current track, which either pauses or plays more
+    /// quietly until the jingle finishes depending on `mode`. Playing another jingle replaces the
+    /// current one.
+    pub fn play_jingle<M: Mixer<ChannelId = TChannelId>>(
+        &mut self,
+        mixer: &mut M,
+        jingle: &'track Track,
+        mode: JingleMode,
+    ) {
+        if let Some(mut previous) = self.jingle.take() {
+            previous.tracker.stop(mixer);
+        }
+
+        if mode == JingleMode::Pause {
+            self.pause(mixer);
+        }
+
+        let mut tracker = Self::new(jingle);
+        tracker.should_loop = false;
+
+        self.jingle = Some(Jingle {
+            tracker: Box::new(tracker),
+            mode,
+        });
+    }
+
+    /// Sets how many rows make up a beat, for the [`TrackerEvent::Beat`] events from
+    /// [`step_with_events`](Self::step_with_events). This defaults to 4.
+    pub fn set_rows_per_beat(&mut self, rows_per_beat: usize) {
+        assert!(rows_per_beat > 0, "a beat must be at least one row");
+        self.rows_per_beat = rows_per_beat;
+    }
+
+    /// Whether a jingle started with [`play_jingle`](Self::play_jingle) is still playing.
+    pub fn is_playing_jingle(&self) -> bool {
+        self.jingle.is_some()
     }
 
     /// Call this once per frame before calling [`mixer.frame`](agb::sound::mixer::Mixer::frame()).
     /// See the [example](crate#example) for how to use the tracker.
     pub fn step<M: Mixer<ChannelId = TChannelId>>(&mut self, mixer: &mut M) {
+        self.step_with_events(mixer, |_| {});
+    }
+
+    /// The same as [`step`](Self::step), but calls `on_event` with anything which happened in the
+    /// music, such as new rows and beats, to let the game keep in time with it. Events from
+    /// jingles aren't reported.
+    ///
+    /// ```rust,no_run
+    /// # #![no_std]
+    /// # #![no_main]
+    /// # use agb_tracker::{Track, Tracker, TrackerEvent};
+    /// # fn foo(mut mixer: agb::sound::mixer::Mixer, track: &'static Track) {
+    /// let mut tracker = Tracker::new(track);
+    /// let mut beats = 0;
+    ///
+    /// tracker.step_with_events(&mut mixer, |event| {
+    ///     if let TrackerEvent::Beat { .. } = event {
+    ///         beats += 1;
+    ///     }
+    /// });
+    /// # }
+    /// ```
+    pub fn step_with_events<M: Mixer<ChannelId = TChannelId>>(
+        &mut self,
+        mixer: &mut M,
+        mut on_event: impl FnMut(TrackerEvent),
+    ) {
+        if let Some(jingle) = &mut self.jingle {
+            jingle.tracker.step(mixer);
+
+            if jingle.tracker.is_finished {
+                jingle.tracker.stop(mixer);
+                self.jingle = None;
+            } else if jingle.mode == JingleMode::Pause {
+                return;
+            }
+        }
+
         if !self.increment_frame() {
             self.update_envelopes();
 
@@ -230,6 +500,14 @@ impl<'track, TChannelId> TrackerInner<'track, TChannelId> {
             return;
         }
 
+        if self.is_finished {
+            return;
+        }
+
+        if self.tick == 0 {
+            self.report_row(&mut on_event);
+        }
+
         let pattern_to_play = self.track.patterns_to_play[self.current_pattern];
         let current_pattern = &self.track.patterns[pattern_to_play];
 
@@ -240,6 +518,17 @@ impl<'track, TChannelId> TrackerInner<'track, TChannelId> {
 
         for (i, (channel, pattern_slot)) in self.channels.iter_mut().zip(pattern_slots).enumerate()
         {
+            if self.tick == 0 {
+                for effect in [&pattern_slot.effect1, &pattern_slot.effect2] {
+                    if let PatternEffect::Marker(value) = effect {
+                        on_event(TrackerEvent::Marker {
+                            channel: i,
+                            value: *value,
+                        });
+                    }
+                }
+            }
+
             if pattern_slot.sample != 0 && self.tick == 0 {
                 let sample = &self.track.samples[pattern_slot.sample as usize - 1];
 
@@ -250,13 +539,7 @@ impl<'track, TChannelId> TrackerInner<'track, TChannelId> {
                     channel.stop();
                 }
 
-                let mut new_channel = M::SoundChannel::new(&sample.data);
-                if sample.should_loop {
-                    new_channel
-                        .should_loop()
-                        .restart_point(sample.restart_point);
-                }
-
+                let new_channel = M::SoundChannel::from_sample(sample);
                 self.mixer_channels[i] = mixer.play_sound(new_channel);
 
                 channel.reset(sample);
@@ -302,6 +585,10 @@ impl<'track, TChannelId> TrackerInner<'track, TChannelId> {
     /// It is expected that you don't call step after this. But doing so will continue from
     /// where you left off. However, notes which were playing won't resume.
     pub fn stop<M: Mixer<ChannelId = TChannelId>>(&mut self, mixer: &mut M) {
+        if let Some(mut jingle) = self.jingle.take() {
+            jingle.tracker.stop(mixer);
+        }
+
         for channel_id in &mut self.mixer_channels {
             if let Some(channel) = channel_id
                 .take()
@@ -312,7 +599,45 @@ impl<'track, TChannelId> TrackerInner<'track, TChannelId> {
         }
     }
 
+    fn report_row(&mut self, on_event: &mut impl FnMut(TrackerEvent)) {
+        let pattern_position = self.current_pattern;
+        let row = self.current_row;
+
+        if row == 0 || self.previous_pattern != Some(pattern_position) {
+            on_event(TrackerEvent::Pattern { pattern_position });
+        }
+        self.previous_pattern = Some(pattern_position);
+
+        on_event(TrackerEvent::Row {
+            pattern_position,
+            row,
+        });
+
+        if row.is_multiple_of(self.rows_per_beat) {
+            on_event(TrackerEvent::Beat {
+                pattern_position,
+                beat: row / self.rows_per_beat,
+            });
+        }
+    }
+
+    fn pause<M: Mixer<ChannelId = TChannelId>>(&mut self, mixer: &mut M) {
+        for channel_id in self.mixer_channels.iter().flatten() {
+            if let Some(channel) = mixer.channel(channel_id) {
+                channel.pause();
+            }
+        }
+    }
+
     fn realise<M: Mixer<ChannelId = TChannelId>>(&mut self, mixer: &mut M) {
+        let volume = match &self.jingle {
+            Some(Jingle {
+                mode: JingleMode::Duck(duck_volume),
+                ..
+            }) => self.volume * *duck_volume,
+            _ => self.volume,
+        };
+
         for (i, (mixer_channel, tracker_channel)) in self
             .mixer_channels
             .iter()
@@ -344,8 +669,21 @@ impl<'track, TChannelId> TrackerInner<'track, TChannelId> {
                     }
                 }
 
+                let is_audible =
+                    !tracker_channel.is_muted && self.solo.is_none_or(|solo| solo == i);
+                let current_volume = if is_audible {
+                    tracker_channel.current_volume * volume
+                } else {
+                    0.into()
+                };
+
+                current_speed *= self.transpose;
+
                 channel.playback(current_speed.change_base());
-                channel.volume(tracker_channel.current_volume.try_change_base().unwrap());
+                // the track's volume can push channels past what the mixer can play
+                channel.volume(Num::<i16, 8>::from_raw(
+                    current_volume.to_raw().clamp(0, i16::MAX.into()) as i16,
+                ));
                 channel.panning(tracker_channel.current_panning.try_change_base().unwrap());
 
                 if let Some(offset) = tracker_channel.current_pos.take() {
@@ -400,11 +738,13 @@ impl<'track, TChannelId> TrackerInner<'track, TChannelId> {
             return true;
         }
 
-        self.frame += 1;
+        self.frame += self.tempo;
 
         if self.frame >= self.global_settings.frames_per_tick {
             self.tick += 1;
             self.frame -= self.global_settings.frames_per_tick;
+            // only one tick can play each frame, so don't fall further behind than that
+            self.frame = self.frame.min(self.global_settings.frames_per_tick);
 
             if self.tick >= self.global_settings.ticks_per_step {
                 if let Some(jump) = self.current_jump.take() {
@@ -421,6 +761,7 @@ impl<'track, TChannelId> TrackerInner<'track, TChannelId> {
 
                         if self.current_pattern >= self.track.patterns_to_play.len() {
                             self.current_pattern = self.track.repeat;
+                            self.is_finished = !self.should_loop;
                         }
                     }
                 }
@@ -451,6 +792,7 @@ impl<'track, TChannelId> TrackerInner<'track, TChannelId> {
         };
         if self.current_pattern >= self.track.patterns_to_play.len() {
             self.current_pattern = self.track.repeat;
+            self.is_finished = !self.should_loop;
         }
         if self.current_row
             >= self.track.patterns[self.track.patterns_to_play[self.current_pattern]].length
@@ -636,6 +978,8 @@ impl TrackerChannel {
                     self.current_pos = Some(0);
                 }
             }
+            // only used by the game, through step_with_events
+            PatternEffect::Marker(_) => {}
         }
     }
 
@@ -735,3 +1079,7 @@ impl Mixer for agb::sound::mixer::Mixer<'_> {
 #[cfg(feature = "agb")]
 /// The type to use if you're using agb-tracker with agb
 pub type Tracker = TrackerInner<'static, agb::sound::mixer::ChannelId>;
+
+#[cfg(feature = "agb")]
+/// The type to use if you're playing some instruments on the hardware channels with a [`PsgMixer`]
+pub type PsgTracker = TrackerInner<'static, PsgChannelId>;
@@ -1,6 +1,7 @@
 #![allow(missing_docs)]
 
 use agb_fixnum::Num;
+use agb_tracker_interop::Sample;
 use alloc::borrow::Cow;
 
 pub trait SoundChannel {
@@ -8,6 +9,20 @@ pub trait SoundChannel {
     #[allow(clippy::ptr_arg)]
     fn new(data: &Cow<'static, [u8]>) -> Self;
 
+    // Backends which play some samples somewhere other than a sample mixer, like the PSG,
+    // can use the rest of the sample's settings to decide how to play it
+    fn from_sample(sample: &Sample) -> Self
+    where
+        Self: Sized,
+    {
+        let mut channel = Self::new(&sample.data);
+        if sample.should_loop {
+            channel.should_loop().restart_point(sample.restart_point);
+        }
+
+        channel
+    }
+
     fn stop(&mut self);
     fn pause(&mut self) -> &mut Self;
     fn resume(&mut self) -> &mut Self;
@@ -0,0 +1,653 @@
+use agb::sound::{
+    dmg::{
+        DmgChannel, DutyCycle, EnvelopeSettings, Sound, SoundDirection, SweepSettings, Wave,
+        WaveBank, WaveBankMode, WaveVolume,
+    },
+    mixer::{self, ChannelId},
+};
+use agb_fixnum::Num;
+use agb_tracker_interop::{PsgInstrument, Sample};
+use alloc::{borrow::Cow, vec::Vec};
+
+use crate::{Mixer, SoundChannel};
+
+const SQUARE_1: usize = 0;
+const SQUARE_2: usize = 1;
+const WAVE: usize = 2;
+const NOISE: usize = 3;
+
+/// A [`Mixer`] which plays samples marked as [`PsgInstrument`]s on the GBA's hardware sound
+/// channels, and everything else on agb's software [mixer](agb::sound::mixer::Mixer). Music
+/// made mostly from PSG instruments then costs very little CPU time to play, leaving the
+/// software mixer free for samples and sound effects.
+///
+/// In an XM file, mark an instrument as a PSG instrument by starting its name with
+/// `psg:square`, `psg:wave` or `psg:noise`. The instrument's sample should be a single cycle
+/// of the waveform, looped, which is used to work out the pitch along with the duty cycle
+/// for square waves and the wave for the wave channel. There are 2 square channels and one
+/// each of wave and noise, so a new note steals the hardware channel from an older one.
+///
+/// Changes to the PSG channels are made in [`frame`](PsgMixer::frame), which should be called
+/// in place of the software mixer's [`frame`](agb::sound::mixer::Mixer::frame).
+///
+/// # Example
+///
+/// ```rust,no_run
+/// #![no_std]
+/// #![no_main]
+///
+/// use agb::{sound::mixer::Frequency, Gba};
+/// use agb_tracker::{include_xm, PsgMixer, PsgTracker, Track};
+///
+/// static CHIPTUNE: Track = include_xm!("examples/db_toffe.xm");
+///
+/// #[agb::entry]
+/// fn main(mut gba: Gba) -> ! {
+///     let vblank_provider = agb::interrupt::VBlank::get();
+///
+///     let mut mixer = gba.mixer.mixer(Frequency::Hz32768);
+///     mixer.enable();
+///     let mut mixer = PsgMixer::new(&mut mixer, &gba.sound);
+///
+///     let mut tracker = PsgTracker::new(&CHIPTUNE);
+///
+///     loop {
+///         tracker.step(&mut mixer);
+///         mixer.frame();
+///
+///         vblank_provider.wait_for_vblank();
+///     }
+/// }
+/// ```
+pub struct PsgMixer<'a, 'gba> {
+    mixer: &'a mut mixer::Mixer<'gba>,
+    sound: &'a Sound,
+
+    channels: Vec<Option<PsgSoundChannel>>,
+    generations: Vec<u32>,
+
+    // which channel is playing on each of the hardware channels
+    hardware: [Option<usize>; 4],
+    notes_started: u32,
+    wave_bank: WaveBank,
+}
+
+/// Identifies a channel played by a [`PsgMixer`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PsgChannelId {
+    index: usize,
+    generation: u32,
+}
+
+/// A sound played by a [`PsgMixer`], either on a hardware channel or the software mixer.
+pub struct PsgSoundChannel {
+    kind: ChannelKind,
+    settings: Settings,
+}
+
+struct Settings {
+    playback: Num<u32, 8>,
+    volume: Num<i16, 8>,
+    panning: Num<i16, 8>,
+    pos: Option<Num<u32, 8>>,
+    is_paused: bool,
+    is_stopped: bool,
+}
+
+enum ChannelKind {
+    Mixer {
+        // the channel is moved into the mixer once it starts playing
+        channel: Option<mixer::SoundChannel>,
+        id: Option<ChannelId>,
+    },
+    Psg(PsgVoice),
+}
+
+struct PsgVoice {
+    instrument: PsgInstrument,
+    cycle_length: u32,
+    duty_cycle: DutyCycle,
+    wave: Wave,
+
+    started: u32,
+    // what was last written to the hardware, or None if it needs starting
+    written: Option<Registers>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Registers {
+    frequency: u16,
+    volume: u8,
+    left: bool,
+    right: bool,
+}
+
+impl<'a, 'gba> PsgMixer<'a, 'gba> {
+    /// Creates a mixer playing through `mixer` and the hardware channels in `sound`, enabling
+    /// the hardware channels.
+    pub fn new(mixer: &'a mut mixer::Mixer<'gba>, sound: &'a Sound) -> Self {
+        sound.enable();
+
+        Self {
+            mixer,
+            sound,
+
+            channels: Vec::new(),
+            generations: Vec::new(),
+
+            hardware: [None; 4],
+            notes_started: 0,
+            wave_bank: WaveBank::Bank0,
+        }
+    }
+
+    /// The software mixer, for playing sound effects alongside the music.
+    pub fn mixer(&mut self) -> &mut mixer::Mixer<'gba> {
+        self.mixer
+    }
+
+    /// Writes any changes to the hardware channels, and then calls the software mixer's
+    /// [`frame`](agb::sound::mixer::Mixer::frame).
+    pub fn frame(&mut self) {
+        for index in 0..self.channels.len() {
+            let Some(channel) = &mut self.channels[index] else {
+                continue;
+            };
+
+            let keep = match &mut channel.kind {
+                ChannelKind::Mixer { id, .. } => {
+                    let id = id
+                        .as_ref()
+                        .expect("mixer channels are only stored once playing");
+                    apply_to_mixer(&mut channel.settings, id, self.mixer)
+                }
+                ChannelKind::Psg(voice) => {
+                    let hardware = self
+                        .hardware
+                        .iter()
+                        .position(|&playing| playing == Some(index))
+                        .expect("psg channels are only stored while on a hardware channel");
+
+                    let keep = apply_to_hardware(
+                        self.sound,
+                        &mut self.wave_bank,
+                        voice,
+                        &channel.settings,
+                        hardware,
+                    );
+                    if !keep {
+                        self.hardware[hardware] = None;
+                    }
+                    keep
+                }
+            };
+
+            if !keep {
+                self.free(index);
+            }
+        }
+
+        self.mixer.frame();
+    }
+
+    fn free(&mut self, index: usize) {
+        self.channels[index] = None;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+    }
+
+    fn store(&mut self, channel: PsgSoundChannel) -> PsgChannelId {
+        let index = match self.channels.iter().position(Option::is_none) {
+            Some(index) => index,
+            None => {
+                self.channels.push(None);
+                self.generations.push(0);
+                self.channels.len() - 1
+            }
+        };
+
+        self.channels[index] = Some(channel);
+
+        PsgChannelId {
+            index,
+            generation: self.generations[index],
+        }
+    }
+
+    fn hardware_for(&self, instrument: PsgInstrument) -> usize {
+        match instrument {
+            PsgInstrument::Square => {
+                let started = |hardware: usize| {
+                    self.hardware[hardware]
+                        .and_then(|index| self.channels[index].as_ref())
+                        .map(|channel| match &channel.kind {
+                            ChannelKind::Psg(voice) => voice.started,
+                            ChannelKind::Mixer { .. } => 0,
+                        })
+                };
+
+                match (started(SQUARE_1), started(SQUARE_2)) {
+                    (None, _) => SQUARE_1,
+                    (_, None) => SQUARE_2,
+                    (Some(first), Some(second)) => {
+                        // steal from whichever note started longest ago
+                        if self.notes_started.wrapping_sub(first)
+                            >= self.notes_started.wrapping_sub(second)
+                        {
+                            SQUARE_1
+                        } else {
+                            SQUARE_2
+                        }
+                    }
+                }
+            }
+            PsgInstrument::Wave => WAVE,
+            PsgInstrument::Noise => NOISE,
+        }
+    }
+}
+
+fn apply_to_mixer(settings: &mut Settings, id: &ChannelId, mixer: &mut mixer::Mixer<'_>) -> bool {
+    let Some(mixer_channel) = mixer.channel(id) else {
+        // the sound has finished
+        return false;
+    };
+
+    if settings.is_stopped {
+        mixer_channel.stop();
+        return false;
+    }
+
+    settings.apply(mixer_channel);
+    true
+}
+
+fn apply_to_hardware(
+    sound: &Sound,
+    wave_bank: &mut WaveBank,
+    voice: &mut PsgVoice,
+    settings: &Settings,
+    hardware: usize,
+) -> bool {
+    if settings.is_stopped {
+        stop_hardware(sound, hardware);
+        return false;
+    }
+
+    let Some(target) = voice.registers(settings) else {
+        if voice.written.take().is_some() {
+            stop_hardware(sound, hardware);
+        }
+        return true;
+    };
+
+    if voice.written.map(|written| (written.left, written.right))
+        != Some((target.left, target.right))
+    {
+        sound.set_channel_output(dmg_channel(hardware), target.left, target.right);
+    }
+
+    match voice.written {
+        Some(written) if written == target => {}
+        Some(written)
+            if voice.instrument != PsgInstrument::Noise && written.volume == target.volume =>
+        {
+            set_hardware_frequency(sound, hardware, target.frequency);
+        }
+        Some(written) if voice.instrument == PsgInstrument::Wave => {
+            sound.channel3().set_volume(wave_volume(target.volume));
+            if written.frequency != target.frequency {
+                set_hardware_frequency(sound, hardware, target.frequency);
+            }
+        }
+        _ => start_hardware(sound, wave_bank, voice, hardware, target),
+    }
+
+    voice.written = Some(target);
+    true
+}
+
+fn start_hardware(
+    sound: &Sound,
+    wave_bank: &mut WaveBank,
+    voice: &PsgVoice,
+    hardware: usize,
+    target: Registers,
+) {
+    let envelope = EnvelopeSettings::new(0, SoundDirection::Increase, target.volume);
+
+    match hardware {
+        SQUARE_1 => sound.channel1().play_sound(
+            target.frequency,
+            None,
+            &SweepSettings::default(),
+            &envelope,
+            voice.duty_cycle,
+        ),
+        SQUARE_2 => {
+            sound
+                .channel2()
+                .play_sound(target.frequency, None, &envelope, voice.duty_cycle);
+        }
+        WAVE => {
+            let channel3 = sound.channel3();
+
+            // load the new wave into the bank that isn't playing, so the old note doesn't glitch
+            *wave_bank = match *wave_bank {
+                WaveBank::Bank0 => WaveBank::Bank1,
+                WaveBank::Bank1 => WaveBank::Bank0,
+            };
+            channel3.load_wave(*wave_bank, &voice.wave);
+            channel3.play_sound(
+                target.frequency,
+                None,
+                wave_volume(target.volume),
+                WaveBankMode::Single(*wave_bank),
+            );
+        }
+        NOISE => {
+            let (shift, divider) = noise_settings(target.frequency);
+            sound
+                .noise()
+                .play_sound(None, &envelope, divider, true, shift);
+        }
+        _ => unreachable!("there are only 4 hardware channels"),
+    }
+}
+
+impl Mixer for PsgMixer<'_, '_> {
+    type ChannelId = PsgChannelId;
+    type SoundChannel = PsgSoundChannel;
+
+    fn channel(&mut self, channel_id: &Self::ChannelId) -> Option<&mut Self::SoundChannel> {
+        if self.generations.get(channel_id.index) != Some(&channel_id.generation) {
+            return None;
+        }
+
+        self.channels[channel_id.index].as_mut()
+    }
+
+    fn play_sound(&mut self, mut channel: Self::SoundChannel) -> Option<Self::ChannelId> {
+        match &mut channel.kind {
+            ChannelKind::Mixer {
+                channel: mixer_channel,
+                id,
+            } => {
+                let mut mixer_channel = mixer_channel.take()?;
+                channel.settings.apply(&mut mixer_channel);
+                *id = Some(self.mixer.play_sound(mixer_channel)?);
+
+                Some(self.store(channel))
+            }
+            ChannelKind::Psg(voice) => {
+                let hardware = self.hardware_for(voice.instrument);
+
+                // the new note takes over the hardware channel, so the old one can't be changed
+                if let Some(previous) = self.hardware[hardware].take() {
+                    self.free(previous);
+                }
+
+                voice.started = self.notes_started;
+                self.notes_started = self.notes_started.wrapping_add(1);
+
+                let id = self.store(channel);
+                self.hardware[hardware] = Some(id.index);
+                Some(id)
+            }
+        }
+    }
+}
+
+impl PsgSoundChannel {
+    fn with_kind(kind: ChannelKind) -> Self {
+        Self {
+            kind,
+            settings: Settings {
+                playback: 1.into(),
+                volume: 1.into(),
+                panning: 0.into(),
+                pos: None,
+                is_paused: false,
+                is_stopped: false,
+            },
+        }
+    }
+}
+
+impl Settings {
+    fn apply(&mut self, channel: &mut mixer::SoundChannel) {
+        channel
+            .playback(self.playback)
+            .volume(self.volume)
+            .panning(self.panning);
+
+        if let Some(pos) = self.pos.take() {
+            channel.set_pos(pos);
+        }
+
+        if self.is_paused {
+            channel.pause();
+        } else {
+            channel.resume();
+        }
+    }
+}
+
+impl PsgVoice {
+    // None if the channel should be silent
+    fn registers(&self, settings: &Settings) -> Option<Registers> {
+        let volume = ((i32::from(settings.volume.to_raw().max(0)) * 15 + 128) >> 8).min(15) as u8;
+        let speed = settings.playback.to_raw();
+        if settings.is_paused || volume == 0 || speed == 0 {
+            return None;
+        }
+
+        let cycle_length = self.cycle_length;
+        let frequency = match self.instrument {
+            // the square channels play 8 steps per cycle at 131072Hz / (2048 - frequency), and the
+            // tracker's speeds are relative to 32768Hz
+            PsgInstrument::Square => {
+                2048 - (cycle_length.saturating_mul(1024) / speed).clamp(1, 2048)
+            }
+            // the wave channel plays 32 samples per cycle at 2097152Hz / (2048 - frequency)
+            PsgInstrument::Wave => 2048 - (cycle_length.saturating_mul(512) / speed).clamp(1, 2048),
+            // the noise channel doesn't have a cycle, so keep the speed for noise_settings
+            PsgInstrument::Noise => speed.min(u32::from(u16::MAX)),
+        } as u16;
+
+        Some(Registers {
+            frequency,
+            volume,
+            left: settings.panning < Num::from_raw(128),
+            right: settings.panning > Num::from_raw(-128),
+        })
+    }
+}
+
+impl SoundChannel for PsgSoundChannel {
+    fn new(data: &Cow<'static, [u8]>) -> Self {
+        Self::with_kind(ChannelKind::Mixer {
+            channel: Some(<mixer::SoundChannel as SoundChannel>::new(data)),
+            id: None,
+        })
+    }
+
+    fn from_sample(sample: &Sample) -> Self {
+        let Some(instrument) = sample.psg else {
+            let mut channel = <Self as SoundChannel>::new(&sample.data);
+            if sample.should_loop {
+                channel.should_loop().restart_point(sample.restart_point);
+            }
+            return channel;
+        };
+
+        let start = if sample.should_loop {
+            (sample.restart_point as usize).min(sample.data.len())
+        } else {
+            0
+        };
+        let cycle: &[u8] = match &sample.data[start..] {
+            [] => &[0],
+            cycle => cycle,
+        };
+
+        Self::with_kind(ChannelKind::Psg(PsgVoice {
+            instrument,
+            cycle_length: cycle.len() as u32,
+            duty_cycle: duty_cycle(cycle),
+            wave: wave(cycle),
+
+            started: 0,
+            written: None,
+        }))
+    }
+
+    fn stop(&mut self) {
+        self.settings.is_stopped = true;
+    }
+
+    fn pause(&mut self) -> &mut Self {
+        self.settings.is_paused = true;
+        self
+    }
+
+    fn resume(&mut self) -> &mut Self {
+        self.settings.is_paused = false;
+        self
+    }
+
+    fn should_loop(&mut self) -> &mut Self {
+        if let ChannelKind::Mixer {
+            channel: Some(channel),
+            ..
+        } = &mut self.kind
+        {
+            channel.should_loop();
+        }
+        self
+    }
+
+    fn volume(&mut self, value: impl Into<Num<i16, 8>>) -> &mut Self {
+        self.settings.volume = value.into();
+        self
+    }
+
+    fn restart_point(&mut self, value: impl Into<Num<u32, 8>>) -> &mut Self {
+        if let ChannelKind::Mixer {
+            channel: Some(channel),
+            ..
+        } = &mut self.kind
+        {
+            channel.restart_point(value);
+        }
+        self
+    }
+
+    fn playback(&mut self, playback_speed: impl Into<Num<u32, 8>>) -> &mut Self {
+        self.settings.playback = playback_speed.into();
+        self
+    }
+
+    fn panning(&mut self, panning: impl Into<Num<i16, 8>>) -> &mut Self {
+        self.settings.panning = panning.into();
+        self
+    }
+
+    fn set_pos(&mut self, pos: impl Into<Num<u32, 8>>) -> &mut Self {
+        self.settings.pos = Some(pos.into());
+        self
+    }
+}
+
+fn dmg_channel(hardware: usize) -> DmgChannel {
+    match hardware {
+        SQUARE_1 => DmgChannel::Channel1,
+        SQUARE_2 => DmgChannel::Channel2,
+        WAVE => DmgChannel::Channel3,
+        _ => DmgChannel::Noise,
+    }
+}
+
+fn stop_hardware(sound: &Sound, hardware: usize) {
+    match hardware {
+        SQUARE_1 => sound.channel1().stop(),
+        SQUARE_2 => sound.channel2().stop(),
+        WAVE => sound.channel3().stop(),
+        _ => sound.noise().stop(),
+    }
+}
+
+fn set_hardware_frequency(sound: &Sound, hardware: usize, frequency: u16) {
+    match hardware {
+        SQUARE_1 => sound.channel1().set_frequency(frequency),
+        SQUARE_2 => sound.channel2().set_frequency(frequency),
+        WAVE => sound.channel3().set_frequency(frequency),
+        _ => unreachable!("the noise channel is restarted to change frequency"),
+    }
+}
+
+fn wave_volume(volume: u8) -> WaveVolume {
+    match volume {
+        0 => WaveVolume::Mute,
+        1..=5 => WaveVolume::Quarter,
+        6..=9 => WaveVolume::Half,
+        10..=13 => WaveVolume::ThreeQuarters,
+        _ => WaveVolume::Full,
+    }
+}
+
+/// Picks the square wave duty cycle closest to the fraction of the cycle which is positive.
+fn duty_cycle(cycle: &[u8]) -> DutyCycle {
+    let high = cycle.iter().filter(|&&sample| sample as i8 >= 0).count();
+    let eighths = high * 8 / cycle.len();
+
+    match eighths {
+        0..=1 => DutyCycle::OneEighth,
+        2..=3 => DutyCycle::OneQuarter,
+        4..=5 => DutyCycle::Half,
+        _ => DutyCycle::ThreeQuarters,
+    }
+}
+
+/// Squashes the cycle into the 32 4-bit samples the wave channel plays.
+fn wave(cycle: &[u8]) -> Wave {
+    let nibble = |i: usize| {
+        let sample = cycle[i * cycle.len() / 32] as i8;
+        ((i16::from(sample) + 128) >> 4) as u8
+    };
+
+    let mut wave = [0; 16];
+    for (i, byte) in wave.iter_mut().enumerate() {
+        *byte = (nibble(i * 2) << 4) | nibble(i * 2 + 1);
+    }
+
+    wave
+}
+
+/// Finds the noise channel's shift clock frequency and divider closest to the tracker's speed.
+/// The noise channel is clocked at 524288Hz / divider / 2^(shift + 1) where a divider of 0
+/// counts as 0.5, and a speed of 1 means 32768Hz.
+fn noise_settings(speed: u16) -> (u8, u8) {
+    // twice the ideal divider * 2^(shift + 1), so a divider of 0.5 is a whole number
+    let target = 32 * 256 / u32::from(speed.max(1));
+
+    let mut best = (0, 0);
+    let mut best_distance = u32::MAX;
+
+    for shift in 0..14 {
+        for divider in 0..8 {
+            let value: u32 = if divider == 0 {
+                1 << (shift + 1)
+            } else {
+                divider << (shift + 2)
+            };
+
+            let distance = value.abs_diff(target);
+            if distance < best_distance {
+                best = (shift as u8, divider as u8);
+                best_distance = distance;
+            }
+        }
+    }
+
+    best
+}
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use agb_fixnum::Num;
-use agb_tracker_interop::{Jump, PatternEffect, RetriggerVolumeChange, Waveform};
+use agb_tracker_interop::{Jump, PatternEffect, PsgInstrument, RetriggerVolumeChange, Waveform};
 
 use xmrs::prelude::*;
 
@@ -18,6 +18,7 @@ pub fn parse_module(module: &Module) -> agb_tracker_interop::Track {
         volume: Num<i16, 8>,
         envelope_id: Option<usize>,
         fadeout: Num<i32, 8>,
+        psg: Option<PsgInstrument>,
     }
 
     let mut samples = vec![];
@@ -25,6 +26,8 @@ pub fn parse_module(module: &Module) -> agb_tracker_interop::Track {
     let mut existing_envelopes: HashMap<EnvelopeData, usize> = Default::default();
 
     for (instrument_index, instrument) in instruments.iter().enumerate() {
+        let psg = psg_instrument(&instrument.name);
+
         let InstrumentType::Default(ref instrument) = instrument.instr_type else {
             continue;
         };
@@ -88,6 +91,7 @@ pub fn parse_module(module: &Module) -> agb_tracker_interop::Track {
                 volume,
                 envelope_id,
                 fadeout,
+                psg,
             });
         }
     }
@@ -498,6 +502,8 @@ pub fn parse_module(module: &Module) -> agb_tracker_interop::Track {
 
                         PatternEffect::Retrigger(volume_type, ticks_between_retriggers)
                     }
+                    // Z, which isn't used by anything else so is free for games to sync to
+                    0x23 => PatternEffect::Marker(slot.effect_parameter),
                     e => {
                         let effect_char = char::from_digit(e as u32, 36)
                             .unwrap_or('?')
@@ -563,6 +569,7 @@ pub fn parse_module(module: &Module) -> agb_tracker_interop::Track {
             volume: sample.volume,
             volume_envelope: sample.envelope_id,
             fadeout: sample.fadeout,
+            psg: sample.psg,
         })
         .collect();
 
@@ -604,6 +611,23 @@ fn bpm_to_frames_per_tick(bpm: u32) -> Num<u32, 8> {
     Num::<u32, 8>::new(150) / bpm
 }
 
+/// Instruments can ask to be played on the hardware channels by starting their name with
+/// `psg:square`, `psg:wave` or `psg:noise`.
+fn psg_instrument(name: &str) -> Option<PsgInstrument> {
+    let name = name.trim().to_ascii_lowercase();
+    let kind = name.strip_prefix("psg:")?;
+
+    if kind.starts_with("square") {
+        Some(PsgInstrument::Square)
+    } else if kind.starts_with("wave") {
+        Some(PsgInstrument::Wave)
+    } else if kind.starts_with("noise") {
+        Some(PsgInstrument::Noise)
+    } else {
+        None
+    }
+}
+
 fn note_to_speed(
     note: Note,
     fine_tune: f64,